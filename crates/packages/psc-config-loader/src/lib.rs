@@ -3,22 +3,57 @@
 
 //! A library for loading and resolving secrets in configuration files.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use psc_secrets::{SecretError, SecretManager};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Vault secrets fetched while resolving one configuration, keyed by path, so each
+/// path is fetched once however many of its keys are referenced. `None` marks a path
+/// whose keys the secret manager cannot list, so they are looked up one at a time.
+type FetchedSecrets = HashMap<String, Option<HashMap<String, String>>>;
+
+/// Error returned when a secret reference in a configuration cannot be resolved.
+#[derive(thiserror::Error, Debug)]
+#[error("Failed to resolve secret at '{path}': {source}")]
+pub struct SecretResolutionError {
+    /// Dotted path of the configuration field holding the reference.
+    pub path: String,
+    /// The underlying secret resolution error.
+    #[source]
+    pub source: SecretError,
+}
 
 /// A loader for configuration files that can resolve secrets from a secret manager.
 pub struct ConfigLoader<S: SecretManager> {
     secret_manager: S,
+    max_depth: usize,
 }
 
 impl<S: SecretManager> ConfigLoader<S> {
+    /// Default for [`ConfigLoader::with_max_depth`].
+    pub const DEFAULT_MAX_DEPTH: usize = 64;
+
     /// Creates a new `ConfigLoader` with the given secret manager.
     pub fn new(secret_manager: S) -> Self {
-        Self { secret_manager }
+        Self {
+            secret_manager,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Sets how deeply objects and arrays may be nested in a configuration.
+    ///
+    /// Deeper configurations fail with `SecretError::MaxDepthExceeded` instead of
+    /// recursing further. Defaults to [`ConfigLoader::DEFAULT_MAX_DEPTH`].
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
     }
 
     /// Loads a configuration from the given source and resolves any secrets within it.
@@ -26,45 +61,98 @@ impl<S: SecretManager> ConfigLoader<S> {
     /// # Arguments
     ///
     /// * `source` - A string containing the configuration in a format that can be deserialized
-    ///              into a `serde_json::Value`.
+    ///   into a `serde_json::Value`.
     ///
     /// # Returns
     ///
     /// A deserialized configuration of type `T` with all secrets resolved, or an error if
     /// loading or secret resolution fails.
     pub async fn load_and_resolve<T: DeserializeOwned>(&self, source: &str) -> Result<T> {
-        let mut config_value: Value = serde_json::from_str(source)?;
-        self.resolve_secrets(&mut config_value).await?;
+        let config_value: Value = serde_json::from_str(source)?;
+        self.resolve_value(config_value).await
+    }
+
+    /// Loads a configuration file and resolves any secrets within it.
+    ///
+    /// The format is detected from the file extension: `.json`, `.yaml`/`.yml` or
+    /// `.toml`. Any other extension is an error.
+    pub async fn load_and_resolve_file<T: DeserializeOwned>(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<T> {
+        let path = path.as_ref();
+        let format = file_format(path)?;
+        let source = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read configuration file {}", path.display()))?;
+        let config_value = format
+            .parse(&source)
+            .with_context(|| format!("Failed to parse configuration file {}", path.display()))?;
+        self.resolve_value(config_value).await
+    }
+
+    /// Resolves the secrets in a parsed configuration and deserializes it into `T`.
+    async fn resolve_value<T: DeserializeOwned>(&self, mut config_value: Value) -> Result<T> {
+        self.resolve_secrets(
+            &mut config_value,
+            String::new(),
+            0,
+            &mut FetchedSecrets::new(),
+        )
+        .await?;
         let config: T = serde_json::from_value(config_value)?;
         Ok(config)
     }
 
     /// Recursively traverses a `serde_json::Value` and resolves any secret paths.
+    ///
+    /// `path` is the dotted location of `value` within the configuration (e.g.
+    /// `database.credentials.password`) and is reported when resolution fails.
+    /// `depth` is the number of objects and arrays enclosing `value`.
     fn resolve_secrets<'a>(
         &'a self,
         value: &'a mut Value,
-    ) -> BoxFuture<'a, Result<(), SecretError>> {
+        path: String,
+        depth: usize,
+        fetched: &'a mut FetchedSecrets,
+    ) -> BoxFuture<'a, Result<(), SecretResolutionError>> {
         async move {
+            if depth > self.max_depth {
+                return Err(SecretResolutionError {
+                    path,
+                    source: SecretError::MaxDepthExceeded(self.max_depth),
+                });
+            }
             match value {
                 Value::Object(map) => {
-                    for (_key, val) in map.iter_mut() {
-                        self.resolve_secrets(val).await?;
+                    for (key, val) in map.iter_mut() {
+                        let child_path = if path.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{}.{}", path, key)
+                        };
+                        self.resolve_secrets(val, child_path, depth + 1, fetched)
+                            .await?;
                     }
                 }
                 Value::Array(arr) => {
-                    for val in arr.iter_mut() {
-                        self.resolve_secrets(val).await?;
+                    for (index, val) in arr.iter_mut().enumerate() {
+                        self.resolve_secrets(
+                            val,
+                            format!("{}[{}]", path, index),
+                            depth + 1,
+                            fetched,
+                        )
+                        .await?;
                     }
                 }
                 Value::String(s) => {
-                    if let Some(secret_path) = s.strip_prefix("vault://") {
-                        let parts: Vec<&str> = secret_path.splitn(2, ':').collect();
-                        if parts.len() == 2 {
-                            let path = parts[0];
-                            let key = parts[1];
-                            let secret_value = self.secret_manager.get_secret(path, key).await?;
-                            *s = secret_value;
-                        }
+                    let resolved = self
+                        .resolve_reference(s, fetched)
+                        .await
+                        .map_err(|source| SecretResolutionError { path, source })?;
+                    if let Some(resolved) = resolved {
+                        *s = resolved;
                     }
                 }
                 _ => {}
@@ -73,4 +161,558 @@ impl<S: SecretManager> ConfigLoader<S> {
         }
         .boxed()
     }
+
+    /// Resolves a single string value if it is a secret reference.
+    ///
+    /// Supported references:
+    ///
+    /// * `vault://path:key` - resolved through the configured secret manager. All keys
+    ///   at `path` are fetched together and kept in `fetched` for later references,
+    ///   unless the secret manager cannot list them, in which case `key` is looked up
+    ///   on its own.
+    /// * `vault://path:key|default` - as above, falling back to `default` when the
+    ///   secret does not exist. Other secret manager errors are still returned.
+    /// * `env://VAR_NAME` - resolved from the process environment.
+    ///
+    /// Returns `None` if the value is not a secret reference and should be left untouched,
+    /// and `SecretError::InvalidSecretData` for a `vault://` reference without a key.
+    async fn resolve_reference(
+        &self,
+        reference: &str,
+        fetched: &mut FetchedSecrets,
+    ) -> Result<Option<String>, SecretError> {
+        if let Some(secret_path) = reference.strip_prefix("vault://") {
+            let (path, key) = secret_path.split_once(':').ok_or_else(|| {
+                SecretError::InvalidSecretData(format!(
+                    "Secret reference '{}' is missing a ':key'",
+                    reference
+                ))
+            })?;
+            let (key, default) = match key.split_once('|') {
+                Some((key, default)) => (key, Some(default)),
+                None => (key, None),
+            };
+            let listed = match fetched.entry(path.to_string()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let listed = match self.secret_manager.get_all(path).await {
+                        Ok(secrets) => Some(secrets),
+                        // Nothing stored at the path, so every key falls back to its default
+                        Err(SecretError::SecretNotFound { .. }) => Some(HashMap::new()),
+                        Err(SecretError::ListingNotSupported(_)) => None,
+                        Err(e) => return Err(e),
+                    };
+                    entry.insert(listed)
+                }
+            };
+            let secret_value = match listed {
+                Some(secrets) => secrets.get(key).cloned(),
+                None => match self.secret_manager.get_secret(path, key).await {
+                    Ok(secret_value) => Some(secret_value),
+                    Err(SecretError::SecretNotFound { .. }) => None,
+                    Err(e) => return Err(e),
+                },
+            };
+            let secret_value = match (secret_value, default) {
+                (Some(secret_value), _) => secret_value,
+                (None, Some(default)) => default.to_string(),
+                (None, None) => {
+                    return Err(SecretError::SecretNotFound {
+                        path: path.to_string(),
+                        key: key.to_string(),
+                    })
+                }
+            };
+            return Ok(Some(secret_value));
+        } else if let Some(var_name) = reference.strip_prefix("env://") {
+            let env_value = std::env::var(var_name)
+                .map_err(|_| SecretError::EnvVarNotSet(var_name.to_string()))?;
+            return Ok(Some(env_value));
+        }
+        Ok(None)
+    }
+}
+
+/// Formats accepted by [`ConfigLoader::load_and_resolve_file`].
+#[derive(Debug, Clone, Copy)]
+enum FileFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl FileFormat {
+    /// Parses `source` as this format, keeping keys exactly as written.
+    fn parse(self, source: &str) -> Result<Value> {
+        let value = match self {
+            FileFormat::Json => serde_json::from_str(source)?,
+            FileFormat::Yaml => serde_yaml::from_str(source)?,
+            FileFormat::Toml => toml::from_str(source)?,
+        };
+        Ok(value)
+    }
+}
+
+/// Detects the format of a configuration file from its extension.
+fn file_format(path: &Path) -> Result<FileFormat> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") => Ok(FileFormat::Json),
+        Some("yaml") | Some("yml") => Ok(FileFormat::Yaml),
+        Some("toml") => Ok(FileFormat::Toml),
+        _ => anyhow::bail!(
+            "Unsupported configuration file {}: expected a .json, .yaml, .yml or .toml extension",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use psc_secrets::{VaultConfig, VaultSecretManager};
+    use serde::Deserialize;
+
+    struct StaticSecretManager {
+        secrets: HashMap<(String, String), String>,
+    }
+
+    impl StaticSecretManager {
+        fn new(secrets: &[(&str, &str, &str)]) -> Self {
+            Self {
+                secrets: secrets
+                    .iter()
+                    .map(|(path, key, value)| {
+                        ((path.to_string(), key.to_string()), value.to_string())
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl SecretManager for StaticSecretManager {
+        async fn get_secret(&self, path: &str, key: &str) -> Result<String, SecretError> {
+            self.secrets
+                .get(&(path.to_string(), key.to_string()))
+                .cloned()
+                .ok_or_else(|| SecretError::SecretNotFound {
+                    path: path.to_string(),
+                    key: key.to_string(),
+                })
+        }
+
+        async fn get_all(&self, path: &str) -> Result<HashMap<String, String>, SecretError> {
+            Ok(self
+                .secrets
+                .iter()
+                .filter(|((secret_path, _), _)| secret_path == path)
+                .map(|((_, key), value)| (key.clone(), value.clone()))
+                .collect())
+        }
+    }
+
+    /// Looks keys up one at a time, without listing the keys at a path.
+    struct PerKeySecretManager(StaticSecretManager);
+
+    #[async_trait]
+    impl SecretManager for PerKeySecretManager {
+        async fn get_secret(&self, path: &str, key: &str) -> Result<String, SecretError> {
+            self.0.get_secret(path, key).await
+        }
+    }
+
+    struct UnavailableSecretManager;
+
+    #[async_trait]
+    impl SecretManager for UnavailableSecretManager {
+        async fn get_secret(&self, _path: &str, _key: &str) -> Result<String, SecretError> {
+            Err(SecretError::VaultApi("connection refused".to_string()))
+        }
+
+        async fn get_all(&self, _path: &str) -> Result<HashMap<String, String>, SecretError> {
+            Err(SecretError::VaultApi("connection refused".to_string()))
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct DatabaseConfig {
+        user: String,
+        password: String,
+    }
+
+    #[tokio::test]
+    async fn test_env_secret_resolved() {
+        std::env::set_var("PSC_CONFIG_LOADER_TEST_USER", "admin");
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[]));
+
+        let config: DatabaseConfig = loader
+            .load_and_resolve(
+                r#"{"user": "env://PSC_CONFIG_LOADER_TEST_USER", "password": "plain"}"#,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(config.user, "admin");
+        assert_eq!(config.password, "plain");
+    }
+
+    #[tokio::test]
+    async fn test_env_secret_missing() {
+        std::env::remove_var("PSC_CONFIG_LOADER_TEST_MISSING");
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[]));
+
+        let result = loader
+            .load_and_resolve::<DatabaseConfig>(
+                r#"{"user": "env://PSC_CONFIG_LOADER_TEST_MISSING", "password": "plain"}"#,
+            )
+            .await;
+
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<SecretResolutionError>().unwrap();
+        assert_eq!(err.path, "user");
+        assert!(matches!(
+            &err.source,
+            SecretError::EnvVarNotSet(name) if name == "PSC_CONFIG_LOADER_TEST_MISSING"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_env_and_vault_secrets_mixed() {
+        std::env::set_var("PSC_CONFIG_LOADER_TEST_MIXED_USER", "service");
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[("db", "password", "s3cr3t")]));
+
+        let config: DatabaseConfig = loader
+            .load_and_resolve(
+                r#"{"user": "env://PSC_CONFIG_LOADER_TEST_MIXED_USER", "password": "vault://db:password"}"#,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            config,
+            DatabaseConfig {
+                user: "service".to_string(),
+                password: "s3cr3t".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vault_secret_default_ignored_when_resolvable() {
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[
+            ("db", "user", "admin"),
+            ("db", "password", "s3cr3t"),
+        ]));
+
+        let config: DatabaseConfig = loader
+            .load_and_resolve(
+                r#"{"user": "vault://db:user|guest", "password": "vault://db:password"}"#,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(config.user, "admin");
+    }
+
+    #[tokio::test]
+    async fn test_vault_secret_default_used_when_missing() {
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[("db", "password", "s3cr3t")]));
+
+        let config: DatabaseConfig = loader
+            .load_and_resolve(
+                r#"{"user": "vault://db:user|guest", "password": "vault://db:password"}"#,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(config.user, "guest");
+        assert_eq!(config.password, "s3cr3t");
+    }
+
+    #[tokio::test]
+    async fn test_vault_secret_resolved_without_listing() {
+        let loader = ConfigLoader::new(PerKeySecretManager(StaticSecretManager::new(&[(
+            "db", "password", "s3cr3t",
+        )])));
+
+        let config: DatabaseConfig = loader
+            .load_and_resolve(
+                r#"{"user": "vault://db:user|guest", "password": "vault://db:password"}"#,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(config.user, "guest");
+        assert_eq!(config.password, "s3cr3t");
+    }
+
+    #[tokio::test]
+    async fn test_vault_secret_default_does_not_mask_other_errors() {
+        let loader = ConfigLoader::new(UnavailableSecretManager);
+
+        let result = loader
+            .load_and_resolve::<DatabaseConfig>(
+                r#"{"user": "vault://db:user|guest", "password": "plain"}"#,
+            )
+            .await;
+
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<SecretResolutionError>().unwrap();
+        assert!(matches!(err.source, SecretError::VaultApi(_)));
+    }
+
+    #[tokio::test]
+    async fn test_error_reports_nested_secret_path() {
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[]));
+
+        let result = loader
+            .load_and_resolve::<Value>(
+                r#"{"database": {"credentials": {"user": "admin", "password": "vault://db:password"}}}"#,
+            )
+            .await;
+
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("database.credentials.password"));
+        let err = err.downcast_ref::<SecretResolutionError>().unwrap();
+        assert_eq!(err.path, "database.credentials.password");
+        assert!(matches!(err.source, SecretError::SecretNotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_vault_secrets_in_array_of_objects_resolved() {
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[
+            ("psp/mtn", "api_key", "mtn-key"),
+            ("psp/orange", "api_key", "orange-key"),
+        ]));
+
+        let config: Value = loader
+            .load_and_resolve(
+                r#"{"providers": [
+                    {"name": "mtn", "api_key": "vault://psp/mtn:api_key"},
+                    {"name": "orange", "credentials": {"api_key": "vault://psp/orange:api_key"}}
+                ]}"#,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(config["providers"][0]["api_key"], "mtn-key");
+        assert_eq!(
+            config["providers"][1]["credentials"]["api_key"],
+            "orange-key"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vault_reference_without_key_is_rejected() {
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[("db", "password", "s3cr3t")]));
+
+        let result = loader
+            .load_and_resolve::<Value>(r#"{"providers": [{"api_key": "vault://psp/mtn"}]}"#)
+            .await;
+
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<SecretResolutionError>().unwrap();
+        assert_eq!(err.path, "providers[0].api_key");
+        assert!(matches!(err.source, SecretError::InvalidSecretData(_)));
+    }
+
+    #[tokio::test]
+    async fn test_nesting_beyond_max_depth_is_rejected() {
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[])).with_max_depth(8);
+        let nested = |levels: usize| {
+            format!(
+                "{}\"plain\"{}",
+                r#"{"a": "#.repeat(levels),
+                "}".repeat(levels)
+            )
+        };
+
+        let result = loader.load_and_resolve::<Value>(&nested(8)).await;
+        assert!(result.is_ok());
+
+        let result = loader.load_and_resolve::<Value>(&nested(9)).await;
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<SecretResolutionError>().unwrap();
+        assert!(matches!(err.source, SecretError::MaxDepthExceeded(8)));
+    }
+
+    #[tokio::test]
+    async fn test_keys_under_one_vault_path_are_fetched_once() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/db"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "data": { "user": "admin", "password": "s3cr3t" } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let loader = ConfigLoader::new(VaultSecretManager::new(VaultConfig {
+            addr: format!("{}/v1/", server.uri()).parse().unwrap(),
+            token: Some("token".to_string()),
+            mount_path: "secret".to_string(),
+        }));
+
+        let config: DatabaseConfig = loader
+            .load_and_resolve(r#"{"user": "vault://db:user", "password": "vault://db:password"}"#)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            config,
+            DatabaseConfig {
+                user: "admin".to_string(),
+                password: "s3cr3t".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vault_secret_default_used_when_path_missing() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/db"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+                "errors": []
+            })))
+            .mount(&server)
+            .await;
+        let loader = ConfigLoader::new(VaultSecretManager::new(VaultConfig {
+            addr: format!("{}/v1/", server.uri()).parse().unwrap(),
+            token: Some("token".to_string()),
+            mount_path: "secret".to_string(),
+        }));
+
+        let config: DatabaseConfig = loader
+            .load_and_resolve(r#"{"user": "vault://db:user|guest", "password": "plain"}"#)
+            .await
+            .unwrap();
+        assert_eq!(config.user, "guest");
+
+        let result = loader
+            .load_and_resolve::<DatabaseConfig>(
+                r#"{"user": "vault://db:user", "password": "plain"}"#,
+            )
+            .await;
+        let err = result.unwrap_err();
+        let err = err.downcast_ref::<SecretResolutionError>().unwrap();
+        assert!(matches!(
+            err.source,
+            SecretError::SecretNotFound { ref key, .. } if key == "user"
+        ));
+    }
+
+    fn write_config(dir: &tempfile::TempDir, name: &str, contents: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_yaml_file_with_vault_secret_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "database.yaml",
+            "user: admin\npassword: \"vault://db:password\"\n",
+        );
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[("db", "password", "s3cr3t")]));
+
+        let config: DatabaseConfig = loader.load_and_resolve_file(&path).await.unwrap();
+
+        assert_eq!(
+            config,
+            DatabaseConfig {
+                user: "admin".to_string(),
+                password: "s3cr3t".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_toml_file_without_secrets_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "database.toml",
+            "user = \"admin\"\npassword = \"plain\"\n",
+        );
+        let loader = ConfigLoader::new(UnavailableSecretManager);
+
+        let config: DatabaseConfig = loader.load_and_resolve_file(&path).await.unwrap();
+
+        assert_eq!(
+            config,
+            DatabaseConfig {
+                user: "admin".to_string(),
+                password: "plain".to_string(),
+            }
+        );
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct ProviderConfig {
+        base_url: String,
+        api_key: String,
+    }
+
+    #[tokio::test]
+    async fn test_file_keys_keep_their_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            write_config(
+                &dir,
+                "provider.json",
+                r#"{"baseUrl": "https://mtn.test", "apiKey": "vault://psp/mtn:api_key"}"#,
+            ),
+            write_config(
+                &dir,
+                "provider.yaml",
+                "baseUrl: https://mtn.test\napiKey: \"vault://psp/mtn:api_key\"\n",
+            ),
+            write_config(
+                &dir,
+                "provider.toml",
+                "baseUrl = \"https://mtn.test\"\napiKey = \"vault://psp/mtn:api_key\"\n",
+            ),
+        ];
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[(
+            "psp/mtn", "api_key", "mtn-key",
+        )]));
+
+        for path in files {
+            let config: ProviderConfig = loader.load_and_resolve_file(&path).await.unwrap();
+
+            assert_eq!(
+                config,
+                ProviderConfig {
+                    base_url: "https://mtn.test".to_string(),
+                    api_key: "mtn-key".to_string(),
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_with_unknown_extension_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(&dir, "database.ini", "user = admin\n");
+        let loader = ConfigLoader::new(StaticSecretManager::new(&[]));
+
+        let result = loader.load_and_resolve_file::<DatabaseConfig>(&path).await;
+
+        assert!(result.unwrap_err().to_string().contains("database.ini"));
+    }
 }
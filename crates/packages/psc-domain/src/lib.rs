@@ -1,23 +1,204 @@
-use rust_decimal::Decimal;
-use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
-use std::ops::{Add, AddAssign};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Errors returned by fallible `Money` operations.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    #[error("currency mismatch: {left} vs {right}")]
+    CurrencyMismatch { left: Currency, right: Currency },
+    #[error("invalid currency code: {0:?}")]
+    InvalidCurrency(String),
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("cannot sum an empty set of amounts")]
+    EmptySum,
+    #[error("invalid amount: {0:?}")]
+    InvalidAmount(String),
+    #[error("amount {amount} has more decimal places than {currency} allows")]
+    TooPrecise { amount: String, currency: Currency },
+    #[error("invalid percentage: {0}")]
+    InvalidPercentage(String),
+    #[error("result is too large to represent")]
+    Overflow,
+}
+
+/// An ISO 4217 alphabetic currency code such as `XAF` or `USD`.
+///
+/// The code is stored inline as three ASCII uppercase letters, so a `Currency` parsed at
+/// runtime (from a database row or a proto message) is as cheap to copy as a constant.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const XAF: Currency = Currency(*b"XAF");
+    pub const XOF: Currency = Currency(*b"XOF");
+    pub const USD: Currency = Currency(*b"USD");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const GBP: Currency = Currency(*b"GBP");
+    pub const JPY: Currency = Currency(*b"JPY");
+    pub const BHD: Currency = Currency(*b"BHD");
+
+    /// The currency code as a string slice, e.g. `"XAF"`.
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("currency codes are validated as ASCII")
+    }
+
+    /// The ISO 4217 minor-unit exponent of this currency.
+    pub fn exponent(&self) -> u32 {
+        currency_exponent(self.as_str())
+    }
+
+    /// Checks that `amount` fits this currency's minor unit, ignoring trailing zeros.
+    ///
+    /// Returns `MoneyError::TooPrecise` for e.g. `1.555` USD or `10.5` XAF, while
+    /// `1.500` USD and `1000.00` XAF pass.
+    pub fn validate_scale(&self, amount: Decimal) -> Result<(), MoneyError> {
+        if amount.normalize().scale() > self.exponent() {
+            return Err(MoneyError::TooPrecise {
+                amount: amount.to_string(),
+                currency: *self,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = MoneyError;
+
+    /// Parses a three-letter uppercase ISO 4217 code.
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code.as_bytes() {
+            [a, b, c] if code.bytes().all(|byte| byte.is_ascii_uppercase()) => {
+                Ok(Currency([*a, *b, *c]))
+            }
+            _ => Err(MoneyError::InvalidCurrency(code.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = MoneyError;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        code.parse()
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.as_str().to_string()
+    }
+}
+
+impl std::fmt::Debug for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Currency({})", self.as_str())
+    }
+}
+
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the ISO 4217 minor-unit exponent for a currency code.
+///
+/// This is the number of decimal places between the major and minor unit, e.g. `2` for
+/// USD (cents), `0` for XAF and `3` for BHD. Unknown codes default to `2`.
+pub fn currency_exponent(currency: &str) -> u32 {
+    match currency {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+/// Returns the display symbol for a currency code, if it has a widely used prefix symbol.
+fn currency_symbol(currency: &str) -> Option<&'static str> {
+    match currency {
+        "USD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "JPY" => Some("¥"),
+        "NGN" => Some("₦"),
+        "GHS" => Some("₵"),
+        _ => None,
+    }
+}
+
+/// How an amount is rounded to a whole minor unit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RoundingMode {
+    /// Round to nearest, ties to even (banker's rounding).
+    #[default]
+    HalfEven,
+    /// Round to nearest, ties away from zero.
+    HalfUp,
+    /// Truncate towards zero.
+    Down,
+    /// Round away from zero.
+    Up,
+}
+
+impl From<RoundingMode> for RoundingStrategy {
+    fn from(mode: RoundingMode) -> Self {
+        match mode {
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::Down => RoundingStrategy::ToZero,
+            RoundingMode::Up => RoundingStrategy::AwayFromZero,
+        }
+    }
+}
+
+/// The number of minor units in one major unit for a given exponent.
+fn minor_unit_scale(exponent: u32) -> Decimal {
+    Decimal::from(10i64.pow(exponent))
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord)]
 pub struct Money {
     amount: Decimal,
-    currency: &'static str,
+    currency: Currency,
 }
 
 impl Money {
-    pub fn new(amount: i64, currency: &'static str) -> Self {
+    pub fn new(amount: i64, currency: Currency) -> Self {
         Self {
             amount: Decimal::from(amount),
             currency,
         }
     }
 
-    pub fn zero(currency: &'static str) -> Self {
+    /// Creates an amount from minor units (e.g. cents), using the currency's ISO 4217
+    /// exponent. `from_minor_units(1050, Currency::USD)` is 10.50 USD while
+    /// `from_minor_units(1050, Currency::XAF)` is 1050 XAF.
+    pub fn from_minor_units(minor_units: i64, currency: Currency) -> Self {
+        Self {
+            amount: Decimal::new(minor_units, currency.exponent()),
+            currency,
+        }
+    }
+
+    /// Parses a decimal amount in major units, such as `"1000.50"`.
+    ///
+    /// Returns `MoneyError::TooPrecise` if the amount has more significant decimal
+    /// places than the currency's exponent, e.g. `"1000.555"` USD or `"10.5"` XAF.
+    pub fn from_decimal_str(s: &str, currency: Currency) -> Result<Money, MoneyError> {
+        let amount = Decimal::from_str(s).map_err(|_| MoneyError::InvalidAmount(s.to_string()))?;
+        currency.validate_scale(amount)?;
+        Ok(Self { amount, currency })
+    }
+
+    pub fn zero(currency: Currency) -> Self {
         Self {
             amount: Decimal::ZERO,
             currency,
@@ -28,19 +209,285 @@ impl Money {
         self.amount
     }
 
-    pub fn currency(&self) -> &'static str {
+    pub fn currency(&self) -> Currency {
         self.currency
     }
 
-    pub fn multiply_percent(&self, percent: f64) -> Self {
-        let percentage = Decimal::from_f64(percent / 100.0).unwrap();
+    /// The ISO 4217 minor-unit exponent of this amount's currency.
+    pub fn exponent(&self) -> u32 {
+        self.currency.exponent()
+    }
+
+    /// Whether the amount is zero, whatever its scale (`0.00` is zero).
+    pub fn is_zero(&self) -> bool {
+        self.amount.is_zero()
+    }
+
+    /// Whether the amount is below zero, e.g. a ledger delta taking funds out.
+    pub fn is_negative(&self) -> bool {
+        self.amount < Decimal::ZERO
+    }
+
+    /// The amount without its sign, in the same currency.
+    pub fn abs(&self) -> Money {
+        Self {
+            amount: self.amount.abs(),
+            currency: self.currency,
+        }
+    }
+
+    /// The amount with its sign flipped, in the same currency, e.g. to reverse a delta.
+    pub fn negate(&self) -> Money {
+        Self {
+            amount: -self.amount,
+            currency: self.currency,
+        }
+    }
+
+    /// Converts the amount to minor units using the currency's ISO 4217 exponent.
+    ///
+    /// Sub-minor-unit fractions are rounded to the nearest minor unit using banker's
+    /// rounding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the amount in minor units does not fit in an `i64`.
+    pub fn to_minor_units(&self) -> i64 {
+        (self.amount * minor_unit_scale(self.exponent()))
+            .round()
+            .to_i64()
+            .expect("Money amount in minor units overflows i64")
+    }
+
+    /// Adds two amounts, returning an error instead of panicking if the currencies differ.
+    ///
+    /// Prefer this over the `+` operator in request-handling code paths.
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        Ok(Self {
+            amount: self.amount + other.amount,
+            currency: self.currency,
+        })
+    }
+
+    /// Subtracts `other` from `self`, returning an error instead of panicking if the
+    /// currencies differ.
+    ///
+    /// Prefer this over the `-` operator in request-handling code paths.
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        Ok(Self {
+            amount: self.amount - other.amount,
+            currency: self.currency,
+        })
+    }
+
+    /// Sums an iterator of amounts, returning an error instead of panicking.
+    ///
+    /// The first element establishes the currency, so an empty iterator yields
+    /// [`MoneyError::EmptySum`] and any element in a different currency yields
+    /// [`MoneyError::CurrencyMismatch`].
+    pub fn try_sum<I>(iter: I) -> Result<Money, MoneyError>
+    where
+        I: IntoIterator<Item = Money>,
+    {
+        let mut iter = iter.into_iter();
+        let first = iter.next().ok_or(MoneyError::EmptySum)?;
+        iter.try_fold(first, |total, money| total.checked_add(&money))
+    }
+
+    fn ensure_same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch {
+                left: self.currency,
+                right: other.currency,
+            });
+        }
+        Ok(())
+    }
+
+    /// Multiplies the amount by `factor`, preserving the currency.
+    ///
+    /// The result keeps full decimal precision; no rounding to minor units is applied.
+    pub fn multiply(&self, factor: Decimal) -> Money {
+        Self {
+            amount: self.amount * factor,
+            currency: self.currency,
+        }
+    }
+
+    /// Divides the amount by `divisor`, preserving the currency.
+    ///
+    /// The result keeps full decimal precision; no rounding to minor units is applied.
+    /// Returns [`MoneyError::DivisionByZero`] if `divisor` is zero.
+    pub fn divide(&self, divisor: Decimal) -> Result<Money, MoneyError> {
+        if divisor.is_zero() {
+            return Err(MoneyError::DivisionByZero);
+        }
+        Ok(Self {
+            amount: self.amount / divisor,
+            currency: self.currency,
+        })
+    }
+
+    /// Splits the amount into parts proportional to `weights`.
+    ///
+    /// Each part is rounded down to a whole minor unit of the currency and the leftover
+    /// minor units are handed out one at a time to the parts with the largest
+    /// remainders (largest-remainder method), so the parts always sum exactly to the
+    /// original amount. Ties go to the earlier weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is empty or all weights are zero.
+    pub fn allocate(&self, weights: &[u32]) -> Vec<Money> {
+        let total_weight: u64 = weights.iter().map(|w| u64::from(*w)).sum();
+        if total_weight == 0 {
+            panic!("Cannot allocate money without a positive total weight");
+        }
+        let total_weight = Decimal::from(total_weight);
+
+        let scale = minor_unit_scale(self.exponent());
+        let amount = self.amount.abs() * scale;
+        let mut parts = Vec::with_capacity(weights.len());
+        let mut remainders = Vec::with_capacity(weights.len());
+        for weight in weights {
+            let exact = amount * Decimal::from(*weight) / total_weight;
+            let part = exact.floor();
+            parts.push(part);
+            remainders.push(exact - part);
+        }
+
+        let mut leftover = amount - parts.iter().sum::<Decimal>();
+        let mut order: Vec<usize> = (0..weights.len()).collect();
+        order.sort_by(|a, b| remainders[*b].cmp(&remainders[*a]));
+        for index in order {
+            if leftover <= Decimal::ZERO {
+                break;
+            }
+            let unit = leftover.min(Decimal::ONE);
+            parts[index] += unit;
+            leftover -= unit;
+        }
+
+        parts
+            .into_iter()
+            .map(|part| {
+                let part = part / scale;
+                Self {
+                    amount: if self.amount.is_sign_negative() {
+                        -part
+                    } else {
+                        part
+                    },
+                    currency: self.currency,
+                }
+            })
+            .collect()
+    }
+
+    /// Converts the amount to `to` at `rate` units of `to` per unit of this currency.
+    ///
+    /// The result is rounded to a whole minor unit of `to` using `rounding`, e.g.
+    /// 1000 XAF at a rate of 0.00163 is 1.63 USD.
+    pub fn convert(&self, to: Currency, rate: Decimal, rounding: RoundingMode) -> Money {
+        Self {
+            amount: (self.amount * rate).round_dp_with_strategy(to.exponent(), rounding.into()),
+            currency: to,
+        }
+    }
+
+    /// Rounds the amount to a whole minor unit of its currency using `mode`, e.g.
+    /// 1.555 USD is 1.56 USD under [`RoundingMode::HalfEven`] and 1.55 USD under
+    /// [`RoundingMode::Down`].
+    ///
+    /// [`Self::multiply`], [`Self::divide`] and [`Self::multiply_percent`] keep full
+    /// precision, so fee and ledger code should call this on their results before
+    /// persisting them.
+    pub fn round_to_currency(&self, mode: RoundingMode) -> Money {
         Self {
-            amount: self.amount * percentage,
+            amount: self
+                .amount
+                .round_dp_with_strategy(self.exponent(), mode.into()),
             currency: self.currency,
         }
     }
+
+    /// Formats the amount with a currency symbol prefix, e.g. `$1000.00` or `-€12.50`.
+    ///
+    /// Currencies without a known symbol fall back to the [`Display`](std::fmt::Display)
+    /// form, e.g. `1000 XAF`.
+    pub fn format_with_symbol(&self) -> String {
+        match currency_symbol(self.currency.as_str()) {
+            Some(symbol) => {
+                let sign = if self.amount.is_sign_negative() {
+                    "-"
+                } else {
+                    ""
+                };
+                format!(
+                    "{}{}{:.prec$}",
+                    sign,
+                    symbol,
+                    self.amount.abs().round_dp(self.exponent()),
+                    prec = self.exponent() as usize
+                )
+            }
+            None => self.to_string(),
+        }
+    }
+
+    /// Returns `percent` percent of the amount, e.g. 0.5% of 1 XAF is 0.005 XAF.
+    ///
+    /// Like [`Self::multiply`], no rounding is applied; see [`Self::round_to_currency`].
+    /// Prefer [`Self::try_multiply_percent`] when the percentage is already a `Decimal`.
+    /// Returns [`MoneyError::InvalidPercentage`] if `percent` is NaN, infinite or too
+    /// large to represent as a `Decimal`, and [`MoneyError::Overflow`] if the result is.
+    pub fn multiply_percent(&self, percent: f64) -> Result<Money, MoneyError> {
+        let percent = Decimal::from_f64(percent)
+            .ok_or_else(|| MoneyError::InvalidPercentage(percent.to_string()))?;
+        self.try_multiply_percent(percent)
+    }
+
+    /// Returns `percent` percent of the amount without going through a float, e.g.
+    /// 0.5% of 1 XAF is 0.005 XAF.
+    ///
+    /// Like [`Self::multiply`], no rounding is applied; see [`Self::round_to_currency`].
+    /// Returns [`MoneyError::Overflow`] if the result is too large to represent.
+    pub fn try_multiply_percent(&self, percent: Decimal) -> Result<Money, MoneyError> {
+        // Scaling the percentage down first keeps amounts near the maximum in range
+        // for percentages up to 100
+        let amount = percent
+            .checked_div(Decimal::ONE_HUNDRED)
+            .and_then(|fraction| self.amount.checked_mul(fraction))
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self {
+            amount,
+            currency: self.currency,
+        })
+    }
+}
+
+/// Formats the amount to the currency's minor-unit exponent followed by the currency
+/// code, e.g. `1000.00 USD` or `1000 XAF`.
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.prec$} {}",
+            self.amount.round_dp(self.exponent()),
+            self.currency,
+            prec = self.exponent() as usize
+        )
+    }
 }
 
+/// Adds two amounts.
+///
+/// # Panics
+///
+/// Panics if the two amounts are in different currencies. Use [`Money::checked_add`]
+/// where a mismatch must be handled gracefully.
 impl Add for Money {
     type Output = Self;
 
@@ -64,6 +511,54 @@ impl AddAssign for Money {
     }
 }
 
+/// Sums an iterator of amounts.
+///
+/// # Panics
+///
+/// Panics if the iterator is empty (there is no currency to return a zero in) or if the
+/// amounts are in different currencies. Use [`Money::try_sum`] to handle these cases.
+impl std::iter::Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Self {
+        match Money::try_sum(iter) {
+            Ok(total) => total,
+            Err(MoneyError::EmptySum) => panic!("Cannot sum an empty iterator of money"),
+            Err(_) => panic!("Cannot add money with different currencies"),
+        }
+    }
+}
+
+/// Subtracts one amount from another.
+///
+/// The result may be negative (e.g. a ledger delta or an overdrawn balance); the
+/// underlying `Decimal` is signed, so no clamping to zero takes place.
+///
+/// # Panics
+///
+/// Panics if the two amounts are in different currencies. Use [`Money::checked_sub`]
+/// where a mismatch must be handled gracefully.
+impl Sub for Money {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        if self.currency != other.currency {
+            panic!("Cannot subtract money with different currencies");
+        }
+        Self {
+            amount: self.amount - other.amount,
+            currency: self.currency,
+        }
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Self) {
+        if self.currency != other.currency {
+            panic!("Cannot subtract money with different currencies");
+        }
+        self.amount -= other.amount;
+    }
+}
+
 use uuid::Uuid;
 
 macro_rules! impl_id {
@@ -0,0 +1,525 @@
+use psc_domain::{Currency, Money, MoneyError, RoundingMode};
+use rust_decimal::Decimal;
+
+#[test]
+fn test_add_money() {
+    let total = Money::new(100, Currency::XAF) + Money::new(50, Currency::XAF);
+    assert_eq!(total, Money::new(150, Currency::XAF));
+}
+
+#[test]
+fn test_sub_money() {
+    let net = Money::new(100, Currency::XAF) - Money::new(30, Currency::XAF);
+    assert_eq!(net, Money::new(70, Currency::XAF));
+
+    let mut balance = Money::new(100, Currency::XAF);
+    balance -= Money::new(40, Currency::XAF);
+    assert_eq!(balance, Money::new(60, Currency::XAF));
+}
+
+#[test]
+fn test_sub_money_negative_result() {
+    let delta = Money::new(30, Currency::XAF) - Money::new(100, Currency::XAF);
+    assert_eq!(delta.amount(), Decimal::from(-70));
+    assert_eq!(delta.currency(), Currency::XAF);
+}
+
+#[test]
+#[should_panic(expected = "Cannot subtract money with different currencies")]
+fn test_sub_money_different_currency() {
+    let _ = Money::new(100, Currency::XAF) - Money::new(30, Currency::USD);
+}
+
+#[test]
+fn test_checked_add_money() {
+    let total = Money::new(100, Currency::XAF).checked_add(&Money::new(50, Currency::XAF));
+    assert_eq!(total, Ok(Money::new(150, Currency::XAF)));
+}
+
+#[test]
+fn test_checked_add_different_currency() {
+    let result = Money::new(100, Currency::XAF).checked_add(&Money::new(50, Currency::USD));
+    assert_eq!(
+        result,
+        Err(MoneyError::CurrencyMismatch {
+            left: Currency::XAF,
+            right: Currency::USD,
+        })
+    );
+}
+
+#[test]
+fn test_checked_sub_money() {
+    let net = Money::new(100, Currency::XAF).checked_sub(&Money::new(30, Currency::XAF));
+    assert_eq!(net, Ok(Money::new(70, Currency::XAF)));
+}
+
+#[test]
+fn test_checked_sub_different_currency() {
+    let result = Money::new(100, Currency::XAF).checked_sub(&Money::new(30, Currency::EUR));
+    assert_eq!(
+        result,
+        Err(MoneyError::CurrencyMismatch {
+            left: Currency::XAF,
+            right: Currency::EUR,
+        })
+    );
+}
+
+#[test]
+fn test_multiply_money() {
+    let tripled = Money::new(150, Currency::XAF).multiply(Decimal::from(3));
+    assert_eq!(tripled, Money::new(450, Currency::XAF));
+
+    let halved = Money::new(101, Currency::XAF).multiply(Decimal::new(5, 1));
+    assert_eq!(halved.amount(), Decimal::new(505, 1));
+    assert_eq!(halved.currency(), Currency::XAF);
+}
+
+#[test]
+fn test_divide_money() {
+    let quarter = Money::new(100, Currency::XAF)
+        .divide(Decimal::from(4))
+        .unwrap();
+    assert_eq!(quarter, Money::new(25, Currency::XAF));
+
+    let fractional = Money::new(10, Currency::XAF)
+        .divide(Decimal::from(4))
+        .unwrap();
+    assert_eq!(fractional.amount(), Decimal::new(25, 1));
+}
+
+#[test]
+fn test_divide_money_by_zero() {
+    let result = Money::new(100, Currency::XAF).divide(Decimal::ZERO);
+    assert_eq!(result, Err(MoneyError::DivisionByZero));
+}
+
+#[test]
+fn test_allocate_even_split() {
+    let parts = Money::new(100, Currency::XAF).allocate(&[1, 1, 1]);
+    assert_eq!(
+        parts,
+        vec![
+            Money::new(34, Currency::XAF),
+            Money::new(33, Currency::XAF),
+            Money::new(33, Currency::XAF),
+        ]
+    );
+    let total: Money = parts.into_iter().sum();
+    assert_eq!(total, Money::new(100, Currency::XAF));
+}
+
+#[test]
+fn test_allocate_weighted_split() {
+    let parts = Money::new(1000, Currency::XAF).allocate(&[70, 20, 10]);
+    assert_eq!(
+        parts,
+        vec![
+            Money::new(700, Currency::XAF),
+            Money::new(200, Currency::XAF),
+            Money::new(100, Currency::XAF),
+        ]
+    );
+
+    let parts = Money::new(10, Currency::XAF).allocate(&[1, 2]);
+    assert_eq!(
+        parts,
+        vec![Money::new(3, Currency::XAF), Money::new(7, Currency::XAF)]
+    );
+}
+
+#[test]
+fn test_allocate_negative_amount() {
+    let parts = Money::new(-100, Currency::XAF).allocate(&[1, 1, 1]);
+    assert_eq!(
+        parts,
+        vec![
+            Money::new(-34, Currency::XAF),
+            Money::new(-33, Currency::XAF),
+            Money::new(-33, Currency::XAF),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Cannot allocate money without a positive total weight")]
+fn test_allocate_zero_weights() {
+    let _ = Money::new(100, Currency::XAF).allocate(&[0, 0]);
+}
+
+#[test]
+fn test_minor_units_xaf() {
+    let money = Money::from_minor_units(1050, Currency::XAF);
+    assert_eq!(money.amount(), Decimal::from(1050));
+    assert_eq!(money.exponent(), 0);
+    assert_eq!(money.to_minor_units(), 1050);
+}
+
+#[test]
+fn test_minor_units_usd() {
+    let money = Money::from_minor_units(1050, Currency::USD);
+    assert_eq!(money.amount(), Decimal::new(1050, 2));
+    assert_eq!(money.exponent(), 2);
+    assert_eq!(money.to_minor_units(), 1050);
+    assert_eq!(Money::new(10, Currency::USD).to_minor_units(), 1000);
+}
+
+#[test]
+fn test_minor_units_bhd() {
+    let money = Money::from_minor_units(1050, Currency::BHD);
+    assert_eq!(money.amount(), Decimal::new(1050, 3));
+    assert_eq!(money.exponent(), 3);
+    assert_eq!(money.to_minor_units(), 1050);
+}
+
+#[test]
+fn test_allocate_respects_minor_units() {
+    let parts = Money::from_minor_units(100, Currency::USD).allocate(&[1, 1, 1]);
+    assert_eq!(
+        parts,
+        vec![
+            Money::from_minor_units(34, Currency::USD),
+            Money::from_minor_units(33, Currency::USD),
+            Money::from_minor_units(33, Currency::USD),
+        ]
+    );
+}
+
+#[test]
+fn test_display_money() {
+    assert_eq!(Money::new(1000, Currency::USD).to_string(), "1000.00 USD");
+    assert_eq!(Money::new(1000, Currency::XAF).to_string(), "1000 XAF");
+    assert_eq!(
+        Money::from_minor_units(1050, Currency::BHD).to_string(),
+        "1.050 BHD"
+    );
+    assert_eq!(
+        Money::from_minor_units(-1250, Currency::USD).to_string(),
+        "-12.50 USD"
+    );
+}
+
+#[test]
+fn test_format_with_symbol() {
+    assert_eq!(
+        Money::new(1000, Currency::USD).format_with_symbol(),
+        "$1000.00"
+    );
+    assert_eq!(
+        Money::from_minor_units(-1250, Currency::EUR).format_with_symbol(),
+        "-€12.50"
+    );
+    assert_eq!(
+        Money::new(1000, Currency::XAF).format_with_symbol(),
+        "1000 XAF"
+    );
+}
+
+#[test]
+fn test_sum_money() {
+    let amounts = vec![
+        Money::new(100, Currency::XAF),
+        Money::new(250, Currency::XAF),
+        Money::new(50, Currency::XAF),
+    ];
+    let total: Money = amounts.into_iter().sum();
+    assert_eq!(total, Money::new(400, Currency::XAF));
+}
+
+#[test]
+#[should_panic(expected = "Cannot add money with different currencies")]
+fn test_sum_money_different_currency() {
+    let amounts = vec![
+        Money::new(100, Currency::XAF),
+        Money::new(250, Currency::USD),
+    ];
+    let _: Money = amounts.into_iter().sum();
+}
+
+#[test]
+fn test_try_sum_money() {
+    let total = Money::try_sum(vec![
+        Money::new(100, Currency::XAF),
+        Money::new(250, Currency::XAF),
+    ]);
+    assert_eq!(total, Ok(Money::new(350, Currency::XAF)));
+
+    let mixed = Money::try_sum(vec![
+        Money::new(100, Currency::XAF),
+        Money::new(250, Currency::USD),
+    ]);
+    assert_eq!(
+        mixed,
+        Err(MoneyError::CurrencyMismatch {
+            left: Currency::XAF,
+            right: Currency::USD,
+        })
+    );
+
+    assert_eq!(Money::try_sum(Vec::new()), Err(MoneyError::EmptySum));
+}
+
+#[test]
+fn test_money_from_runtime_currency() {
+    let code = String::from("KES");
+    let currency: Currency = code.parse().unwrap();
+    let money = Money::from_minor_units(1050, currency);
+    assert_eq!(money.currency().as_str(), "KES");
+    assert_eq!(money.to_string(), "10.50 KES");
+}
+
+#[test]
+fn test_invalid_currency_rejected() {
+    for code in ["", "US", "usd", "USDT", "U$D"] {
+        assert_eq!(
+            code.parse::<Currency>(),
+            Err(MoneyError::InvalidCurrency(code.to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_convert_xaf_to_usd_and_back() {
+    let xaf = Money::new(10_000, Currency::XAF);
+
+    let usd = xaf.convert(Currency::USD, Decimal::new(16, 4), RoundingMode::HalfEven);
+    assert_eq!(usd, Money::from_minor_units(1600, Currency::USD));
+    assert_eq!(usd.currency(), Currency::USD);
+
+    let back = usd.convert(Currency::XAF, Decimal::from(625), RoundingMode::HalfEven);
+    assert_eq!(back, xaf);
+    assert_eq!(back.currency(), Currency::XAF);
+}
+
+#[test]
+fn test_convert_rounds_to_target_exponent() {
+    // 1234 XAF * 0.0016 = 1.9744 USD
+    let xaf = Money::new(1234, Currency::XAF);
+    let rate = Decimal::new(16, 4);
+    assert_eq!(
+        xaf.convert(Currency::USD, rate, RoundingMode::HalfEven),
+        Money::from_minor_units(197, Currency::USD)
+    );
+    assert_eq!(
+        xaf.convert(Currency::USD, rate, RoundingMode::Down),
+        Money::from_minor_units(197, Currency::USD)
+    );
+
+    // 10.50 USD * 601.5 = 6315.75 XAF
+    let usd = Money::from_minor_units(1050, Currency::USD);
+    let rate = Decimal::new(6015, 1);
+    assert_eq!(
+        usd.convert(Currency::XAF, rate, RoundingMode::HalfEven),
+        Money::new(6316, Currency::XAF)
+    );
+    assert_eq!(
+        usd.convert(Currency::XAF, rate, RoundingMode::Down),
+        Money::new(6315, Currency::XAF)
+    );
+}
+
+#[test]
+fn test_convert_rounding_modes_at_midpoint() {
+    // 5 XAF * 0.001 = 0.005 USD, exactly halfway between 0.00 and 0.01 USD
+    let xaf = Money::new(5, Currency::XAF);
+    let rate = Decimal::new(1, 3);
+    let convert = |mode| xaf.convert(Currency::USD, rate, mode);
+
+    assert_eq!(convert(RoundingMode::HalfEven), Money::zero(Currency::USD));
+    assert_eq!(
+        convert(RoundingMode::HalfUp),
+        Money::from_minor_units(1, Currency::USD)
+    );
+    assert_eq!(convert(RoundingMode::Down), Money::zero(Currency::USD));
+    assert_eq!(
+        convert(RoundingMode::Up),
+        Money::from_minor_units(1, Currency::USD)
+    );
+}
+
+#[test]
+fn test_abs_and_negate_preserve_currency() {
+    let delta = Money::new(30, Currency::XAF) - Money::new(100, Currency::XAF);
+    assert!(delta.is_negative());
+
+    assert_eq!(delta.abs(), Money::new(70, Currency::XAF));
+    assert_eq!(delta.negate(), Money::new(70, Currency::XAF));
+    assert!(!delta.abs().is_negative());
+    assert_eq!(delta.negate().negate(), delta);
+
+    let refund = Money::from_minor_units(1050, Currency::USD).negate();
+    assert_eq!(refund.amount(), Decimal::new(-1050, 2));
+    assert_eq!(refund.currency(), Currency::USD);
+    assert_eq!(refund.abs(), Money::from_minor_units(1050, Currency::USD));
+}
+
+#[test]
+fn test_is_zero() {
+    assert!(Money::zero(Currency::XAF).is_zero());
+    assert!(!Money::zero(Currency::XAF).is_negative());
+    assert!(Money::from_minor_units(0, Currency::USD).is_zero());
+    assert!((Money::new(100, Currency::XAF) - Money::new(100, Currency::XAF)).is_zero());
+    assert!(!Money::new(1, Currency::XAF).is_zero());
+}
+
+#[test]
+fn test_round_to_currency_xaf() {
+    // 0.5% of 1 XAF, halfway between 0 and 1 XAF
+    let fee = Money::new(1, Currency::XAF).multiply_percent(0.5).unwrap();
+    assert_eq!(fee.amount(), Decimal::new(5, 3));
+
+    assert_eq!(
+        fee.round_to_currency(RoundingMode::HalfEven),
+        Money::zero(Currency::XAF)
+    );
+    assert_eq!(
+        fee.round_to_currency(RoundingMode::HalfUp),
+        Money::zero(Currency::XAF)
+    );
+    assert_eq!(
+        fee.round_to_currency(RoundingMode::Down),
+        Money::zero(Currency::XAF)
+    );
+    assert_eq!(
+        fee.round_to_currency(RoundingMode::Up),
+        Money::new(1, Currency::XAF)
+    );
+}
+
+#[test]
+fn test_round_to_currency_usd() {
+    let amount = Money::new(1, Currency::USD).multiply(Decimal::new(1555, 3));
+    let round = |mode| amount.round_to_currency(mode);
+
+    assert_eq!(
+        round(RoundingMode::HalfEven),
+        Money::from_minor_units(156, Currency::USD)
+    );
+    assert_eq!(
+        round(RoundingMode::HalfUp),
+        Money::from_minor_units(156, Currency::USD)
+    );
+    assert_eq!(
+        round(RoundingMode::Down),
+        Money::from_minor_units(155, Currency::USD)
+    );
+    assert_eq!(
+        round(RoundingMode::Up),
+        Money::from_minor_units(156, Currency::USD)
+    );
+
+    // Ties go to the even minor unit under HalfEven but away from zero under HalfUp
+    let amount = Money::new(1, Currency::USD).multiply(Decimal::new(1565, 3));
+    assert_eq!(
+        amount.round_to_currency(RoundingMode::HalfEven),
+        Money::from_minor_units(156, Currency::USD)
+    );
+    assert_eq!(
+        amount.round_to_currency(RoundingMode::HalfUp),
+        Money::from_minor_units(157, Currency::USD)
+    );
+}
+
+#[test]
+fn test_from_decimal_str() {
+    assert_eq!(
+        Money::from_decimal_str("1000.50", Currency::USD).unwrap(),
+        Money::from_minor_units(100050, Currency::USD)
+    );
+    assert_eq!(
+        Money::from_decimal_str("1000.500", Currency::USD).unwrap(),
+        Money::from_minor_units(100050, Currency::USD)
+    );
+    assert_eq!(
+        Money::from_decimal_str("1000", Currency::XAF).unwrap(),
+        Money::new(1000, Currency::XAF)
+    );
+}
+
+#[test]
+fn test_from_decimal_str_rejects_over_precise_amounts() {
+    assert!(matches!(
+        Money::from_decimal_str("1000.555", Currency::USD),
+        Err(MoneyError::TooPrecise {
+            currency: Currency::USD,
+            ..
+        })
+    ));
+    assert!(matches!(
+        Money::from_decimal_str("10.5", Currency::XAF),
+        Err(MoneyError::TooPrecise { .. })
+    ));
+    assert_eq!(
+        Money::from_decimal_str("ten", Currency::USD),
+        Err(MoneyError::InvalidAmount("ten".to_string()))
+    );
+}
+
+#[test]
+fn test_validate_scale() {
+    let amount = Decimal::new(1555, 3);
+    assert_eq!(
+        Currency::USD.validate_scale(amount),
+        Err(MoneyError::TooPrecise {
+            amount: "1.555".to_string(),
+            currency: Currency::USD,
+        })
+    );
+    assert_eq!(Currency::BHD.validate_scale(amount), Ok(()));
+    assert_eq!(Currency::USD.validate_scale(Decimal::new(1500, 3)), Ok(()));
+}
+
+#[test]
+fn test_validate_scale_accepts_whole_amounts_for_zero_exponent_currencies() {
+    for amount in [
+        Decimal::new(1000, 0),
+        Decimal::new(100000, 2),
+        Decimal::new(1000000, 3),
+    ] {
+        assert_eq!(Currency::XAF.validate_scale(amount), Ok(()));
+    }
+    assert!(matches!(
+        Currency::XAF.validate_scale(Decimal::new(105, 1)),
+        Err(MoneyError::TooPrecise { .. })
+    ));
+}
+
+#[test]
+fn test_try_multiply_percent() {
+    let amount = Money::from_minor_units(10_000, Currency::USD);
+    let fee = amount.try_multiply_percent(Decimal::new(15, 1)).unwrap();
+    assert_eq!(fee, Money::from_minor_units(150, Currency::USD));
+
+    assert_eq!(
+        amount.multiply_percent(1.5).unwrap(),
+        amount.try_multiply_percent(Decimal::new(15, 1)).unwrap()
+    );
+}
+
+#[test]
+fn test_try_multiply_percent_near_max_amount() {
+    let amount = Money::from_decimal_str(&Decimal::MAX.to_string(), Currency::XAF).unwrap();
+
+    let fee = amount.try_multiply_percent(Decimal::new(15, 1)).unwrap();
+    assert!(fee < amount);
+    assert_eq!(
+        amount.try_multiply_percent(Decimal::ONE_HUNDRED).unwrap(),
+        amount
+    );
+    assert_eq!(
+        amount.try_multiply_percent(Decimal::from(200)),
+        Err(MoneyError::Overflow)
+    );
+}
+
+#[test]
+fn test_multiply_percent_rejects_non_finite() {
+    let amount = Money::new(100, Currency::XAF);
+    for percent in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(matches!(
+            amount.multiply_percent(percent),
+            Err(MoneyError::InvalidPercentage(_))
+        ));
+    }
+}
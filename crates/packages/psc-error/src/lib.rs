@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,11 +13,27 @@ pub enum Error {
     #[error("not found: {0}")]
     NotFound(String),
 
+    #[error("conflict: {0}")]
+    Conflict(String),
+
     #[error("internal error: {0}")]
     Internal(String),
 
     #[error("provider error (code: {code}): {message}")]
-    Provider { code: String, message: String },
+    Provider {
+        code: String,
+        message: String,
+        kind: ProviderErrorKind,
+    },
+
+    #[error("timeout: {0}")]
+    Timeout(String),
+
+    #[error("rate limited (retry after: {retry_after:?})")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("unsupported: {0}")]
+    Unsupported(String),
 
     #[error(transparent)]
     Database(#[from] sqlx::Error),
@@ -25,3 +43,427 @@ pub enum Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// How a failure reported by a provider should be handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ProviderErrorKind {
+    /// Repeating the request cannot succeed, e.g. insufficient funds or an unknown payer.
+    Terminal,
+    /// The provider is temporarily unable to process the request.
+    Transient,
+    /// The provider is throttling requests.
+    RateLimited,
+}
+
+impl ProviderErrorKind {
+    /// Classifies a provider response by HTTP status alone, for errors the provider
+    /// did not describe with a code of its own.
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            429 => ProviderErrorKind::RateLimited,
+            500..=599 => ProviderErrorKind::Transient,
+            _ => ProviderErrorKind::Terminal,
+        }
+    }
+}
+
+impl Error {
+    /// Whether the failed operation is worth retrying.
+    ///
+    /// Transient failures (internal errors, timeouts, rate limiting, provider errors not
+    /// classified as [`ProviderErrorKind::Terminal`] and connection-level database
+    /// errors) are retryable. Caller errors such as
+    /// `InvalidArgument`, `BadRequest` and `NotFound` are not, since repeating the same
+    /// request cannot succeed. Neither is `Conflict`: the caller has to re-read the
+    /// current state before trying again, nor `Unsupported`, which a provider will
+    /// keep answering the same way.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::InvalidArgument(_)
+            | Error::BadRequest(_)
+            | Error::NotFound(_)
+            | Error::Conflict(_)
+            | Error::Unsupported(_) => false,
+            Error::Internal(_) | Error::Timeout(_) | Error::RateLimited { .. } => true,
+            Error::Provider { kind, .. } => *kind != ProviderErrorKind::Terminal,
+            Error::Database(e) => matches!(
+                e,
+                sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed
+            ),
+            Error::Anyhow(_) => false,
+        }
+    }
+
+    /// The HTTP status code a REST edge should respond with for this error.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Error::InvalidArgument(_) | Error::BadRequest(_) => 400,
+            Error::NotFound(_) => 404,
+            Error::Conflict(_) => 409,
+            Error::RateLimited { .. } => 429,
+            Error::Provider { .. } => 502,
+            Error::Timeout(_) => 504,
+            Error::Unsupported(_) => 501,
+            Error::Internal(_) | Error::Database(_) | Error::Anyhow(_) => 500,
+        }
+    }
+
+    /// A stable, machine-readable identifier for the error category.
+    ///
+    /// Unlike the `Display` output, these codes are part of the API contract and do not
+    /// change when messages are reworded, so clients should branch on them instead of
+    /// parsing error strings. For `Provider` errors, [`Error::provider_code`] exposes the
+    /// provider's own code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidArgument(_) => "INVALID_ARGUMENT",
+            Error::BadRequest(_) => "BAD_REQUEST",
+            Error::NotFound(_) => "NOT_FOUND",
+            Error::Conflict(_) => "CONFLICT",
+            Error::Internal(_) | Error::Database(_) | Error::Anyhow(_) => "INTERNAL",
+            Error::Provider { .. } => "PROVIDER_ERROR",
+            Error::Timeout(_) => "TIMEOUT",
+            Error::RateLimited { .. } => "RATE_LIMITED",
+            Error::Unsupported(_) => "UNSUPPORTED",
+        }
+    }
+
+    /// The provider's own error code, for `Provider` errors.
+    pub fn provider_code(&self) -> Option<&str> {
+        match self {
+            Error::Provider { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+}
+
+/// JSON body returned alongside an error's HTTP status.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_code: Option<String>,
+}
+
+impl From<&Error> for ErrorBody {
+    fn from(err: &Error) -> Self {
+        Self {
+            code: err.code().to_string(),
+            message: err.to_string(),
+            provider_code: err.provider_code().map(str::to_string),
+        }
+    }
+}
+
+/// Maps the unified error onto a gRPC status so handlers can use `?` directly.
+#[cfg(feature = "tonic")]
+impl From<Error> for tonic::Status {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::InvalidArgument(msg) | Error::BadRequest(msg) => {
+                tonic::Status::invalid_argument(msg)
+            }
+            Error::NotFound(msg) => tonic::Status::not_found(msg),
+            Error::Conflict(msg) => tonic::Status::aborted(msg),
+            Error::Provider { kind, .. } => match kind {
+                ProviderErrorKind::Terminal => tonic::Status::failed_precondition(err.to_string()),
+                ProviderErrorKind::Transient => tonic::Status::unavailable(err.to_string()),
+                ProviderErrorKind::RateLimited => {
+                    tonic::Status::resource_exhausted(err.to_string())
+                }
+            },
+            Error::Timeout(msg) => tonic::Status::deadline_exceeded(msg),
+            Error::RateLimited { .. } => tonic::Status::resource_exhausted(err.to_string()),
+            Error::Unsupported(msg) => tonic::Status::unimplemented(msg),
+            _ => tonic::Status::internal(err.to_string()),
+        }
+    }
+}
+
+/// Maps Redis failures onto the unified error.
+///
+/// Connection and timeout failures become retryable `Internal` errors; anything else
+/// (type errors, rejected commands, ...) is kept as the source of a non-retryable
+/// `Anyhow` error.
+#[cfg(feature = "redis")]
+impl From<redis::RedisError> for Error {
+    fn from(err: redis::RedisError) -> Self {
+        if err.is_connection_refusal() || err.is_connection_dropped() || err.is_timeout() {
+            Error::Internal(format!("redis connection error: {}", err))
+        } else {
+            Error::Anyhow(anyhow::Error::new(err).context("redis command failed"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider_error(code: &str) -> Error {
+        provider_error_of_kind(code, ProviderErrorKind::Terminal)
+    }
+
+    fn provider_error_of_kind(code: &str, kind: ProviderErrorKind) -> Error {
+        Error::Provider {
+            code: code.to_string(),
+            message: "provider failure".to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_caller_errors_are_not_retryable() {
+        assert!(!Error::InvalidArgument("bad id".to_string()).is_retryable());
+        assert!(!Error::BadRequest("unbalanced".to_string()).is_retryable());
+        assert!(!Error::NotFound("account".to_string()).is_retryable());
+        assert!(!Error::Conflict("stale version".to_string()).is_retryable());
+        assert!(!Error::Unsupported("cancel".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_transient_errors_are_retryable() {
+        assert!(Error::Internal("boom".to_string()).is_retryable());
+        assert!(Error::Timeout("mtn".to_string()).is_retryable());
+        assert!(Error::RateLimited { retry_after: None }.is_retryable());
+        assert!(Error::RateLimited {
+            retry_after: Some(Duration::from_secs(5))
+        }
+        .is_retryable());
+    }
+
+    #[test]
+    fn test_provider_errors_retryable_unless_terminal() {
+        assert!(provider_error_of_kind("HTTP_503", ProviderErrorKind::Transient).is_retryable());
+        assert!(
+            provider_error_of_kind("TOO_MANY_REQUESTS", ProviderErrorKind::RateLimited)
+                .is_retryable()
+        );
+        assert!(
+            !provider_error_of_kind("NOT_ENOUGH_FUNDS", ProviderErrorKind::Terminal).is_retryable()
+        );
+    }
+
+    #[test]
+    fn test_provider_error_kind_from_http_status() {
+        assert_eq!(
+            ProviderErrorKind::from_http_status(503),
+            ProviderErrorKind::Transient
+        );
+        assert_eq!(
+            ProviderErrorKind::from_http_status(429),
+            ProviderErrorKind::RateLimited
+        );
+        assert_eq!(
+            ProviderErrorKind::from_http_status(400),
+            ProviderErrorKind::Terminal
+        );
+    }
+
+    #[test]
+    fn test_database_errors_retryable_only_for_connection_failures() {
+        assert!(Error::Database(sqlx::Error::PoolTimedOut).is_retryable());
+        assert!(!Error::Database(sqlx::Error::RowNotFound).is_retryable());
+    }
+
+    #[test]
+    fn test_anyhow_errors_are_not_retryable() {
+        assert!(!Error::Anyhow(anyhow::anyhow!("boom")).is_retryable());
+    }
+
+    #[test]
+    fn test_http_status() {
+        assert_eq!(
+            Error::InvalidArgument("bad id".to_string()).http_status(),
+            400
+        );
+        assert_eq!(
+            Error::BadRequest("unbalanced".to_string()).http_status(),
+            400
+        );
+        assert_eq!(Error::NotFound("account".to_string()).http_status(), 404);
+        assert_eq!(
+            Error::Conflict("stale version".to_string()).http_status(),
+            409
+        );
+        assert_eq!(Error::RateLimited { retry_after: None }.http_status(), 429);
+        assert_eq!(provider_error("HTTP_503").http_status(), 502);
+        assert_eq!(Error::Timeout("mtn".to_string()).http_status(), 504);
+        assert_eq!(Error::Unsupported("cancel".to_string()).http_status(), 501);
+        assert_eq!(Error::Internal("boom".to_string()).http_status(), 500);
+        assert_eq!(Error::Database(sqlx::Error::RowNotFound).http_status(), 500);
+        assert_eq!(Error::Anyhow(anyhow::anyhow!("boom")).http_status(), 500);
+    }
+
+    #[test]
+    fn test_error_body() {
+        let body = ErrorBody::from(&Error::NotFound("account 42".to_string()));
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: "NOT_FOUND".to_string(),
+                message: "not found: account 42".to_string(),
+                provider_code: None,
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&body).unwrap(),
+            r#"{"code":"NOT_FOUND","message":"not found: account 42"}"#
+        );
+
+        let body = ErrorBody::from(&provider_error("PAYER_NOT_FOUND"));
+        assert_eq!(body.code, "PROVIDER_ERROR");
+        assert_eq!(body.provider_code.as_deref(), Some("PAYER_NOT_FOUND"));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(
+            Error::InvalidArgument("bad id".to_string()).code(),
+            "INVALID_ARGUMENT"
+        );
+        assert_eq!(
+            Error::BadRequest("unbalanced".to_string()).code(),
+            "BAD_REQUEST"
+        );
+        assert_eq!(Error::NotFound("account".to_string()).code(), "NOT_FOUND");
+        assert_eq!(
+            Error::Conflict("stale version".to_string()).code(),
+            "CONFLICT"
+        );
+        assert_eq!(Error::Internal("boom".to_string()).code(), "INTERNAL");
+        assert_eq!(Error::Database(sqlx::Error::RowNotFound).code(), "INTERNAL");
+        assert_eq!(Error::Anyhow(anyhow::anyhow!("boom")).code(), "INTERNAL");
+        assert_eq!(provider_error("HTTP_503").code(), "PROVIDER_ERROR");
+        assert_eq!(Error::Timeout("mtn".to_string()).code(), "TIMEOUT");
+        assert_eq!(
+            Error::RateLimited { retry_after: None }.code(),
+            "RATE_LIMITED"
+        );
+        assert_eq!(
+            Error::Unsupported("cancel".to_string()).code(),
+            "UNSUPPORTED"
+        );
+    }
+
+    #[test]
+    fn test_error_codes_independent_of_message() {
+        let short = Error::NotFound("x".to_string());
+        let long = Error::NotFound("account 42 in ledger 7 was deleted".to_string());
+        assert_ne!(short.to_string(), long.to_string());
+        assert_eq!(short.code(), long.code());
+    }
+
+    #[test]
+    fn test_provider_code() {
+        assert_eq!(
+            provider_error("PAYER_NOT_FOUND").provider_code(),
+            Some("PAYER_NOT_FOUND")
+        );
+        assert_eq!(Error::Internal("boom".to_string()).provider_code(), None);
+    }
+}
+
+#[cfg(all(test, feature = "tonic"))]
+mod tonic_tests {
+    use super::*;
+    use tonic::Code;
+
+    #[test]
+    fn test_into_tonic_status() {
+        let cases = vec![
+            (
+                Error::InvalidArgument("bad id".to_string()),
+                Code::InvalidArgument,
+            ),
+            (
+                Error::BadRequest("unbalanced".to_string()),
+                Code::InvalidArgument,
+            ),
+            (Error::NotFound("account".to_string()), Code::NotFound),
+            (Error::Conflict("stale version".to_string()), Code::Aborted),
+            (
+                Error::Provider {
+                    code: "PAYER_NOT_FOUND".to_string(),
+                    message: "unknown payer".to_string(),
+                    kind: ProviderErrorKind::Terminal,
+                },
+                Code::FailedPrecondition,
+            ),
+            (
+                Error::Provider {
+                    code: "SERVICE_UNAVAILABLE".to_string(),
+                    message: "try again later".to_string(),
+                    kind: ProviderErrorKind::Transient,
+                },
+                Code::Unavailable,
+            ),
+            (Error::Timeout("mtn".to_string()), Code::DeadlineExceeded),
+            (
+                Error::RateLimited { retry_after: None },
+                Code::ResourceExhausted,
+            ),
+            (
+                Error::Unsupported("cancel".to_string()),
+                Code::Unimplemented,
+            ),
+            (Error::Internal("boom".to_string()), Code::Internal),
+            (Error::Anyhow(anyhow::anyhow!("boom")), Code::Internal),
+        ];
+
+        for (err, code) in cases {
+            assert_eq!(tonic::Status::from(err).code(), code);
+        }
+    }
+
+    #[test]
+    fn test_into_tonic_status_keeps_message() {
+        let status = tonic::Status::from(Error::NotFound("account 42".to_string()));
+        assert_eq!(status.message(), "account 42");
+    }
+}
+
+#[cfg(all(test, feature = "redis"))]
+mod redis_tests {
+    use super::*;
+    use redis::{ErrorKind, RedisError};
+    use std::io;
+
+    #[test]
+    fn test_redis_connection_errors_map_to_internal() {
+        let refused = RedisError::from(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+        let err = Error::from(refused);
+        assert!(matches!(err, Error::Internal(_)));
+        assert!(err.is_retryable());
+
+        let dropped = RedisError::from(io::Error::new(io::ErrorKind::ConnectionReset, "reset"));
+        assert!(matches!(Error::from(dropped), Error::Internal(_)));
+    }
+
+    #[test]
+    fn test_redis_timeout_maps_to_internal() {
+        let timeout = RedisError::from(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        let err = Error::from(timeout);
+        assert!(matches!(err, Error::Internal(_)));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_redis_command_errors_keep_context() {
+        let type_error = RedisError::from((ErrorKind::TypeError, "unexpected response type"));
+        let err = Error::from(type_error);
+        assert!(!err.is_retryable());
+        match err {
+            Error::Anyhow(e) => {
+                assert_eq!(e.to_string(), "redis command failed");
+                assert!(e
+                    .root_cause()
+                    .to_string()
+                    .contains("unexpected response type"));
+            }
+            other => panic!("expected Anyhow error, got {:?}", other),
+        }
+    }
+}
@@ -3,7 +3,9 @@
 
 //! A shared library for calculating various types of fees based on configurable rules.
 
-use psc_domain::Money;
+use psc_domain::{Currency, Money, MoneyError, RoundingMode};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -12,6 +14,29 @@ pub enum FeeError {
     InvalidPercentage(f64),
     #[error("Tiered fees must be sorted by threshold")]
     UnsortedTiers,
+    #[error("Fee currency {found} does not match amount currency {expected}")]
+    CurrencyMismatch { expected: String, found: String },
+    #[error("Tier starting at {from} ends below its start at {up_to}")]
+    InvalidTierBounds { from: Money, up_to: Money },
+    #[error("Tiers leave a gap between {up_to} and {from}")]
+    TierGap { up_to: Money, from: Money },
+    #[error("Tier ending at {up_to} overlaps the next tier starting at {from}")]
+    TierOverlap { up_to: Money, from: Money },
+    #[error("Cannot compute a fee rate for a zero amount")]
+    ZeroAmount,
+    #[error("Fee on {0} is too large to represent")]
+    Overflow(Money),
+}
+
+/// Returns `FeeError::CurrencyMismatch` unless `money` is in `currency`.
+fn ensure_currency(currency: Currency, money: &Money) -> Result<(), FeeError> {
+    if money.currency() != currency {
+        return Err(FeeError::CurrencyMismatch {
+            expected: currency.to_string(),
+            found: money.currency().to_string(),
+        });
+    }
+    Ok(())
 }
 
 /// Represents a rule for calculating a fee.
@@ -27,42 +52,162 @@ pub enum FeeRule {
         max: Option<Money>,
     },
     /// A fee that varies based on the transaction amount.
-    /// The tiers must be sorted by their `up_to` threshold.
+    /// The tiers must be sorted by their `up_to` threshold; build the rule with
+    /// [`FeeRule::tiered`] to also check that they are contiguous. Amounts above the
+    /// last tier pay its fee, amounts in no tier below it pay nothing.
     Tiered { tiers: Vec<Tier> },
+    /// A fixed base fee plus a percentage of the transaction amount, with the
+    /// total optionally capped at `max_total`.
+    /// The percentage should be between 0 and 100. The total is rounded half-even
+    /// to a whole minor unit before the cap is applied.
+    Composite {
+        base: Money,
+        percentage: Decimal,
+        max_total: Option<Money>,
+    },
 }
 
 /// Represents a single tier in a tiered fee structure.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Tier {
+    /// The lower bound for this tier (inclusive).
+    pub from: Money,
     /// The upper bound for this tier (inclusive).
     pub up_to: Money,
     /// The fee to apply for amounts within this tier.
     pub fee: Money,
 }
 
+/// Which limit of a percentage or composite rule replaced the computed fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeCap {
+    Min,
+    Max,
+}
+
+/// How a [`FeeRule`] arrived at a fee, as returned by [`FeeRule::explain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeExplanation {
+    /// Index of the tier that matched, for tiered rules.
+    pub matched_tier: Option<usize>,
+    /// The fee before `min` and `max` are applied, for percentage and composite rules.
+    pub uncapped_fee: Option<Money>,
+    /// The limit that replaced the uncapped fee, if any.
+    pub cap_applied: Option<FeeCap>,
+    /// The fee `calculate` would return.
+    pub fee: Money,
+}
+
+/// How [`calculate_fee_with_mode`] combines the fees of several rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeeApplyMode {
+    /// Add up the fees of all rules.
+    #[default]
+    Sum,
+    /// Use the fee of the first rule, in order, that yields a non-zero fee.
+    FirstMatch,
+    /// Use the largest fee of any single rule.
+    Max,
+}
+
 impl FeeRule {
+    /// Builds a tiered rule, checking that each tier starts one minor unit above
+    /// where the previous one ends, so every amount from the first tier's `from` up
+    /// to the last tier's `up_to` falls in exactly one tier.
+    ///
+    /// Returns `FeeError::InvalidTierBounds` for a tier ending below its start,
+    /// `FeeError::TierGap` or `FeeError::TierOverlap` for tiers that are not
+    /// contiguous, and `FeeError::CurrencyMismatch` for tiers in different currencies.
+    pub fn tiered(tiers: Vec<Tier>) -> Result<FeeRule, FeeError> {
+        if let Some(first) = tiers.first() {
+            let currency = first.from.currency();
+            for tier in &tiers {
+                ensure_currency(currency, &tier.from)?;
+                ensure_currency(currency, &tier.up_to)?;
+                ensure_currency(currency, &tier.fee)?;
+                if tier.up_to < tier.from {
+                    return Err(FeeError::InvalidTierBounds {
+                        from: tier.from,
+                        up_to: tier.up_to,
+                    });
+                }
+            }
+
+            let minor_unit = Money::from_minor_units(1, currency);
+            for pair in tiers.windows(2) {
+                let (up_to, from) = (pair[0].up_to, pair[1].from);
+                let expected_from = up_to + minor_unit;
+                if from > expected_from {
+                    return Err(FeeError::TierGap { up_to, from });
+                }
+                if from < expected_from {
+                    return Err(FeeError::TierOverlap { up_to, from });
+                }
+            }
+        }
+        Ok(FeeRule::Tiered { tiers })
+    }
+
     /// Calculates the fee for a given amount based on the rule.
     pub fn calculate(&self, amount: Money) -> Result<Money, FeeError> {
+        self.explain(amount).map(|explanation| explanation.fee)
+    }
+
+    /// Calculates the fee for a given amount and reports how the rule produced it,
+    /// without changing anything. Useful for previewing pricing.
+    ///
+    /// Every amount in the rule must be in the currency of `amount`, otherwise
+    /// `FeeError::CurrencyMismatch` is returned.
+    pub fn explain(&self, amount: Money) -> Result<FeeExplanation, FeeError> {
+        let currency = amount.currency();
         match self {
-            FeeRule::Fixed(fee) => Ok(*fee),
+            FeeRule::Fixed(fee) => {
+                ensure_currency(currency, fee)?;
+                Ok(FeeExplanation {
+                    matched_tier: None,
+                    uncapped_fee: None,
+                    cap_applied: None,
+                    fee: *fee,
+                })
+            }
             FeeRule::Percentage { value, min, max } => {
                 if !(0.0..=100.0).contains(value) {
                     return Err(FeeError::InvalidPercentage(*value));
                 }
-                let mut fee = amount.multiply_percent(*value);
+                for limit in min.iter().chain(max.iter()) {
+                    ensure_currency(currency, limit)?;
+                }
+                let uncapped_fee = amount.multiply_percent(*value).map_err(|e| match e {
+                    MoneyError::Overflow => FeeError::Overflow(amount),
+                    _ => FeeError::InvalidPercentage(*value),
+                })?;
+                let mut fee = uncapped_fee;
+                let mut cap_applied = None;
                 if let Some(min_fee) = min {
                     if fee < *min_fee {
                         fee = *min_fee;
+                        cap_applied = Some(FeeCap::Min);
                     }
                 }
                 if let Some(max_fee) = max {
                     if fee > *max_fee {
                         fee = *max_fee;
+                        cap_applied = Some(FeeCap::Max);
                     }
                 }
-                Ok(fee)
+                Ok(FeeExplanation {
+                    matched_tier: None,
+                    uncapped_fee: Some(uncapped_fee),
+                    cap_applied,
+                    fee,
+                })
             }
             FeeRule::Tiered { tiers } => {
+                for tier in tiers {
+                    ensure_currency(currency, &tier.from)?;
+                    ensure_currency(currency, &tier.up_to)?;
+                    ensure_currency(currency, &tier.fee)?;
+                }
                 // Ensure tiers are sorted
                 for i in 1..tiers.len() {
                     if tiers[i - 1].up_to > tiers[i].up_to {
@@ -70,16 +215,54 @@ impl FeeRule {
                     }
                 }
 
-                for tier in tiers {
-                    if amount <= tier.up_to {
-                        return Ok(tier.fee);
+                let index = match tiers
+                    .iter()
+                    .position(|tier| tier.from <= amount && amount <= tier.up_to)
+                {
+                    Some(index) => Some(index),
+                    // If amount is greater than all tiers, use the highest tier
+                    None if tiers.last().is_some_and(|tier| amount > tier.up_to) => {
+                        Some(tiers.len() - 1)
                     }
+                    // Amounts below the first tier, or between tiers, pay no fee
+                    None => None,
+                };
+                Ok(FeeExplanation {
+                    matched_tier: index,
+                    uncapped_fee: None,
+                    cap_applied: None,
+                    fee: index.map_or(Money::zero(currency), |index| tiers[index].fee),
+                })
+            }
+            FeeRule::Composite {
+                base,
+                percentage,
+                max_total,
+            } => {
+                if !(Decimal::ZERO..=Decimal::ONE_HUNDRED).contains(percentage) {
+                    return Err(FeeError::InvalidPercentage(
+                        percentage.to_f64().unwrap_or_default(),
+                    ));
+                }
+                ensure_currency(currency, base)?;
+                if let Some(max_total) = max_total {
+                    ensure_currency(currency, max_total)?;
                 }
-                // If amount is greater than all tiers, return the fee for the highest tier
-                tiers
-                    .last()
-                    .map(|t| t.fee)
-                    .ok_or_else(|| FeeError::UnsortedTiers) // Should not happen if tiers is not empty
+                let percentage_fee = amount
+                    .try_multiply_percent(*percentage)
+                    .map_err(|_| FeeError::Overflow(amount))?;
+                let uncapped_fee =
+                    (*base + percentage_fee).round_to_currency(RoundingMode::HalfEven);
+                let (fee, cap_applied) = match max_total {
+                    Some(max_total) if uncapped_fee > *max_total => (*max_total, Some(FeeCap::Max)),
+                    _ => (uncapped_fee, None),
+                };
+                Ok(FeeExplanation {
+                    matched_tier: None,
+                    uncapped_fee: Some(uncapped_fee),
+                    cap_applied,
+                    fee,
+                })
             }
         }
     }
@@ -94,67 +277,109 @@ impl FeeRule {
 ///
 /// # Returns
 ///
-/// The total calculated fee, or an error if any of the rules are invalid.
+/// The total calculated fee in the currency of `amount`, or an error if any of the
+/// rules are invalid or use a different currency.
 pub fn calculate_fee(amount: Money, rules: &[FeeRule]) -> Result<Money, FeeError> {
-    let mut total_fee = Money::zero("XAF");
+    calculate_fee_with_mode(amount, rules, FeeApplyMode::Sum)
+}
+
+/// Calculates the fee for a given amount, combining the rules' fees as `mode` says.
+///
+/// With `FeeApplyMode::FirstMatch` the rules after the first non-zero fee are not
+/// evaluated. When no rule yields a fee, the result is zero in every mode.
+pub fn calculate_fee_with_mode(
+    amount: Money,
+    rules: &[FeeRule],
+    mode: FeeApplyMode,
+) -> Result<Money, FeeError> {
+    let mut total_fee = Money::zero(amount.currency());
     for rule in rules {
-        total_fee = total_fee + rule.calculate(amount)?;
+        let fee = rule.calculate(amount)?;
+        match mode {
+            FeeApplyMode::Sum => total_fee += fee,
+            FeeApplyMode::FirstMatch => {
+                if !fee.is_zero() {
+                    return Ok(fee);
+                }
+            }
+            FeeApplyMode::Max => total_fee = total_fee.max(fee),
+        }
     }
     Ok(total_fee)
 }
 
+/// Computes the effective rate a customer paid, as a percentage of the transaction
+/// amount: a fee of 150 on 10,000 is a rate of `1.5`.
+///
+/// # Arguments
+///
+/// * `amount` - The transaction amount.
+/// * `total_fee` - The total fee charged on it, e.g. from `calculate_fee`.
+///
+/// # Returns
+///
+/// The unrounded percentage, or `FeeError::ZeroAmount` if `amount` is zero and
+/// `FeeError::CurrencyMismatch` if the fee is in a different currency.
+pub fn effective_rate(amount: Money, total_fee: Money) -> Result<Decimal, FeeError> {
+    ensure_currency(amount.currency(), &total_fee)?;
+    if amount.is_zero() {
+        return Err(FeeError::ZeroAmount);
+    }
+    Ok(total_fee.amount() * Decimal::ONE_HUNDRED / amount.amount())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use psc_domain::Money;
+    use psc_domain::{Currency, Money};
 
     #[test]
     fn test_fixed_fee() {
-        let amount = Money::new(10000, "XAF");
-        let rule = FeeRule::Fixed(Money::new(100, "XAF"));
+        let amount = Money::new(10000, Currency::XAF);
+        let rule = FeeRule::Fixed(Money::new(100, Currency::XAF));
         let fee = calculate_fee(amount, &[rule]).unwrap();
-        assert_eq!(fee, Money::new(100, "XAF"));
+        assert_eq!(fee, Money::new(100, Currency::XAF));
     }
 
     #[test]
     fn test_percentage_fee() {
-        let amount = Money::new(10000, "XAF");
+        let amount = Money::new(10000, Currency::XAF);
         let rule = FeeRule::Percentage {
             value: 1.5,
             min: None,
             max: None,
         };
         let fee = calculate_fee(amount, &[rule]).unwrap();
-        assert_eq!(fee, Money::new(150, "XAF"));
+        assert_eq!(fee, Money::new(150, Currency::XAF));
     }
 
     #[test]
     fn test_percentage_fee_with_min_cap() {
-        let amount = Money::new(1000, "XAF");
+        let amount = Money::new(1000, Currency::XAF);
         let rule = FeeRule::Percentage {
             value: 1.0,
-            min: Some(Money::new(50, "XAF")),
+            min: Some(Money::new(50, Currency::XAF)),
             max: None,
         };
         let fee = calculate_fee(amount, &[rule]).unwrap();
-        assert_eq!(fee, Money::new(50, "XAF"));
+        assert_eq!(fee, Money::new(50, Currency::XAF));
     }
 
     #[test]
     fn test_percentage_fee_with_max_cap() {
-        let amount = Money::new(100000, "XAF");
+        let amount = Money::new(100000, Currency::XAF);
         let rule = FeeRule::Percentage {
             value: 2.0,
             min: None,
-            max: Some(Money::new(1500, "XAF")),
+            max: Some(Money::new(1500, Currency::XAF)),
         };
         let fee = calculate_fee(amount, &[rule]).unwrap();
-        assert_eq!(fee, Money::new(1500, "XAF"));
+        assert_eq!(fee, Money::new(1500, Currency::XAF));
     }
 
     #[test]
     fn test_invalid_percentage() {
-        let amount = Money::new(10000, "XAF");
+        let amount = Money::new(10000, Currency::XAF);
         let rule = FeeRule::Percentage {
             value: 101.0,
             min: None,
@@ -168,56 +393,206 @@ mod tests {
     fn test_tiered_fee() {
         let tiers = vec![
             Tier {
-                up_to: Money::new(5000, "XAF"),
-                fee: Money::new(50, "XAF"),
+                from: Money::new(0, Currency::XAF),
+                up_to: Money::new(5000, Currency::XAF),
+                fee: Money::new(50, Currency::XAF),
             },
             Tier {
-                up_to: Money::new(20000, "XAF"),
-                fee: Money::new(100, "XAF"),
+                from: Money::new(5001, Currency::XAF),
+                up_to: Money::new(20000, Currency::XAF),
+                fee: Money::new(100, Currency::XAF),
             },
             Tier {
-                up_to: Money::new(50000, "XAF"),
-                fee: Money::new(200, "XAF"),
+                from: Money::new(20001, Currency::XAF),
+                up_to: Money::new(50000, Currency::XAF),
+                fee: Money::new(200, Currency::XAF),
             },
         ];
         let rule = FeeRule::Tiered { tiers };
 
-        let amount1 = Money::new(4000, "XAF");
-        let fee1 = calculate_fee(amount1, &[rule.clone()]).unwrap();
-        assert_eq!(fee1, Money::new(50, "XAF"));
+        let amount1 = Money::new(4000, Currency::XAF);
+        let fee1 = calculate_fee(amount1, std::slice::from_ref(&rule)).unwrap();
+        assert_eq!(fee1, Money::new(50, Currency::XAF));
+
+        let amount2 = Money::new(20000, Currency::XAF);
+        let fee2 = calculate_fee(amount2, std::slice::from_ref(&rule)).unwrap();
+        assert_eq!(fee2, Money::new(100, Currency::XAF));
+
+        let amount3 = Money::new(60000, Currency::XAF);
+        let fee3 = calculate_fee(amount3, std::slice::from_ref(&rule)).unwrap();
+        assert_eq!(fee3, Money::new(200, Currency::XAF));
+    }
+
+    #[test]
+    fn test_explain_percentage_fee_with_max_cap() {
+        let rule = FeeRule::Percentage {
+            value: 2.0,
+            min: Some(Money::new(50, Currency::XAF)),
+            max: Some(Money::new(1500, Currency::XAF)),
+        };
+        let explanation = rule.explain(Money::new(100000, Currency::XAF)).unwrap();
+        assert_eq!(explanation.matched_tier, None);
+        assert_eq!(
+            explanation.uncapped_fee,
+            Some(Money::new(2000, Currency::XAF))
+        );
+        assert_eq!(explanation.cap_applied, Some(FeeCap::Max));
+        assert_eq!(explanation.fee, Money::new(1500, Currency::XAF));
+
+        let explanation = rule.explain(Money::new(10000, Currency::XAF)).unwrap();
+        assert_eq!(
+            explanation.uncapped_fee,
+            Some(Money::new(200, Currency::XAF))
+        );
+        assert_eq!(explanation.cap_applied, None);
+        assert_eq!(explanation.fee, Money::new(200, Currency::XAF));
+    }
+
+    #[test]
+    fn test_explain_tiered_fee() {
+        let rule = FeeRule::Tiered {
+            tiers: vec![
+                Tier {
+                    from: Money::new(0, Currency::XAF),
+                    up_to: Money::new(5000, Currency::XAF),
+                    fee: Money::new(50, Currency::XAF),
+                },
+                Tier {
+                    from: Money::new(5001, Currency::XAF),
+                    up_to: Money::new(20000, Currency::XAF),
+                    fee: Money::new(100, Currency::XAF),
+                },
+            ],
+        };
+
+        let explanation = rule.explain(Money::new(6000, Currency::XAF)).unwrap();
+        assert_eq!(explanation.matched_tier, Some(1));
+        assert_eq!(explanation.uncapped_fee, None);
+        assert_eq!(explanation.cap_applied, None);
+        assert_eq!(explanation.fee, Money::new(100, Currency::XAF));
+
+        let explanation = rule.explain(Money::new(60000, Currency::XAF)).unwrap();
+        assert_eq!(explanation.matched_tier, Some(1));
+        assert_eq!(explanation.fee, Money::new(100, Currency::XAF));
+    }
+
+    #[test]
+    fn test_explain_tiered_fee_below_first_tier() {
+        let rule = FeeRule::Tiered {
+            tiers: vec![
+                Tier {
+                    from: Money::new(1000, Currency::XAF),
+                    up_to: Money::new(5000, Currency::XAF),
+                    fee: Money::new(50, Currency::XAF),
+                },
+                Tier {
+                    from: Money::new(5001, Currency::XAF),
+                    up_to: Money::new(20000, Currency::XAF),
+                    fee: Money::new(100, Currency::XAF),
+                },
+            ],
+        };
+
+        let explanation = rule.explain(Money::new(500, Currency::XAF)).unwrap();
+        assert_eq!(explanation.matched_tier, None);
+        assert_eq!(explanation.fee, Money::zero(Currency::XAF));
+        assert_eq!(
+            rule.calculate(Money::new(500, Currency::XAF)).unwrap(),
+            Money::zero(Currency::XAF)
+        );
+
+        let explanation = rule.explain(Money::new(1000, Currency::XAF)).unwrap();
+        assert_eq!(explanation.matched_tier, Some(0));
+        assert_eq!(explanation.fee, Money::new(50, Currency::XAF));
+    }
+
+    fn composite(percentage: Decimal) -> FeeRule {
+        FeeRule::Composite {
+            base: Money::new(100, Currency::XAF),
+            percentage,
+            max_total: Some(Money::new(1000, Currency::XAF)),
+        }
+    }
+
+    #[test]
+    fn test_composite_fee_below_cap() {
+        let explanation = composite(Decimal::new(15, 1))
+            .explain(Money::new(20000, Currency::XAF))
+            .unwrap();
+        assert_eq!(explanation.cap_applied, None);
+        assert_eq!(explanation.fee, Money::new(400, Currency::XAF));
+
+        // 100 + 300.495 rounds half-even to a whole franc
+        let fee = composite(Decimal::new(15, 1))
+            .calculate(Money::new(20033, Currency::XAF))
+            .unwrap();
+        assert_eq!(fee, Money::new(400, Currency::XAF));
+        assert_eq!(
+            composite(Decimal::new(1001, 1)).calculate(Money::new(20000, Currency::XAF)),
+            Err(FeeError::InvalidPercentage(100.1))
+        );
+    }
+
+    #[test]
+    fn test_composite_fee_caps_total() {
+        let rule = composite(Decimal::new(15, 1));
+
+        let explanation = rule.explain(Money::new(60000, Currency::XAF)).unwrap();
+        assert_eq!(explanation.cap_applied, None);
+        assert_eq!(explanation.fee, Money::new(1000, Currency::XAF));
+
+        let explanation = rule.explain(Money::new(100000, Currency::XAF)).unwrap();
+        assert_eq!(
+            explanation.uncapped_fee,
+            Some(Money::new(1600, Currency::XAF))
+        );
+        assert_eq!(explanation.cap_applied, Some(FeeCap::Max));
+        assert_eq!(explanation.fee, Money::new(1000, Currency::XAF));
+    }
 
-        let amount2 = Money::new(20000, "XAF");
-        let fee2 = calculate_fee(amount2, &[rule.clone()]).unwrap();
-        assert_eq!(fee2, Money::new(100, "XAF"));
+    #[test]
+    fn test_composite_fee_on_near_max_amount() {
+        let amount = Money::from_decimal_str(&Decimal::MAX.to_string(), Currency::XAF).unwrap();
 
-        let amount3 = Money::new(60000, "XAF");
-        let fee3 = calculate_fee(amount3, &[rule.clone()]).unwrap();
-        assert_eq!(fee3, Money::new(200, "XAF"));
+        let fee = composite(Decimal::new(15, 1)).calculate(amount).unwrap();
+        assert_eq!(fee, Money::new(1000, Currency::XAF));
+    }
+
+    #[test]
+    fn test_composite_fee_with_zero_percentage_is_base_only() {
+        let fee = calculate_fee(
+            Money::new(100000, Currency::XAF),
+            &[composite(Decimal::ZERO)],
+        )
+        .unwrap();
+        assert_eq!(fee, Money::new(100, Currency::XAF));
     }
 
     #[test]
     fn test_unsorted_tiers() {
         let tiers = vec![
             Tier {
-                up_to: Money::new(20000, "XAF"),
-                fee: Money::new(100, "XAF"),
+                from: Money::new(5001, Currency::XAF),
+                up_to: Money::new(20000, Currency::XAF),
+                fee: Money::new(100, Currency::XAF),
             },
             Tier {
-                up_to: Money::new(5000, "XAF"),
-                fee: Money::new(50, "XAF"),
+                from: Money::new(0, Currency::XAF),
+                up_to: Money::new(5000, Currency::XAF),
+                fee: Money::new(50, Currency::XAF),
             },
         ];
         let rule = FeeRule::Tiered { tiers };
-        let amount = Money::new(4000, "XAF");
+        let amount = Money::new(4000, Currency::XAF);
         let result = calculate_fee(amount, &[rule]);
         assert_eq!(result, Err(FeeError::UnsortedTiers));
     }
 
     #[test]
     fn test_combined_fees() {
-        let amount = Money::new(10000, "XAF");
+        let amount = Money::new(10000, Currency::XAF);
         let rules = vec![
-            FeeRule::Fixed(Money::new(25, "XAF")),
+            FeeRule::Fixed(Money::new(25, Currency::XAF)),
             FeeRule::Percentage {
                 value: 1.0,
                 min: None,
@@ -225,14 +600,14 @@ mod tests {
             },
         ];
         let fee = calculate_fee(amount, &rules).unwrap();
-        assert_eq!(fee, Money::new(125, "XAF"));
+        assert_eq!(fee, Money::new(125, Currency::XAF));
     }
 
     #[test]
     fn test_zero_amount() {
-        let amount = Money::zero("XAF");
+        let amount = Money::zero(Currency::XAF);
         let rules = vec![
-            FeeRule::Fixed(Money::new(50, "XAF")),
+            FeeRule::Fixed(Money::new(50, Currency::XAF)),
             FeeRule::Percentage {
                 value: 2.0,
                 min: None,
@@ -240,6 +615,215 @@ mod tests {
             },
         ];
         let fee = calculate_fee(amount, &rules).unwrap();
-        assert_eq!(fee, Money::new(50, "XAF"));
+        assert_eq!(fee, Money::new(50, Currency::XAF));
+    }
+
+    #[test]
+    fn test_fixed_fee_in_other_currency_is_rejected() {
+        let amount = Money::new(10000, Currency::XAF);
+        let rules = vec![
+            FeeRule::Fixed(Money::new(25, Currency::XAF)),
+            FeeRule::Fixed(Money::new(100, Currency::USD)),
+        ];
+        let error = calculate_fee(amount, &rules).unwrap_err();
+        assert_eq!(
+            error,
+            FeeError::CurrencyMismatch {
+                expected: "XAF".to_string(),
+                found: "USD".to_string(),
+            }
+        );
+        let message = error.to_string();
+        assert!(message.contains("XAF") && message.contains("USD"));
+    }
+
+    #[test]
+    fn test_capped_fee_in_other_currency_is_rejected() {
+        let rule = FeeRule::Percentage {
+            value: 1.0,
+            min: None,
+            max: Some(Money::new(1500, Currency::EUR)),
+        };
+        assert_eq!(
+            rule.calculate(Money::new(10000, Currency::USD)),
+            Err(FeeError::CurrencyMismatch {
+                expected: "USD".to_string(),
+                found: "EUR".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_total_fee_is_in_amount_currency() {
+        let amount = Money::new(10000, Currency::USD);
+        let fee = calculate_fee(amount, &[FeeRule::Fixed(Money::new(30, Currency::USD))]).unwrap();
+        assert_eq!(fee, Money::new(30, Currency::USD));
+        assert_eq!(
+            calculate_fee(amount, &[]).unwrap(),
+            Money::zero(Currency::USD)
+        );
+    }
+
+    fn tier(from: i64, up_to: i64, fee: i64) -> Tier {
+        Tier {
+            from: Money::from_minor_units(from, Currency::USD),
+            up_to: Money::from_minor_units(up_to, Currency::USD),
+            fee: Money::from_minor_units(fee, Currency::USD),
+        }
+    }
+
+    #[test]
+    fn test_tiered_accepts_contiguous_tiers() {
+        let tiers = vec![tier(0, 5000, 50), tier(5001, 20000, 100)];
+        let rule = FeeRule::tiered(tiers.clone()).unwrap();
+        assert_eq!(rule, FeeRule::Tiered { tiers });
+        assert_eq!(
+            rule.calculate(Money::from_minor_units(5001, Currency::USD)),
+            Ok(Money::from_minor_units(100, Currency::USD))
+        );
+        assert!(FeeRule::tiered(Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn test_tiered_rejects_overlapping_tiers() {
+        let result = FeeRule::tiered(vec![tier(0, 5000, 50), tier(4000, 20000, 100)]);
+        assert_eq!(
+            result,
+            Err(FeeError::TierOverlap {
+                up_to: Money::from_minor_units(5000, Currency::USD),
+                from: Money::from_minor_units(4000, Currency::USD),
+            })
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Tier ending at 50.00 USD overlaps the next tier starting at 40.00 USD"
+        );
+    }
+
+    #[test]
+    fn test_tiered_rejects_gap_between_tiers() {
+        let result = FeeRule::tiered(vec![tier(0, 5000, 50), tier(6000, 20000, 100)]);
+        assert_eq!(
+            result,
+            Err(FeeError::TierGap {
+                up_to: Money::from_minor_units(5000, Currency::USD),
+                from: Money::from_minor_units(6000, Currency::USD),
+            })
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Tiers leave a gap between 50.00 USD and 60.00 USD"
+        );
+    }
+
+    #[test]
+    fn test_tiered_rejects_inverted_tier() {
+        assert!(matches!(
+            FeeRule::tiered(vec![tier(5000, 0, 50)]),
+            Err(FeeError::InvalidTierBounds { .. })
+        ));
+    }
+
+    fn fixed_and_percentage() -> Vec<FeeRule> {
+        vec![
+            FeeRule::Fixed(Money::new(100, Currency::XAF)),
+            FeeRule::Percentage {
+                value: 1.5,
+                min: None,
+                max: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_calculate_fee_with_mode_sum() {
+        let amount = Money::new(10000, Currency::XAF);
+        let fee = calculate_fee_with_mode(amount, &fixed_and_percentage(), FeeApplyMode::Sum);
+        assert_eq!(fee, Ok(Money::new(250, Currency::XAF)));
+        assert_eq!(fee, calculate_fee(amount, &fixed_and_percentage()));
+    }
+
+    #[test]
+    fn test_calculate_fee_with_mode_first_match() {
+        let amount = Money::new(10000, Currency::XAF);
+        let mut rules = fixed_and_percentage();
+        assert_eq!(
+            calculate_fee_with_mode(amount, &rules, FeeApplyMode::FirstMatch),
+            Ok(Money::new(100, Currency::XAF))
+        );
+
+        // A zero fee does not match, so the percentage rule wins
+        rules[0] = FeeRule::Fixed(Money::zero(Currency::XAF));
+        assert_eq!(
+            calculate_fee_with_mode(amount, &rules, FeeApplyMode::FirstMatch),
+            Ok(Money::new(150, Currency::XAF))
+        );
+
+        // Rules after the match are not evaluated
+        let rules = vec![
+            FeeRule::Fixed(Money::new(100, Currency::XAF)),
+            FeeRule::Percentage {
+                value: 101.0,
+                min: None,
+                max: None,
+            },
+        ];
+        assert_eq!(
+            calculate_fee_with_mode(amount, &rules, FeeApplyMode::FirstMatch),
+            Ok(Money::new(100, Currency::XAF))
+        );
+    }
+
+    #[test]
+    fn test_calculate_fee_with_mode_max() {
+        let rules = fixed_and_percentage();
+        assert_eq!(
+            calculate_fee_with_mode(Money::new(10000, Currency::XAF), &rules, FeeApplyMode::Max),
+            Ok(Money::new(150, Currency::XAF))
+        );
+        assert_eq!(
+            calculate_fee_with_mode(Money::new(1000, Currency::XAF), &rules, FeeApplyMode::Max),
+            Ok(Money::new(100, Currency::XAF))
+        );
+        assert_eq!(
+            calculate_fee_with_mode(Money::new(1000, Currency::XAF), &[], FeeApplyMode::Max),
+            Ok(Money::zero(Currency::XAF))
+        );
+    }
+
+    #[test]
+    fn test_effective_rate() {
+        // 2.75 USD on 110.00 USD: 2.75 / 110 * 100 = 2.5%
+        let rate = effective_rate(
+            Money::from_minor_units(11000, Currency::USD),
+            Money::from_minor_units(275, Currency::USD),
+        );
+        assert_eq!(rate, Ok(Decimal::new(25, 1)));
+
+        let amount = Money::new(10000, Currency::XAF);
+        let fee = calculate_fee(amount, &fixed_and_percentage()).unwrap();
+        assert_eq!(effective_rate(amount, fee), Ok(Decimal::new(25, 1)));
+    }
+
+    #[test]
+    fn test_effective_rate_rejects_zero_amount() {
+        assert_eq!(
+            effective_rate(Money::zero(Currency::XAF), Money::new(100, Currency::XAF)),
+            Err(FeeError::ZeroAmount)
+        );
+    }
+
+    #[test]
+    fn test_effective_rate_rejects_currency_mismatch() {
+        assert_eq!(
+            effective_rate(
+                Money::new(10000, Currency::XAF),
+                Money::new(1, Currency::USD)
+            ),
+            Err(FeeError::CurrencyMismatch {
+                expected: "XAF".to_string(),
+                found: "USD".to_string(),
+            })
+        );
     }
 }
@@ -0,0 +1,166 @@
+//! Short-lived caching of query results.
+//!
+//! Values are opaque bytes so that callers can cache whatever encoding suits them
+//! (JSON, protobuf, ...) and the trait stays object safe.
+
+use crate::{retry_transient, RedisIdempotencyStore};
+use async_trait::async_trait;
+use psc_error::Error;
+use psc_retry::RetryPolicy;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Key/value cache with per-entry expiry.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    /// Retrieve the value stored for `key`.
+    ///
+    /// Returns `None` if nothing was stored or the entry has expired.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Store `value` for `key`, replacing any previous value, for `ttl`.
+    ///
+    /// A zero `ttl` stores nothing.
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), Error>;
+}
+
+/// Redis-backed cache.
+///
+/// Transient Redis errors are retried like in [`RedisIdempotencyStore`].
+pub struct RedisCache {
+    client: redis::Client,
+    retry_policy: RetryPolicy,
+}
+
+impl RedisCache {
+    /// Create a new Redis cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Redis client cannot be created
+    pub fn new(redis_url: &str) -> Result<Self, Error> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self {
+            client,
+            retry_policy: RedisIdempotencyStore::default_retry_policy(),
+        })
+    }
+
+    /// Set the policy used to retry transient Redis errors.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            Ok(conn.get(key).await?)
+        })
+        .await
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), Error> {
+        if ttl.is_zero() {
+            return Ok(());
+        }
+        let value = &value;
+
+        retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            redis::cmd("SET")
+                .arg(key)
+                .arg(value)
+                .arg("PX")
+                .arg(ttl.as_millis() as u64)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// In-process cache, for tests and single-instance deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryCache {
+    entries: Mutex<HashMap<String, (Vec<u8>, Instant)>>,
+}
+
+impl InMemoryCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Cache for InMemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        match entries.get(key) {
+            Some((value, expires_at)) if *expires_at > Instant::now() => Ok(Some(value.clone())),
+            Some(_) => {
+                entries.remove(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> Result<(), Error> {
+        if ttl.is_zero() {
+            return Ok(());
+        }
+
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key.to_string(), (value, Instant::now() + ttl));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_cache_round_trip() {
+        let cache = InMemoryCache::new();
+        assert_eq!(cache.get("balance").await.unwrap(), None);
+
+        cache
+            .set("balance", b"100".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(cache.get("balance").await.unwrap(), Some(b"100".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_cache_expires_entries() {
+        let cache = InMemoryCache::new();
+        cache
+            .set("balance", b"100".to_vec(), Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(cache.get("balance").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_cache_ignores_zero_ttl() {
+        let cache = InMemoryCache::new();
+        cache
+            .set("balance", b"100".to_vec(), Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(cache.get("balance").await.unwrap(), None);
+    }
+}
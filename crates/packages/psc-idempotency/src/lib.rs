@@ -7,7 +7,7 @@
 //! # Example
 //!
 //! ```no_run
-//! use psc_idempotency::{IdempotencyStore, RedisIdempotencyStore};
+//! use psc_idempotency::{IdempotencyStore, Outcome, RedisIdempotencyStore};
 //! use serde::{Deserialize, Serialize};
 //!
 //! #[derive(Serialize, Deserialize, Debug)]
@@ -32,7 +32,8 @@
 //!         println!("Result was stored for the first time");
 //!     } else {
 //!         println!("Result was already stored, retrieving existing result");
-//!         let existing_result: Option<PaymentResult> = store.get_result("payment_123").await?;
+//!         let existing_result: Option<Outcome<PaymentResult>> =
+//!             store.get_result("payment_123").await?;
 //!         println!("Existing result: {:?}", existing_result);
 //!     }
 //!
@@ -42,8 +43,106 @@
 
 use async_trait::async_trait;
 use psc_error::Error;
-use redis::AsyncCommands;
+use psc_retry::{do_with_retry_if, RetryError, RetryPolicy};
+use redis::{AsyncCommands, IntoConnectionInfo};
 use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::time::Duration;
+
+pub mod cache;
+pub mod reservation;
+pub mod retry;
+
+pub use cache::{Cache, InMemoryCache, RedisCache};
+pub use reservation::{InMemoryReservations, RedisReservations, Reservations};
+pub use retry::retry_idempotent;
+
+/// What an idempotency store remembers for a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<T> {
+    /// The stored result of the operation.
+    Found(T),
+    /// The operation was recorded as having no result yet, see
+    /// [`IdempotencyStore::check_and_set_negative`].
+    NegativeCached,
+    /// The operation was claimed with [`IdempotencyStore::begin`] and has not
+    /// completed, or its lock has not expired yet.
+    InProgress,
+}
+
+impl<T> Outcome<T> {
+    /// The stored result, if there is one.
+    pub fn found(self) -> Option<T> {
+        match self {
+            Outcome::Found(result) => Some(result),
+            Outcome::NegativeCached | Outcome::InProgress => None,
+        }
+    }
+}
+
+/// Value stored for negative results. It is not valid JSON, so it cannot collide
+/// with a serialized result.
+pub(crate) const NEGATIVE_TOMBSTONE: &str = "!negative";
+
+/// Value stored while an operation is in progress, see [`IdempotencyStore::begin`].
+pub(crate) const IN_PROGRESS_MARKER: &str = "!in-progress";
+
+/// Stores `ARGV[1]` under `KEYS[1]` for `ARGV[2]` seconds unless the key holds a
+/// value other than one of the replaceable markers in `ARGV[3..]`.
+const SET_UNLESS_RESULT_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current then
+    local replaceable = false
+    for i = 3, #ARGV do
+        if current == ARGV[i] then
+            replaceable = true
+        end
+    end
+    if not replaceable then
+        return 0
+    end
+end
+redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+return 1
+"#;
+
+/// Sets `KEYS[1]` to `ARGV[1]` with a TTL of `ARGV[2]` seconds if it holds `ARGV[3]`.
+const SET_IF_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[3] then
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+/// Deletes `KEYS[1]` if it holds `ARGV[1]`.
+const DELETE_IF_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Default limit on the size of a serialized result, in bytes.
+pub const DEFAULT_MAX_VALUE_BYTES: usize = 256 * 1024;
+
+/// How many keys each `SCAN` call asks Redis to examine when listing keys.
+const SCAN_BATCH_SIZE: usize = 500;
+
+/// Escape the glob metacharacters in `prefix` so it matches literally in `SCAN MATCH`.
+fn escape_glob(prefix: &str) -> String {
+    let mut escaped = String::with_capacity(prefix.len());
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
 
 /// Trait for idempotency store implementations.
 ///
@@ -54,7 +153,9 @@ pub trait IdempotencyStore {
     /// Store a result for an idempotency key if it doesn't already exist.
     ///
     /// Returns `true` if the result was stored, `false` if a result was
-    /// already stored for the key.
+    /// already stored for the key or the operation is in progress. A negative
+    /// result is replaced. Use [`complete`](Self::complete) to store the result of
+    /// an operation claimed with [`begin`](Self::begin).
     ///
     /// # Parameters
     ///
@@ -68,15 +169,103 @@ pub trait IdempotencyStore {
         ttl_seconds: usize,
     ) -> Result<bool, Error>;
 
+    /// Remember that the operation for an idempotency key has no result yet, such
+    /// as a provider answering "not found", so it is not re-queried for a while.
+    ///
+    /// Returns `true` if the negative result was stored, `false` if something was
+    /// already stored for the key. A later [`check_and_set`](Self::check_and_set)
+    /// replaces it.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The idempotency key
+    /// * `ttl_seconds` - How long to remember the negative result, in seconds
+    async fn check_and_set_negative(&self, key: &str, ttl_seconds: usize) -> Result<bool, Error>;
+
+    /// Claim the operation for an idempotency key before running it, so concurrent
+    /// callers see it as in progress rather than running it too.
+    ///
+    /// Returns `true` if the claim was made, `false` if the operation is already in
+    /// progress or has a stored result. A negative result is replaced. Complete the
+    /// operation with [`complete`](Self::complete), passing the result TTL, or give
+    /// the claim up with [`release`](Self::release).
+    ///
+    /// The lock TTL only bounds how long the in-progress marker is held. Keep it
+    /// short, so that if the worker crashes the key can be claimed again soon,
+    /// while the result TTL decides how long the completed result is remembered.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The idempotency key
+    /// * `lock_ttl_seconds` - How long to hold the in-progress marker, in seconds
+    async fn begin(&self, key: &str, lock_ttl_seconds: usize) -> Result<bool, Error>;
+
+    /// Store the result of an operation claimed with [`begin`](Self::begin).
+    ///
+    /// Returns `true` if the result was stored, `false` if the key no longer holds
+    /// an in-progress marker, e.g. because the lock expired and another caller
+    /// stored a result first. Unlike [`check_and_set`](Self::check_and_set), this
+    /// never writes over a key that was not claimed.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The idempotency key
+    /// * `result` - The result to store
+    /// * `ttl_seconds` - Time-to-live for the stored result in seconds
+    async fn complete<T: Serialize + Send + Sync>(
+        &self,
+        key: &str,
+        result: &T,
+        ttl_seconds: usize,
+    ) -> Result<bool, Error>;
+
+    /// Give up a claim made with [`begin`](Self::begin), e.g. because the operation
+    /// failed, so the key can be claimed again without waiting for the lock to
+    /// expire.
+    ///
+    /// Returns `true` if an in-progress marker was removed. Results and negative
+    /// results are left alone.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The idempotency key
+    async fn release(&self, key: &str) -> Result<bool, Error>;
+
     /// Retrieve a result for an idempotency key.
     ///
-    /// Returns `Some(result)` if a result was stored for the key,
-    /// `None` if no result was found.
+    /// Returns `Some(Outcome::Found(result))` if a result was stored for the key,
+    /// `Some(Outcome::NegativeCached)` if a negative result was stored,
+    /// `Some(Outcome::InProgress)` if the operation is in progress, and `None` if
+    /// nothing was found.
     ///
     /// # Parameters
     ///
     /// * `key` - The idempotency key
-    async fn get_result<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error>;
+    async fn get_result<T: DeserializeOwned>(&self, key: &str)
+        -> Result<Option<Outcome<T>>, Error>;
+
+    /// How long whatever is stored for an idempotency key is kept for, whether a
+    /// result, a negative result or an in-progress marker.
+    ///
+    /// Returns `None` if nothing is stored for the key. Useful when debugging, and
+    /// for deciding whether a lock from [`begin`](Self::begin) is about to expire.
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - The idempotency key
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>, Error>;
+}
+
+/// What [`RedisIdempotencyStore`] does when Redis cannot be reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+    /// Proceed without idempotency: lookups find nothing and `check_and_set`
+    /// reports the key as new. Only suitable for flows where a duplicate is
+    /// acceptable.
+    FailOpen,
+    /// Return the error to the caller.
+    #[default]
+    FailClosed,
 }
 
 /// Redis-based implementation of the idempotency store.
@@ -84,8 +273,16 @@ pub trait IdempotencyStore {
 /// This implementation uses Redis to store results associated with
 /// idempotency keys. Results are stored with a TTL (time-to-live)
 /// to prevent indefinite storage.
+///
+/// Transient failures (connection refused or dropped, timeouts) are retried with
+/// exponential backoff according to the store's [`RetryPolicy`]; command errors
+/// such as `WRONGTYPE` are returned immediately.
 pub struct RedisIdempotencyStore {
     client: redis::Client,
+    retry_policy: RetryPolicy,
+    hash_keys: bool,
+    failure_policy: FailurePolicy,
+    max_value_bytes: usize,
 }
 
 impl RedisIdempotencyStore {
@@ -103,11 +300,235 @@ impl RedisIdempotencyStore {
     ///
     /// Returns an error if the Redis client cannot be created
     pub fn new(redis_url: &str) -> Result<Self, Error> {
-        let client = redis::Client::open(redis_url).map_err(|e| Error::Internal(e.to_string()))?;
-        Ok(Self { client })
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self::from_client(client))
+    }
+
+    /// Create a new Redis idempotency store using logical database `db_index`,
+    /// overriding any database given in the URL.
+    ///
+    /// Keeps idempotency data apart from other users of a shared Redis instance.
+    /// Every connection the store opens selects the database before use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL cannot be parsed
+    pub fn with_db(redis_url: &str, db_index: i64) -> Result<Self, Error> {
+        let mut connection_info = redis_url.into_connection_info()?;
+        connection_info.redis.db = db_index;
+        let client = redis::Client::open(connection_info)?;
+        Ok(Self::from_client(client))
+    }
+
+    fn from_client(client: redis::Client) -> Self {
+        Self {
+            client,
+            retry_policy: Self::default_retry_policy(),
+            hash_keys: false,
+            failure_policy: FailurePolicy::default(),
+            max_value_bytes: DEFAULT_MAX_VALUE_BYTES,
+        }
+    }
+
+    /// Set the policy used to retry transient Redis errors.
+    ///
+    /// The default makes two quick retries, keeping the added latency small since
+    /// the store sits on the request path. Use `with_max_retries(0)` to disable
+    /// retries.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Store results under the SHA-256 hash of the idempotency key rather than the
+    /// key itself.
+    ///
+    /// Keeps Redis keys short when callers use long keys such as full request URIs.
+    /// Callers keep passing the raw key. Results stored with hashing off are not
+    /// found with it on, and vice versa.
+    pub fn with_key_hashing(mut self, hash_keys: bool) -> Self {
+        self.hash_keys = hash_keys;
+        self
+    }
+
+    /// Set what happens when Redis is unreachable once retries are exhausted.
+    ///
+    /// Defaults to [`FailurePolicy::FailClosed`]. Command errors are returned
+    /// regardless of the policy.
+    pub fn with_failure_policy(mut self, failure_policy: FailurePolicy) -> Self {
+        self.failure_policy = failure_policy;
+        self
+    }
+
+    /// Set the largest serialized result, in bytes, that `check_and_set` stores.
+    ///
+    /// Defaults to [`DEFAULT_MAX_VALUE_BYTES`]. Larger results are rejected with
+    /// `Error::BadRequest` before anything is written to Redis.
+    pub fn with_max_value_bytes(mut self, max_value_bytes: usize) -> Self {
+        self.max_value_bytes = max_value_bytes;
+        self
+    }
+
+    /// Apply the failure policy to the result of a Redis operation, substituting
+    /// `fallback` for transient errors when failing open.
+    fn apply_failure_policy<T>(
+        &self,
+        key: &str,
+        result: Result<T, Error>,
+        fallback: T,
+    ) -> Result<T, Error> {
+        match result {
+            Err(e) if self.failure_policy == FailurePolicy::FailOpen && e.is_retryable() => {
+                tracing::warn!(
+                    error = %e,
+                    key,
+                    "Redis unavailable, proceeding without idempotency"
+                );
+                Ok(fallback)
+            }
+            result => result,
+        }
+    }
+
+    /// The Redis key a result for `key` is stored under.
+    fn storage_key<'k>(&self, key: &'k str) -> Cow<'k, str> {
+        if self.hash_keys {
+            Cow::Owned(hex::encode(Sha256::digest(key.as_bytes())))
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+
+    /// Connect to Redis and send a `PING`.
+    ///
+    /// [`RedisIdempotencyStore::new`] only parses the URL, so call this at startup to
+    /// fail fast on an unreachable or misconfigured server. Transient errors are not
+    /// retried here.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be established or the server does
+    /// not answer the ping
+    pub async fn connect(&self) -> Result<(), Error> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// List up to `limit` stored keys starting with `prefix`, for operational tooling.
+    ///
+    /// Keys are returned sorted, with `prefix` stripped. Redis is walked with `SCAN`
+    /// rather than `KEYS` so listing never blocks the server; keys written or expiring
+    /// during the walk may or may not be included. The failure policy does not apply.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` when key hashing is on, since hashed keys do
+    /// not keep their prefix
+    pub async fn list_keys(&self, prefix: &str, limit: usize) -> Result<Vec<String>, Error> {
+        if self.hash_keys {
+            return Err(Error::InvalidArgument(
+                "cannot list keys by prefix when key hashing is enabled".to_string(),
+            ));
+        }
+        let pattern = &format!("{}*", escape_glob(prefix));
+
+        retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+            // SCAN may return a key more than once, so collect into a set
+            let mut keys = BTreeSet::new();
+            let mut cursor: u64 = 0;
+            loop {
+                let (next_cursor, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(pattern)
+                    .arg("COUNT")
+                    .arg(SCAN_BATCH_SIZE)
+                    .query_async(&mut conn)
+                    .await?;
+                keys.extend(
+                    batch
+                        .iter()
+                        .filter_map(|key| key.strip_prefix(prefix))
+                        .map(str::to_string),
+                );
+                if next_cursor == 0 || keys.len() >= limit {
+                    break;
+                }
+                cursor = next_cursor;
+            }
+
+            Ok(keys.into_iter().take(limit).collect())
+        })
+        .await
+    }
+
+    /// Whether Redis is currently reachable, for use in readiness probes.
+    pub async fn healthy(&self) -> bool {
+        self.connect().await.is_ok()
+    }
+
+    /// Store `value` under `key` for `ttl_seconds` unless the key holds something
+    /// other than one of the `replaceable` markers.
+    async fn set_unless_result(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_seconds: usize,
+        replaceable: &[&str],
+    ) -> Result<bool, Error> {
+        let storage_key = &self.storage_key(key);
+
+        let script = &redis::Script::new(SET_UNLESS_RESULT_SCRIPT);
+
+        let was_set = retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+            let mut invocation = script.key(storage_key.as_ref());
+            invocation.arg(value).arg(ttl_seconds);
+            for marker in replaceable {
+                invocation.arg(*marker);
+            }
+            let was_set: bool = invocation.invoke_async(&mut conn).await?;
+
+            Ok(was_set)
+        })
+        .await;
+        self.apply_failure_policy(key, was_set, true)
+    }
+
+    fn default_retry_policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_max_retries(2)
+            .with_initial_backoff(Duration::from_millis(25))
+            .with_max_backoff(Duration::from_millis(250))
     }
 }
 
+/// Run a Redis operation, retrying the errors `Error::is_retryable` deems transient.
+async fn retry_transient<T, F, Fut>(policy: &RetryPolicy, operation: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    do_with_retry_if(policy, None, operation, Error::is_retryable)
+        .await
+        .map_err(|e| match e {
+            RetryError::AttemptsExhausted(e)
+            | RetryError::NonRetryable(e)
+            | RetryError::CircuitOpenedDuringRetry(e)
+            | RetryError::Cancelled(e) => e,
+            RetryError::CircuitBreakerOpen => {
+                Error::Internal("circuit breaker is open".to_string())
+            }
+            RetryError::BulkheadFull => Error::Internal("bulkhead is full".to_string()),
+        })
+}
+
 #[async_trait]
 impl IdempotencyStore for RedisIdempotencyStore {
     async fn check_and_set<T: Serialize + Send + Sync>(
@@ -116,47 +537,277 @@ impl IdempotencyStore for RedisIdempotencyStore {
         result: &T,
         ttl_seconds: usize,
     ) -> Result<bool, Error> {
-        let mut conn = self
-            .client
-            .get_multiplexed_async_connection()
+        let result_json =
+            serde_json::to_string(result).map_err(|e| Error::Internal(e.to_string()))?;
+        if result_json.len() > self.max_value_bytes {
+            return Err(Error::BadRequest("idempotency value too large".to_string()));
+        }
+        self.set_unless_result(key, &result_json, ttl_seconds, &[NEGATIVE_TOMBSTONE])
             .await
-            .map_err(|e| Error::Internal(e.to_string()))?;
+    }
+
+    async fn check_and_set_negative(&self, key: &str, ttl_seconds: usize) -> Result<bool, Error> {
+        let storage_key = &self.storage_key(key);
+
+        let was_set = retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+            let was_set: bool = redis::cmd("SET")
+                .arg(storage_key.as_ref())
+                .arg(NEGATIVE_TOMBSTONE)
+                .arg("NX")
+                .arg("EX")
+                .arg(ttl_seconds)
+                .query_async(&mut conn)
+                .await?;
+
+            Ok(was_set)
+        })
+        .await;
+        self.apply_failure_policy(key, was_set, true)
+    }
+
+    async fn begin(&self, key: &str, lock_ttl_seconds: usize) -> Result<bool, Error> {
+        self.set_unless_result(
+            key,
+            IN_PROGRESS_MARKER,
+            lock_ttl_seconds,
+            &[NEGATIVE_TOMBSTONE],
+        )
+        .await
+    }
 
+    async fn complete<T: Serialize + Send + Sync>(
+        &self,
+        key: &str,
+        result: &T,
+        ttl_seconds: usize,
+    ) -> Result<bool, Error> {
         let result_json =
             serde_json::to_string(result).map_err(|e| Error::Internal(e.to_string()))?;
+        if result_json.len() > self.max_value_bytes {
+            return Err(Error::BadRequest("idempotency value too large".to_string()));
+        }
+        let result_json = result_json.as_str();
+        let storage_key = &self.storage_key(key);
 
-        let was_set: bool = redis::cmd("SET")
-            .arg(key)
-            .arg(&result_json)
-            .arg("NX")
-            .arg("EX")
-            .arg(ttl_seconds)
-            .query_async(&mut conn)
-            .await
-            .map_err(|e| Error::Internal(e.to_string()))?;
+        let script = &redis::Script::new(SET_IF_SCRIPT);
+
+        let was_set = retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
 
-        Ok(was_set)
+            let was_set: bool = script
+                .key(storage_key.as_ref())
+                .arg(result_json)
+                .arg(ttl_seconds)
+                .arg(IN_PROGRESS_MARKER)
+                .invoke_async(&mut conn)
+                .await?;
+
+            Ok(was_set)
+        })
+        .await;
+        self.apply_failure_policy(key, was_set, true)
     }
 
-    async fn get_result<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
-        let mut conn = self
-            .client
-            .get_multiplexed_async_connection()
-            .await
-            .map_err(|e| Error::Internal(e.to_string()))?;
+    async fn release(&self, key: &str) -> Result<bool, Error> {
+        let storage_key = &self.storage_key(key);
 
-        let result_json: Option<String> = conn
-            .get(key)
-            .await
-            .map_err(|e| Error::Internal(e.to_string()))?;
+        let script = &redis::Script::new(DELETE_IF_SCRIPT);
+
+        let released = retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+            let released: bool = script
+                .key(storage_key.as_ref())
+                .arg(IN_PROGRESS_MARKER)
+                .invoke_async(&mut conn)
+                .await?;
+
+            Ok(released)
+        })
+        .await;
+        self.apply_failure_policy(key, released, false)
+    }
+
+    async fn get_result<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<Outcome<T>>, Error> {
+        let storage_key = &self.storage_key(key);
+        let result_json = retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            Ok(conn.get(storage_key.as_ref()).await?)
+        })
+        .await;
+        let result_json: Option<String> = self.apply_failure_policy(key, result_json, None)?;
 
         match result_json {
+            Some(json) if json == NEGATIVE_TOMBSTONE => Ok(Some(Outcome::NegativeCached)),
+            Some(json) if json == IN_PROGRESS_MARKER => Ok(Some(Outcome::InProgress)),
             Some(json) => {
                 let result =
                     serde_json::from_str(&json).map_err(|e| Error::Internal(e.to_string()))?;
-                Ok(Some(result))
+                Ok(Some(Outcome::Found(result)))
             }
             None => Ok(None),
         }
     }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>, Error> {
+        let storage_key = &self.storage_key(key);
+        let ttl_millis = retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            let ttl_millis: i64 = redis::cmd("PTTL")
+                .arg(storage_key.as_ref())
+                .query_async(&mut conn)
+                .await?;
+            Ok(ttl_millis)
+        })
+        .await;
+        let ttl_millis = self.apply_failure_policy(key, ttl_millis, -2)?;
+
+        // PTTL is -2 for a missing key and -1 for a key without expiry, which this
+        // store never writes
+        Ok(u64::try_from(ttl_millis).ok().map(Duration::from_millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redis::{ErrorKind, RedisError};
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_max_retries(3)
+            .with_initial_backoff(Duration::from_millis(1))
+            .with_jitter(false)
+    }
+
+    fn connection_refused() -> Error {
+        RedisError::from(io::Error::new(io::ErrorKind::ConnectionRefused, "refused")).into()
+    }
+
+    #[test]
+    fn test_key_hashing_changes_storage_key() {
+        let key = "POST /v1/payments?wallet=w_123&reference=r_456";
+        let plain = RedisIdempotencyStore::new("redis://127.0.0.1:6379").unwrap();
+        let hashed = RedisIdempotencyStore::new("redis://127.0.0.1:6379")
+            .unwrap()
+            .with_key_hashing(true);
+
+        assert_eq!(plain.storage_key(key), key);
+        assert_ne!(hashed.storage_key(key), plain.storage_key(key));
+        assert_eq!(hashed.storage_key(key), hashed.storage_key(key));
+        assert_eq!(hashed.storage_key(key).len(), 64);
+    }
+
+    #[test]
+    fn test_with_db_overrides_url_database() {
+        let store = RedisIdempotencyStore::with_db("redis://127.0.0.1:6379/2", 3).unwrap();
+        assert_eq!(store.client.get_connection_info().redis.db, 3);
+        assert!(RedisIdempotencyStore::with_db("invalid-url", 3).is_err());
+    }
+
+    #[test]
+    fn test_escape_glob_matches_prefix_literally() {
+        assert_eq!(escape_glob("payments:"), "payments:");
+        assert_eq!(escape_glob("a*b?[c]\\"), "a\\*b\\?\\[c\\]\\\\");
+    }
+
+    fn unreachable_store(failure_policy: FailurePolicy) -> RedisIdempotencyStore {
+        // Nothing listens on port 1, so connections are refused immediately
+        RedisIdempotencyStore::new("redis://127.0.0.1:1")
+            .unwrap()
+            .with_retry_policy(RetryPolicy::new().with_max_retries(0))
+            .with_failure_policy(failure_policy)
+    }
+
+    #[tokio::test]
+    async fn test_fail_open_treats_unreachable_redis_as_first_time() {
+        let store = unreachable_store(FailurePolicy::FailOpen);
+
+        assert!(store.begin("key", 5).await.unwrap());
+        assert!(store.complete("key", &"result", 60).await.unwrap());
+        let result: Option<Outcome<String>> = store.get_result("key").await.unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    async fn test_value_over_size_limit_is_rejected() {
+        // Rejected before Redis is contacted, so the store need not be reachable
+        let store = unreachable_store(FailurePolicy::FailClosed).with_max_value_bytes(16);
+
+        // Serializes to 17 bytes including the JSON quotes
+        assert!(matches!(
+            store.check_and_set("key", &"a".repeat(15), 60).await,
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fail_closed_propagates_connection_error() {
+        let store = unreachable_store(FailurePolicy::FailClosed);
+
+        assert!(store.check_and_set("key", &"result", 60).await.is_err());
+        assert!(store.get_result::<String>("key").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transient_error_is_retried() {
+        let attempts = AtomicUsize::new(0);
+
+        let result = retry_transient(&fast_policy(), || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt == 0 {
+                    Err(connection_refused())
+                } else {
+                    Ok("OK")
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "OK");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_command_error_is_not_retried() {
+        let attempts = AtomicUsize::new(0);
+
+        let result: Result<(), Error> = retry_transient(&fast_policy(), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async {
+                Err(RedisError::from((
+                    ErrorKind::ExtensionError,
+                    "WRONGTYPE",
+                    "Operation against a key holding the wrong kind of value".to_string(),
+                ))
+                .into())
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_give_up_after_max_retries() {
+        let attempts = AtomicUsize::new(0);
+
+        let result: Result<(), Error> = retry_transient(&fast_policy(), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(connection_refused()) }
+        })
+        .await;
+
+        assert!(matches!(result, Err(Error::Internal(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
 }
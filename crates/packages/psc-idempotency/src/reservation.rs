@@ -0,0 +1,241 @@
+//! Reserving parts of a limited amount, such as the refundable balance of a payment.
+//!
+//! Each reservation is made for an id, such as the idempotency key of the request
+//! making it, so a replayed request does not reserve its amount twice.
+
+use crate::{retry_transient, RedisIdempotencyStore};
+use async_trait::async_trait;
+use psc_error::Error;
+use psc_retry::RetryPolicy;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Reserves `ARGV[2]` for `ARGV[1]` in the hash `KEYS[1]` unless the reserved total
+/// would exceed `ARGV[3]`, then expires the hash after `ARGV[4]` milliseconds.
+const RESERVE_SCRIPT: &str = r#"
+if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 1 then
+    return 1
+end
+local total = 0
+for _, amount in ipairs(redis.call('HVALS', KEYS[1])) do
+    total = total + tonumber(amount)
+end
+if total + tonumber(ARGV[2]) > tonumber(ARGV[3]) then
+    return 0
+end
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+redis.call('PEXPIRE', KEYS[1], ARGV[4])
+return 1
+"#;
+
+/// Amounts reserved against a limit, per key.
+#[async_trait]
+pub trait Reservations: Send + Sync {
+    /// Reserve `amount` under `key` for `id`, unless the amounts reserved under
+    /// `key` would then add up to more than `limit`.
+    ///
+    /// Returns `true` if the amount was reserved, or was already reserved for
+    /// `id`, and `false` if it would exceed `limit`. The reservations under `key`
+    /// are forgotten `ttl` after the last one was made.
+    async fn reserve(
+        &self,
+        key: &str,
+        id: &str,
+        amount: i64,
+        limit: i64,
+        ttl: Duration,
+    ) -> Result<bool, Error>;
+
+    /// Drop the reservation made under `key` for `id`, e.g. because the operation it
+    /// was made for failed.
+    async fn release(&self, key: &str, id: &str) -> Result<(), Error>;
+}
+
+/// Redis-backed reservations, kept in one hash per key.
+///
+/// Transient Redis errors are retried like in [`RedisIdempotencyStore`]. Unlike
+/// there, other errors are always returned, so an amount is never reserved past its
+/// limit while Redis is unavailable.
+pub struct RedisReservations {
+    client: redis::Client,
+    retry_policy: RetryPolicy,
+}
+
+impl RedisReservations {
+    /// Create new Redis-backed reservations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Redis client cannot be created
+    pub fn new(redis_url: &str) -> Result<Self, Error> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self {
+            client,
+            retry_policy: RedisIdempotencyStore::default_retry_policy(),
+        })
+    }
+
+    /// Set the policy used to retry transient Redis errors.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+}
+
+#[async_trait]
+impl Reservations for RedisReservations {
+    async fn reserve(
+        &self,
+        key: &str,
+        id: &str,
+        amount: i64,
+        limit: i64,
+        ttl: Duration,
+    ) -> Result<bool, Error> {
+        let script = &redis::Script::new(RESERVE_SCRIPT);
+
+        retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+            let reserved: bool = script
+                .key(key)
+                .arg(id)
+                .arg(amount)
+                .arg(limit)
+                .arg(ttl.as_millis() as u64)
+                .invoke_async(&mut conn)
+                .await?;
+
+            Ok(reserved)
+        })
+        .await
+    }
+
+    async fn release(&self, key: &str, id: &str) -> Result<(), Error> {
+        retry_transient(&self.retry_policy, || async move {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            redis::cmd("HDEL")
+                .arg(key)
+                .arg(id)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// The amounts reserved under one key, by id, and when they are forgotten.
+type Reserved = (HashMap<String, i64>, Instant);
+
+/// In-process reservations, for tests and single-instance deployments.
+#[derive(Debug, Default)]
+pub struct InMemoryReservations {
+    entries: Mutex<HashMap<String, Reserved>>,
+}
+
+impl InMemoryReservations {
+    /// Create reservations with nothing reserved.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Reservations for InMemoryReservations {
+    async fn reserve(
+        &self,
+        key: &str,
+        id: &str,
+        amount: i64,
+        limit: i64,
+        ttl: Duration,
+    ) -> Result<bool, Error> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let (reserved, expires_at) = entries
+            .entry(key.to_string())
+            .or_insert_with(|| (HashMap::new(), now));
+        if *expires_at <= now {
+            reserved.clear();
+        }
+        if reserved.contains_key(id) {
+            return Ok(true);
+        }
+        if reserved.values().sum::<i64>() + amount > limit {
+            return Ok(false);
+        }
+        reserved.insert(id.to_string(), amount);
+        *expires_at = now + ttl;
+        Ok(true)
+    }
+
+    async fn release(&self, key: &str, id: &str) -> Result<(), Error> {
+        if let Some((reserved, _)) = self
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(key)
+        {
+            reserved.remove(id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn test_in_memory_reservations_stay_within_limit() {
+        let reservations = InMemoryReservations::new();
+
+        assert!(reservations
+            .reserve("payment", "refund-1", 60, 100, TTL)
+            .await
+            .unwrap());
+        assert!(!reservations
+            .reserve("payment", "refund-2", 60, 100, TTL)
+            .await
+            .unwrap());
+        assert!(reservations
+            .reserve("payment", "refund-2", 40, 100, TTL)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_reservation_is_made_once_per_id() {
+        let reservations = InMemoryReservations::new();
+
+        for _ in 0..3 {
+            assert!(reservations
+                .reserve("payment", "refund-1", 60, 100, TTL)
+                .await
+                .unwrap());
+        }
+        assert!(reservations
+            .reserve("payment", "refund-2", 40, 100, TTL)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_release_frees_the_amount() {
+        let reservations = InMemoryReservations::new();
+        assert!(reservations
+            .reserve("payment", "refund-1", 60, 100, TTL)
+            .await
+            .unwrap());
+
+        reservations.release("payment", "refund-1").await.unwrap();
+        assert!(reservations
+            .reserve("payment", "refund-2", 100, 100, TTL)
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,415 @@
+//! Retrying side-effecting operations without executing them twice.
+
+use crate::{retry_transient, IdempotencyStore, Outcome};
+use psc_error::Error;
+use psc_retry::RetryPolicy;
+use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+
+/// Run `operation` at most once per `key`, retrying it according to `policy`.
+///
+/// The key is claimed with [`IdempotencyStore::begin`] before `operation` runs, so
+/// concurrent callers with the same key cannot both run it. The claim is held for
+/// `lock_ttl_seconds`, which should outlast all retries of `operation`.
+///
+/// If a result is already stored for `key` it is returned without invoking
+/// `operation`, and if another caller holds the claim `Error::Conflict` is
+/// returned. Otherwise, including when only a negative result is stored,
+/// `operation` is retried on the errors `Error::is_retryable` deems transient, and
+/// its successful result is stored under `key` for `ttl_seconds`. Failures are not
+/// stored and release the claim, so a later call with the same key tries again.
+///
+/// The result is only stored while the claim is held. If it expired and another
+/// caller stored a result first, that result is returned so all callers agree on
+/// the outcome.
+pub async fn retry_idempotent<S, T, F, Fut>(
+    store: &S,
+    key: &str,
+    ttl_seconds: usize,
+    lock_ttl_seconds: usize,
+    policy: &RetryPolicy,
+    operation: F,
+) -> Result<T, Error>
+where
+    S: IdempotencyStore + Sync,
+    T: Serialize + DeserializeOwned + Send + Sync,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    if !store.begin(key, lock_ttl_seconds).await? {
+        return match store.get_result(key).await? {
+            Some(Outcome::Found(result)) => Ok(result),
+            // Another caller holds the claim, or held it and released it after a
+            // failure; either way its outcome isn't known yet.
+            Some(Outcome::InProgress) | Some(Outcome::NegativeCached) | None => {
+                Err(Error::Conflict(format!(
+                    "operation for idempotency key {key} is already in progress"
+                )))
+            }
+        };
+    }
+
+    let result = match retry_transient(policy, operation).await {
+        Ok(result) => result,
+        Err(e) => {
+            store.release(key).await?;
+            return Err(e);
+        }
+    };
+
+    if store.complete(key, &result, ttl_seconds).await? {
+        return Ok(result);
+    }
+    // The claim expired while the operation ran. Never overwrite what is stored
+    // now: another caller's result wins, and another caller's claim is left alone.
+    match store.get_result(key).await? {
+        Some(Outcome::Found(stored)) => Ok(stored),
+        _ => {
+            tracing::warn!(
+                key,
+                "idempotency claim expired before the result was stored"
+            );
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IN_PROGRESS_MARKER, NEGATIVE_TOMBSTONE};
+    use async_trait::async_trait;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Mutex, MutexGuard};
+    use std::time::{Duration, Instant};
+
+    type Entries = HashMap<String, (String, Instant)>;
+
+    #[derive(Default)]
+    struct InMemoryStore {
+        results: Mutex<Entries>,
+    }
+
+    impl InMemoryStore {
+        /// Lock the stored values, dropping the ones that have expired.
+        fn live_results(&self) -> MutexGuard<'_, Entries> {
+            let mut results = self.results.lock().unwrap();
+            let now = Instant::now();
+            results.retain(|_, (_, expires_at)| *expires_at > now);
+            results
+        }
+    }
+
+    fn expiry(ttl_seconds: usize) -> Instant {
+        Instant::now() + Duration::from_secs(ttl_seconds as u64)
+    }
+
+    #[async_trait]
+    impl IdempotencyStore for InMemoryStore {
+        async fn check_and_set<T: Serialize + Send + Sync>(
+            &self,
+            key: &str,
+            result: &T,
+            ttl_seconds: usize,
+        ) -> Result<bool, Error> {
+            let json = serde_json::to_string(result).unwrap();
+            let mut results = self.live_results();
+            if results
+                .get(key)
+                .is_some_and(|(stored, _)| stored != NEGATIVE_TOMBSTONE)
+            {
+                return Ok(false);
+            }
+            results.insert(key.to_string(), (json, expiry(ttl_seconds)));
+            Ok(true)
+        }
+
+        async fn check_and_set_negative(
+            &self,
+            key: &str,
+            ttl_seconds: usize,
+        ) -> Result<bool, Error> {
+            let mut results = self.live_results();
+            if results.contains_key(key) {
+                return Ok(false);
+            }
+            results.insert(
+                key.to_string(),
+                (NEGATIVE_TOMBSTONE.to_string(), expiry(ttl_seconds)),
+            );
+            Ok(true)
+        }
+
+        async fn begin(&self, key: &str, lock_ttl_seconds: usize) -> Result<bool, Error> {
+            let mut results = self.live_results();
+            if results
+                .get(key)
+                .is_some_and(|(stored, _)| stored != NEGATIVE_TOMBSTONE)
+            {
+                return Ok(false);
+            }
+            results.insert(
+                key.to_string(),
+                (IN_PROGRESS_MARKER.to_string(), expiry(lock_ttl_seconds)),
+            );
+            Ok(true)
+        }
+
+        async fn complete<T: Serialize + Send + Sync>(
+            &self,
+            key: &str,
+            result: &T,
+            ttl_seconds: usize,
+        ) -> Result<bool, Error> {
+            let json = serde_json::to_string(result).unwrap();
+            let mut results = self.live_results();
+            if results
+                .get(key)
+                .is_none_or(|(stored, _)| stored != IN_PROGRESS_MARKER)
+            {
+                return Ok(false);
+            }
+            results.insert(key.to_string(), (json, expiry(ttl_seconds)));
+            Ok(true)
+        }
+
+        async fn release(&self, key: &str) -> Result<bool, Error> {
+            let mut results = self.live_results();
+            if results
+                .get(key)
+                .is_none_or(|(stored, _)| stored != IN_PROGRESS_MARKER)
+            {
+                return Ok(false);
+            }
+            results.remove(key);
+            Ok(true)
+        }
+
+        async fn get_result<T: DeserializeOwned>(
+            &self,
+            key: &str,
+        ) -> Result<Option<Outcome<T>>, Error> {
+            let results = self.live_results();
+            Ok(results.get(key).map(|(stored, _)| {
+                if stored == NEGATIVE_TOMBSTONE {
+                    Outcome::NegativeCached
+                } else if stored == IN_PROGRESS_MARKER {
+                    Outcome::InProgress
+                } else {
+                    Outcome::Found(serde_json::from_str(stored).unwrap())
+                }
+            }))
+        }
+
+        async fn ttl(&self, key: &str) -> Result<Option<Duration>, Error> {
+            let results = self.live_results();
+            Ok(results
+                .get(key)
+                .map(|(_, expires_at)| expires_at.saturating_duration_since(Instant::now())))
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Payout {
+        id: String,
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_max_retries(3)
+            .with_initial_backoff(Duration::from_millis(1))
+            .with_jitter(false)
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_returns_stored_result_for_same_key() {
+        let store = InMemoryStore::default();
+        let attempts = AtomicUsize::new(0);
+        let operation = || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 2 {
+                    Err(Error::Timeout("provider".to_string()))
+                } else {
+                    Ok(Payout {
+                        id: format!("payout-{attempt}"),
+                    })
+                }
+            }
+        };
+
+        let first = retry_idempotent(&store, "payout-key", 60, 30, &fast_policy(), operation)
+            .await
+            .unwrap();
+        assert_eq!(first.id, "payout-2");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let second = retry_idempotent(&store, "payout-key", 60, 30, &fast_policy(), operation)
+            .await
+            .unwrap();
+        assert_eq!(second, first);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_negative_result_is_replaced_by_operation_result() {
+        let store = InMemoryStore::default();
+        assert!(store
+            .check_and_set_negative("payout-key", 60)
+            .await
+            .unwrap());
+        assert!(!store
+            .check_and_set_negative("payout-key", 60)
+            .await
+            .unwrap());
+
+        let result = retry_idempotent(&store, "payout-key", 60, 30, &fast_policy(), || async {
+            Ok(Payout {
+                id: "payout-1".to_string(),
+            })
+        })
+        .await;
+        assert_eq!(result.unwrap().id, "payout-1");
+
+        let stored: Option<Outcome<Payout>> = store.get_result("payout-key").await.unwrap();
+        assert_eq!(
+            stored,
+            Some(Outcome::Found(Payout {
+                id: "payout-1".to_string(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failure_is_not_stored() {
+        let store = InMemoryStore::default();
+
+        let result: Result<Payout, Error> =
+            retry_idempotent(&store, "payout-key", 60, 30, &fast_policy(), || async {
+                Err(Error::BadRequest("invalid payee".to_string()))
+            })
+            .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+
+        let result = retry_idempotent(&store, "payout-key", 60, 30, &fast_policy(), || async {
+            Ok(Payout {
+                id: "payout-1".to_string(),
+            })
+        })
+        .await;
+        assert_eq!(result.unwrap().id, "payout-1");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_callers_run_operation_once() {
+        let store = InMemoryStore::default();
+        let attempts = AtomicUsize::new(0);
+        let operation = || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(Payout {
+                    id: "payout-1".to_string(),
+                })
+            }
+        };
+
+        let policy = fast_policy();
+        let (first, second) = tokio::join!(
+            retry_idempotent(&store, "payout-key", 60, 30, &policy, operation),
+            retry_idempotent(&store, "payout-key", 60, 30, &policy, operation),
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        let (won, lost) = if first.is_ok() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        assert_eq!(won.unwrap().id, "payout-1");
+        assert!(matches!(lost, Err(Error::Conflict(_))));
+
+        let replayed = retry_idempotent(&store, "payout-key", 60, 30, &policy, operation)
+            .await
+            .unwrap();
+        assert_eq!(replayed.id, "payout-1");
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_progress_key_is_a_conflict() {
+        let store = InMemoryStore::default();
+        assert!(store.begin("payout-key", 30).await.unwrap());
+
+        let attempts = AtomicUsize::new(0);
+        let result: Result<Payout, Error> =
+            retry_idempotent(&store, "payout-key", 60, 30, &fast_policy(), || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async {
+                    Ok(Payout {
+                        id: "payout-1".to_string(),
+                    })
+                }
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Conflict(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
+
+        let stored: Option<Outcome<Payout>> = store.get_result("payout-key").await.unwrap();
+        assert_eq!(stored, Some(Outcome::InProgress));
+    }
+
+    #[tokio::test]
+    async fn test_expired_claim_does_not_overwrite_other_result() {
+        let store = InMemoryStore::default();
+
+        let result = retry_idempotent(&store, "payout-key", 60, 30, &fast_policy(), || async {
+            // The claim expires mid-operation and another worker completes the key
+            store.results.lock().unwrap().remove("payout-key");
+            assert!(store.begin("payout-key", 30).await.unwrap());
+            assert!(store
+                .complete(
+                    "payout-key",
+                    &Payout {
+                        id: "payout-other".to_string(),
+                    },
+                    60,
+                )
+                .await
+                .unwrap());
+            Ok(Payout {
+                id: "payout-1".to_string(),
+            })
+        })
+        .await;
+        assert_eq!(result.unwrap().id, "payout-other");
+
+        let stored: Option<Outcome<Payout>> = store.get_result("payout-key").await.unwrap();
+        assert_eq!(
+            stored,
+            Some(Outcome::Found(Payout {
+                id: "payout-other".to_string(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ttl_counts_down_until_expiry() {
+        let store = InMemoryStore::default();
+        assert_eq!(store.ttl("payout-key").await.unwrap(), None);
+
+        assert!(store.begin("payout-key", 1).await.unwrap());
+        let first = store.ttl("payout-key").await.unwrap().unwrap();
+        assert!(first <= Duration::from_secs(1));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let second = store.ttl("payout-key").await.unwrap().unwrap();
+        assert!(second < first);
+
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        assert_eq!(store.ttl("payout-key").await.unwrap(), None);
+        let stored: Option<Outcome<Payout>> = store.get_result("payout-key").await.unwrap();
+        assert_eq!(stored, None);
+    }
+}
@@ -1,4 +1,8 @@
-use psc_idempotency::{IdempotencyStore, RedisIdempotencyStore};
+use psc_error::Error;
+use psc_idempotency::{
+    FailurePolicy, IdempotencyStore, Outcome, RedisIdempotencyStore, RedisReservations,
+    Reservations,
+};
 use serde::{Deserialize, Serialize};
 use tokio;
 use uuid;
@@ -61,8 +65,9 @@ async fn test_check_and_set_duplicate() {
     assert!(!was_set2);
 
     // Getting the result should return the first value
-    let retrieved: Option<TestResult> = store.get_result(&key).await.expect("Failed to get result");
-    assert_eq!(retrieved, Some(result1));
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::Found(result1)));
 }
 
 #[tokio::test]
@@ -74,7 +79,8 @@ async fn test_get_result_not_found() {
     // Use a unique key for each test run
     let key = format!("non_existent_key_{}", uuid::Uuid::new_v4());
 
-    let result: Option<TestResult> = store.get_result(&key).await.expect("Failed to get result");
+    let result: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
     assert_eq!(result, None);
 }
 
@@ -102,6 +108,365 @@ async fn test_ttl_expiration() {
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
     // Getting the result should return None after expiration
-    let retrieved: Option<TestResult> = store.get_result(&key).await.expect("Failed to get result");
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
     assert_eq!(retrieved, None);
 }
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_connect_live_server() {
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+
+    store.connect().await.expect("Failed to ping Redis");
+    assert!(store.healthy().await);
+}
+
+#[tokio::test]
+async fn test_connect_dead_port_fails() {
+    // Nothing listens on port 1, so the connection is refused
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:1").expect("Failed to create Redis store");
+
+    assert!(store.connect().await.is_err());
+    assert!(!store.healthy().await);
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_key_hashing_stores_under_a_different_key() {
+    let plain =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+    let hashed = RedisIdempotencyStore::new("redis://127.0.0.1:6379")
+        .expect("Failed to create Redis store")
+        .with_key_hashing(true);
+    let result = TestResult {
+        value: "hashed".to_string(),
+        count: 1,
+    };
+
+    // Use a unique key for each test run
+    let key = format!("/v1/payments?reference={}", uuid::Uuid::new_v4());
+
+    let was_set = hashed
+        .check_and_set(&key, &result, 60)
+        .await
+        .expect("Failed to check and set");
+    assert!(was_set);
+
+    let retrieved: Option<Outcome<TestResult>> =
+        hashed.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::Found(result)));
+
+    // The raw key was never written, so the plain store sees nothing
+    let retrieved: Option<Outcome<TestResult>> =
+        plain.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, None);
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_negative_result_lifecycle() {
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+    let result = TestResult {
+        value: "found".to_string(),
+        count: 1,
+    };
+
+    // Use a unique key for each test run
+    let key = format!("test_key_negative_{}", uuid::Uuid::new_v4());
+
+    let was_set = store
+        .check_and_set_negative(&key, 60)
+        .await
+        .expect("Failed to set negative result");
+    assert!(was_set);
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::NegativeCached));
+
+    // A real result replaces the negative one...
+    let was_set = store
+        .check_and_set(&key, &result, 60)
+        .await
+        .expect("Failed to check and set");
+    assert!(was_set);
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::Found(result)));
+
+    // ...but not the other way around
+    let was_set = store
+        .check_and_set_negative(&key, 60)
+        .await
+        .expect("Failed to set negative result");
+    assert!(!was_set);
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_negative_result_expires() {
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+
+    // Use a unique key for each test run
+    let key = format!("test_key_negative_ttl_{}", uuid::Uuid::new_v4());
+
+    let was_set = store
+        .check_and_set_negative(&key, 1)
+        .await
+        .expect("Failed to set negative result");
+    assert!(was_set);
+
+    // Wait for expiration
+    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, None);
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_list_keys_by_prefix() {
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+    let prefix = format!("test_list_{}:", uuid::Uuid::new_v4());
+    for name in ["a", "b", "c"] {
+        assert!(store
+            .check_and_set(&format!("{prefix}{name}"), &name, 60)
+            .await
+            .unwrap());
+    }
+    store
+        .check_and_set(&format!("other_{prefix}d"), &"d", 60)
+        .await
+        .unwrap();
+
+    let keys = store.list_keys(&prefix, 10).await.unwrap();
+    assert_eq!(keys, vec!["a", "b", "c"]);
+
+    let keys = store.list_keys(&prefix, 2).await.unwrap();
+    assert_eq!(keys.len(), 2);
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_begin_lock_expires_but_result_persists() {
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+    let result = TestResult {
+        value: "done".to_string(),
+        count: 1,
+    };
+
+    // Use a unique key for each test run
+    let key = format!("test_key_begin_{}", uuid::Uuid::new_v4());
+
+    assert!(store.begin(&key, 1).await.expect("Failed to begin"));
+    assert!(!store.begin(&key, 1).await.expect("Failed to begin"));
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::InProgress));
+
+    // A crashed worker's lock expires, so the operation can be claimed again
+    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    assert!(store.begin(&key, 1).await.expect("Failed to begin"));
+
+    // Only the claim holder stores the result
+    let was_set = store
+        .check_and_set(&key, &result, 60)
+        .await
+        .expect("Failed to check and set");
+    assert!(!was_set);
+    let was_set = store
+        .complete(&key, &result, 60)
+        .await
+        .expect("Failed to complete");
+    assert!(was_set);
+
+    // The result outlives the lock TTL and blocks further claims
+    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::Found(result)));
+    assert!(!store.begin(&key, 1).await.expect("Failed to begin"));
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_complete_and_release_require_claim() {
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+    let result = TestResult {
+        value: "done".to_string(),
+        count: 1,
+    };
+
+    // Use a unique key for each test run
+    let key = format!("test_key_complete_{}", uuid::Uuid::new_v4());
+
+    // Nothing to complete or release before the key is claimed
+    assert!(!store
+        .complete(&key, &result, 60)
+        .await
+        .expect("Failed to complete"));
+    assert!(!store.release(&key).await.expect("Failed to release"));
+
+    // A released claim can be taken again straight away
+    assert!(store.begin(&key, 60).await.expect("Failed to begin"));
+    assert!(store.release(&key).await.expect("Failed to release"));
+    assert!(store.begin(&key, 60).await.expect("Failed to begin"));
+
+    assert!(store
+        .complete(&key, &result, 60)
+        .await
+        .expect("Failed to complete"));
+    assert!(!store
+        .complete(&key, &result, 60)
+        .await
+        .expect("Failed to complete"));
+    assert!(!store.release(&key).await.expect("Failed to release"));
+    let retrieved: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::Found(result)));
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_with_db_isolates_keys() {
+    let db1 = RedisIdempotencyStore::with_db("redis://127.0.0.1:6379", 1)
+        .expect("Failed to create Redis store");
+    let db0 = RedisIdempotencyStore::new("redis://127.0.0.1:6379/0")
+        .expect("Failed to create Redis store");
+    let result = TestResult {
+        value: "db1".to_string(),
+        count: 1,
+    };
+
+    // Use a unique key for each test run
+    let key = format!("test_key_db_{}", uuid::Uuid::new_v4());
+
+    assert!(db1
+        .check_and_set(&key, &result, 60)
+        .await
+        .expect("Failed to check and set"));
+
+    let retrieved: Option<Outcome<TestResult>> =
+        db1.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, Some(Outcome::Found(result)));
+    let retrieved: Option<Outcome<TestResult>> =
+        db0.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(retrieved, None);
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_ttl_counts_down_until_expiry() {
+    let store =
+        RedisIdempotencyStore::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+    let result = TestResult {
+        value: "ttl".to_string(),
+        count: 1,
+    };
+
+    // Use a unique key for each test run
+    let key = format!("test_key_ttl_{}", uuid::Uuid::new_v4());
+    assert_eq!(store.ttl(&key).await.expect("Failed to get TTL"), None);
+
+    assert!(store.begin(&key, 1).await.expect("Failed to begin"));
+    let first = store
+        .ttl(&key)
+        .await
+        .expect("Failed to get TTL")
+        .expect("Key should have a TTL");
+    assert!(first <= std::time::Duration::from_secs(1));
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let second = store
+        .ttl(&key)
+        .await
+        .expect("Failed to get TTL")
+        .expect("Key should have a TTL");
+    assert!(second < first);
+
+    // The result is kept for its own TTL, not the remainder of the claim's
+    assert!(store
+        .complete(&key, &result, 2)
+        .await
+        .expect("Failed to complete"));
+    let stored = store
+        .ttl(&key)
+        .await
+        .expect("Failed to get TTL")
+        .expect("Key should have a TTL");
+    assert!(stored > std::time::Duration::from_secs(1));
+    assert!(stored <= std::time::Duration::from_secs(2));
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(2100)).await;
+    assert_eq!(store.ttl(&key).await.expect("Failed to get TTL"), None);
+    let expired: Option<Outcome<TestResult>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(expired, None);
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_value_size_limit_is_enforced() {
+    let store = RedisIdempotencyStore::new("redis://127.0.0.1:6379")
+        .expect("Failed to create Redis store")
+        .with_failure_policy(FailurePolicy::FailClosed)
+        .with_max_value_bytes(16);
+
+    // Use a unique key for each test run
+    let key = format!("test_key_size_{}", uuid::Uuid::new_v4());
+
+    // Serializes to 16 and 17 bytes including the JSON quotes
+    assert!(matches!(
+        store.check_and_set(&key, &"a".repeat(15), 60).await,
+        Err(Error::BadRequest(_))
+    ));
+    assert!(store
+        .check_and_set(&key, &"a".repeat(14), 60)
+        .await
+        .expect("Failed to check and set"));
+    let stored: Option<Outcome<String>> =
+        store.get_result(&key).await.expect("Failed to get result");
+    assert_eq!(stored, Some(Outcome::Found("a".repeat(14))));
+}
+
+#[tokio::test]
+#[ignore] // This test requires a running Redis instance
+async fn test_reservations_stay_within_limit() {
+    let reservations =
+        RedisReservations::new("redis://127.0.0.1:6379").expect("Failed to create Redis store");
+    let ttl = std::time::Duration::from_secs(60);
+
+    // Use a unique key for each test run
+    let key = format!("test_key_reserve_{}", uuid::Uuid::new_v4());
+
+    assert!(reservations
+        .reserve(&key, "refund-1", 60, 100, ttl)
+        .await
+        .expect("Failed to reserve"));
+    // Replaying a reservation does not count it twice
+    assert!(reservations
+        .reserve(&key, "refund-1", 60, 100, ttl)
+        .await
+        .expect("Failed to reserve"));
+    assert!(!reservations
+        .reserve(&key, "refund-2", 60, 100, ttl)
+        .await
+        .expect("Failed to reserve"));
+
+    reservations
+        .release(&key, "refund-1")
+        .await
+        .expect("Failed to release");
+    assert!(reservations
+        .reserve(&key, "refund-2", 100, 100, ttl)
+        .await
+        .expect("Failed to reserve"));
+}
@@ -0,0 +1,21 @@
+-- Create the balance_reservations table (funds held against an account, e.g. pending payouts)
+CREATE TABLE balance_reservations (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL REFERENCES accounts(id),
+    amount_minor_units BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'ACTIVE', -- 'ACTIVE', 'RELEASED' or 'SETTLED'
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT chk_reservation_amount_positive CHECK (amount_minor_units > 0),
+    CONSTRAINT chk_reservation_status CHECK (status IN ('ACTIVE', 'RELEASED', 'SETTLED'))
+);
+
+-- Only active reservations count against the available balance
+CREATE INDEX idx_balance_reservations_active_account_id
+    ON balance_reservations (account_id)
+    WHERE status = 'ACTIVE';
+
+CREATE TRIGGER update_balance_reservations_updated_at
+BEFORE UPDATE ON balance_reservations
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
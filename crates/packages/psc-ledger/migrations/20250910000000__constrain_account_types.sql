@@ -0,0 +1,8 @@
+-- Normalise the descriptive account types used so far to the fixed set of
+-- accounting types understood by the ledger
+UPDATE accounts SET type = 'ASSET' WHERE type IN ('Float Assets', 'Clearing Receivable/Payable');
+UPDATE accounts SET type = 'LIABILITY' WHERE type IN ('Customer Escrow Payable', 'Merchant Payable');
+UPDATE accounts SET type = 'REVENUE' WHERE type = 'Fee Revenue';
+
+ALTER TABLE accounts
+    ADD CONSTRAINT chk_account_type CHECK (type IN ('ASSET', 'LIABILITY', 'EQUITY', 'REVENUE', 'EXPENSE'));
@@ -0,0 +1,3 @@
+-- Optimistic-concurrency version of each account, bumped whenever a journal
+-- posts to it
+ALTER TABLE accounts ADD COLUMN version BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Free-form metadata of each journal entry, e.g. the id of the payment it was
+-- posted for
+ALTER TABLE journal_entries ADD COLUMN metadata JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,52 @@
+//! gRPC health reporting for `JournalService`, tied to ledger database health.
+//!
+//! Register the `HealthServer` from `tonic_health::server::health_reporter()` next to
+//! the journal service and pass its reporter to [`spawn_health_reporter`].
+
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tonic_health::ServingStatus;
+use tonic_health::server::HealthReporter;
+
+use crate::LedgerRepository;
+use crate::pb::psc::journal::v1::journal_service_server::JournalServiceServer;
+use crate::service::JournalService;
+
+/// How often [`spawn_health_reporter`] re-checks the database by default.
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Pings the database and reports `JournalService` as serving if it answered, or
+/// not serving otherwise. Returns the status reported.
+pub async fn report_health(
+    repository: &LedgerRepository,
+    reporter: &HealthReporter,
+) -> ServingStatus {
+    if repository.ping().await.is_ok() {
+        reporter
+            .set_serving::<JournalServiceServer<JournalService>>()
+            .await;
+        ServingStatus::Serving
+    } else {
+        reporter
+            .set_not_serving::<JournalServiceServer<JournalService>>()
+            .await;
+        ServingStatus::NotServing
+    }
+}
+
+/// Spawns a task that calls [`report_health`] every `interval`, so the reported
+/// status follows the pool's health. Abort the returned handle to stop it.
+pub fn spawn_health_reporter(
+    repository: LedgerRepository,
+    reporter: HealthReporter,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            report_health(&repository, &reporter).await;
+        }
+    })
+}
@@ -1,10 +1,19 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use psc_domain::Money;
 use psc_error::Result;
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::types::Json;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 use time::OffsetDateTime;
 use uuid::Uuid; // Use Uuid temporarily
 
+pub mod health;
 mod service;
 
 pub mod pb {
@@ -27,8 +36,10 @@ pub struct Account {
     pub id: Uuid, // Changed from Cuid to Uuid
     pub name: String,
     #[sqlx(rename = "type")]
-    pub account_type: String,
+    pub account_type: AccountType,
     pub currency: String,
+    /// Incremented by every journal posted to the account.
+    pub version: i64,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
@@ -48,10 +59,15 @@ pub struct JournalEntry {
     pub account_id: Uuid,   // Changed from Cuid to Uuid
     pub entry_type: String, // "DEBIT" or "CREDIT"
     pub amount_minor_units: i64,
+    pub metadata: Json<EntryMetadata>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
 
+/// Metadata stored with a journal entry, e.g. the id of the payment it was posted
+/// for.
+pub type EntryMetadata = HashMap<String, String>;
+
 // Enum for entry type to ensure type safety
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "TEXT", rename_all = "SCREAMING_SNAKE_CASE")]
@@ -59,31 +75,182 @@ pub enum EntryType {
     Debit,
     Credit,
 }
+
+impl EntryType {
+    /// The other side of a journal: credit for debit and debit for credit.
+    pub fn opposite(&self) -> EntryType {
+        match self {
+            EntryType::Debit => EntryType::Credit,
+            EntryType::Credit => EntryType::Debit,
+        }
+    }
+}
+
+/// Accounting classification of an account, which determines its balance sign.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AccountType {
+    Asset,
+    Liability,
+    Equity,
+    Revenue,
+    Expense,
+}
+
+impl AccountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Asset => "ASSET",
+            AccountType::Liability => "LIABILITY",
+            AccountType::Equity => "EQUITY",
+            AccountType::Revenue => "REVENUE",
+            AccountType::Expense => "EXPENSE",
+        }
+    }
+
+    /// The entry type that increases the balance of accounts of this type.
+    ///
+    /// Assets and expenses grow with debits; liabilities, equity and revenue grow
+    /// with credits.
+    pub fn normal_balance(&self) -> EntryType {
+        match self {
+            AccountType::Asset | AccountType::Expense => EntryType::Debit,
+            AccountType::Liability | AccountType::Equity | AccountType::Revenue => {
+                EntryType::Credit
+            }
+        }
+    }
+}
+
+impl fmt::Display for AccountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AccountType {
+    type Err = psc_error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "ASSET" => Ok(AccountType::Asset),
+            "LIABILITY" => Ok(AccountType::Liability),
+            "EQUITY" => Ok(AccountType::Equity),
+            "REVENUE" => Ok(AccountType::Revenue),
+            "EXPENSE" => Ok(AccountType::Expense),
+            _ => Err(psc_error::Error::BadRequest(format!(
+                "Unknown account type {s:?}: expected one of ASSET, LIABILITY, EQUITY, REVENUE, EXPENSE"
+            ))),
+        }
+    }
+}
+
+/// Identifier of a balance reservation created by [`LedgerRepository::reserve`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReservationId(pub Uuid);
+
+impl fmt::Display for ReservationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Posted and reserved balance of an account, in minor units.
+///
+/// The posted balance follows the account type's normal balance: debits minus
+/// credits for assets and expenses, credits minus debits otherwise. Active
+/// reservations are held back from it to give the amount that can still be spent.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct AccountBalance {
+    pub account_id: Uuid,
+    pub balance_minor_units: i64,
+    pub reserved_minor_units: i64,
+}
+
+impl AccountBalance {
+    pub fn available_minor_units(&self) -> i64 {
+        self.balance_minor_units - self.reserved_minor_units
+    }
+}
+
+/// Encodes the position of `entry` as an opaque page token for
+/// [`LedgerRepository::list_entries_keyset`].
+fn encode_entry_cursor(entry: &JournalEntry) -> String {
+    URL_SAFE_NO_PAD.encode(format!(
+        "{}:{}",
+        entry.created_at.unix_timestamp_nanos(),
+        entry.id
+    ))
+}
+
+/// Decodes a page token produced by [`encode_entry_cursor`].
+fn decode_entry_cursor(cursor: &str) -> Result<(OffsetDateTime, Uuid)> {
+    let invalid = || psc_error::Error::BadRequest(format!("Invalid page token: {cursor:?}"));
+    let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (created_at, id) = decoded.split_once(':').ok_or_else(invalid)?;
+    let created_at = created_at
+        .parse::<i128>()
+        .ok()
+        .and_then(|nanos| OffsetDateTime::from_unix_timestamp_nanos(nanos).ok())
+        .ok_or_else(invalid)?;
+    let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+    Ok((created_at, id))
+}
+
 pub struct LedgerRepository {
     pool: PgPool,
 }
 
 impl LedgerRepository {
+    /// How long [`LedgerRepository::connect`] pools wait for a free connection.
+    pub const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
 
+    /// Connects to `database_url` with a pool of at most `max_connections`.
+    ///
+    /// Prefer this over building a pool by hand so every service sizes and times out
+    /// its ledger connections the same way. Acquiring a connection fails with
+    /// `Error::Database(sqlx::Error::PoolTimedOut)` after [`Self::ACQUIRE_TIMEOUT`].
+    pub async fn connect(database_url: &str, max_connections: u32) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .acquire_timeout(Self::ACQUIRE_TIMEOUT)
+            .connect(database_url)
+            .await?;
+        Ok(Self::new(pool))
+    }
+
+    /// Runs `SELECT 1` to check that the database answers, for health checks.
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Creates an account. `account_type` must name an [`AccountType`], otherwise
+    /// `Error::BadRequest` is returned.
     pub async fn create_account(
         &self,
         name: String,
         account_type: String,
         currency: String,
     ) -> Result<Account> {
+        let account_type: AccountType = account_type.parse()?;
+
         let account = sqlx::query_as!(
             Account,
             r#"
             INSERT INTO accounts (id, name, type, currency)
             VALUES ($1, $2, $3, $4)
-            RETURNING id, name, type as "account_type", currency, created_at, updated_at
+            RETURNING id, name, type as "account_type: AccountType", currency, version, created_at, updated_at
             "#,
             Uuid::new_v4(),
             name,
-            account_type,
+            account_type.as_str(),
             currency
         )
         .fetch_one(&self.pool)
@@ -96,7 +263,7 @@ impl LedgerRepository {
         let account = sqlx::query_as!(
             Account,
             r#"
-            SELECT id, name, type as "account_type", currency, created_at, updated_at
+            SELECT id, name, type as "account_type: AccountType", currency, version, created_at, updated_at
             FROM accounts
             WHERE id = $1
             "#,
@@ -108,11 +275,41 @@ impl LedgerRepository {
         Ok(account)
     }
 
+    /// Renames an account and bumps its `updated_at`.
+    ///
+    /// Returns `Error::BadRequest` if another account already has `new_name`, and
+    /// `Error::NotFound` if there is no account `id`.
+    pub async fn rename_account(&self, id: Uuid, new_name: String) -> Result<Account> {
+        let account = sqlx::query_as!(
+            Account,
+            r#"
+            UPDATE accounts
+            SET name = $2, updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, name, type as "account_type: AccountType", currency, version, created_at, updated_at
+            "#,
+            id,
+            new_name
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => {
+                psc_error::Error::BadRequest(format!(
+                    "An account named {new_name:?} already exists"
+                ))
+            }
+            e => e.into(),
+        })?;
+
+        account.ok_or_else(|| psc_error::Error::NotFound(format!("Account {id} not found")))
+    }
+
     pub async fn get_account_by_name(&self, name: &str) -> Result<Option<Account>> {
         let account = sqlx::query_as!(
             Account,
             r#"
-            SELECT id, name, type as "account_type", currency, created_at, updated_at
+            SELECT id, name, type as "account_type: AccountType", currency, version, created_at, updated_at
             FROM accounts
             WHERE name = $1
             "#,
@@ -123,16 +320,124 @@ impl LedgerRepository {
 
         Ok(account)
     }
+
+    /// Lists accounts ordered by name, optionally only those of `account_type` and/or
+    /// in `currency`, skipping `offset` accounts and returning at most `limit`.
+    ///
+    /// `account_type` must name an [`AccountType`], otherwise `Error::BadRequest` is
+    /// returned.
+    pub async fn list_accounts(
+        &self,
+        account_type: Option<String>,
+        currency: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Account>> {
+        let account_type = account_type
+            .map(|account_type| account_type.parse::<AccountType>())
+            .transpose()?;
+
+        let accounts = sqlx::query_as!(
+            Account,
+            r#"
+            SELECT id, name, type as "account_type: AccountType", currency, version, created_at, updated_at
+            FROM accounts
+            WHERE ($1::TEXT IS NULL OR type = $1)
+              AND ($2::TEXT IS NULL OR currency = $2)
+            ORDER BY name ASC
+            LIMIT $3 OFFSET $4
+            "#,
+            account_type.map(|account_type| account_type.as_str()),
+            currency,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
     pub async fn create_journal_with_entries(
         &self,
         description: Option<String>,
         entries: Vec<(Uuid, EntryType, i64)>, // (account_id, entry_type, amount_minor_units)
+    ) -> Result<Journal> {
+        self.create_journal_with_entry_metadata(description, without_metadata(entries))
+            .await
+    }
+
+    /// Like [`Self::create_journal_with_entries`], but stores `metadata` with each
+    /// entry so it can be traced back to what it was posted for.
+    pub async fn create_journal_with_entry_metadata(
+        &self,
+        description: Option<String>,
+        entries: Vec<(Uuid, EntryType, i64, EntryMetadata)>, // (account_id, entry_type, amount_minor_units, metadata)
     ) -> Result<Journal> {
         // 1. Validate debit/credit invariant
+        Self::validate_balanced(&entries)?;
+
+        let mut tx = self.pool.begin().await?;
+        let journal = Self::insert_journal(&mut tx, description, entries).await?;
+        tx.commit().await?;
+
+        Ok(journal)
+    }
+
+    /// Like [`Self::create_journal_with_entries`], but only posts if each account in
+    /// `expected_versions` is still at the given version, e.g. the one read along
+    /// with the balance the journal was computed from.
+    ///
+    /// The accounts are locked for the duration of the transaction. Returns
+    /// `Error::Conflict` if any version has moved on, in which case nothing is posted.
+    pub async fn create_journal_with_entries_versioned(
+        &self,
+        description: Option<String>,
+        entries: Vec<(Uuid, EntryType, i64)>,
+        mut expected_versions: Vec<(Uuid, i64)>, // (account_id, version)
+    ) -> Result<Journal> {
+        let entries = without_metadata(entries);
+        Self::validate_balanced(&entries)?;
+
+        // Locked in the same order as the accounts are bumped, so that concurrent
+        // journals over the same accounts cannot deadlock
+        expected_versions.sort_by_key(|(account_id, _)| *account_id);
+
+        let mut tx = self.pool.begin().await?;
+
+        for (account_id, expected_version) in expected_versions {
+            let account = sqlx::query!(
+                r#"
+                SELECT version
+                FROM accounts
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+                account_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| psc_error::Error::NotFound(format!("Account {account_id} not found")))?;
+
+            if account.version != expected_version {
+                return Err(psc_error::Error::Conflict(format!(
+                    "Account {account_id} is at version {}, expected {expected_version}",
+                    account.version
+                )));
+            }
+        }
+
+        let journal = Self::insert_journal(&mut tx, description, entries).await?;
+        tx.commit().await?;
+
+        Ok(journal)
+    }
+
+    fn validate_balanced(entries: &[(Uuid, EntryType, i64, EntryMetadata)]) -> Result<()> {
         let mut total_debits: i64 = 0;
         let mut total_credits: i64 = 0;
 
-        for (_, entry_type, amount) in &entries {
+        for (_, entry_type, amount, _) in entries {
             match entry_type {
                 EntryType::Debit => total_debits += amount,
                 EntryType::Credit => total_credits += amount,
@@ -145,8 +450,14 @@ impl LedgerRepository {
             ));
         }
 
-        let mut tx = self.pool.begin().await?;
+        Ok(())
+    }
 
+    async fn insert_journal(
+        tx: &mut Transaction<'_, Postgres>,
+        description: Option<String>,
+        entries: Vec<(Uuid, EntryType, i64, EntryMetadata)>,
+    ) -> Result<Journal> {
         // 2. Create the journal
         let journal = sqlx::query_as!(
             Journal,
@@ -158,28 +469,354 @@ impl LedgerRepository {
             Uuid::new_v4(),
             description
         )
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut **tx)
         .await?;
 
+        // Touched accounts are bumped in a fixed order so that concurrent journals
+        // lock them consistently
+        let account_ids: BTreeSet<Uuid> = entries.iter().map(|(id, _, _, _)| *id).collect();
+
         // 3. Create journal entries
-        for (account_id, entry_type, amount) in entries {
+        for (account_id, entry_type, amount, metadata) in entries {
             sqlx::query!(
                 r#"
-                INSERT INTO journal_entries (id, journal_id, account_id, entry_type, amount_minor_units)
-                VALUES ($1, $2, $3, $4, $5)
+                INSERT INTO journal_entries (id, journal_id, account_id, entry_type, amount_minor_units, metadata)
+                VALUES ($1, $2, $3, $4, $5, $6)
                 "#,
                 Uuid::new_v4(),
                 journal.id,
                 account_id,
                 entry_type.to_string(),
-                amount
+                amount,
+                Json(metadata) as _
             )
-            .execute(&mut *tx)
+            .execute(&mut **tx)
             .await?;
         }
 
+        // 4. Bump the version of every touched account
+        for account_id in account_ids {
+            sqlx::query!(
+                r#"
+                UPDATE accounts
+                SET version = version + 1
+                WHERE id = $1
+                "#,
+                account_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(journal)
+    }
+
+    /// Returns the entries posted by journal `journal_id`, with their metadata.
+    pub async fn get_journal_entries(&self, journal_id: Uuid) -> Result<Vec<JournalEntry>> {
+        let entries = sqlx::query_as!(
+            JournalEntry,
+            r#"
+            SELECT id, journal_id, account_id, entry_type, amount_minor_units, metadata as "metadata: Json<EntryMetadata>", created_at, updated_at
+            FROM journal_entries
+            WHERE journal_id = $1
+            ORDER BY created_at ASC, id ASC
+            "#,
+            journal_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Returns the entries posted to `account_id` between `from` and `to`
+    /// (both inclusive), oldest first.
+    pub async fn entries_between(
+        &self,
+        account_id: Uuid,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> Result<Vec<JournalEntry>> {
+        if from > to {
+            return Err(psc_error::Error::InvalidArgument(format!(
+                "Invalid date range: {from} is after {to}"
+            )));
+        }
+
+        let entries = sqlx::query_as!(
+            JournalEntry,
+            r#"
+            SELECT id, journal_id, account_id, entry_type, amount_minor_units, metadata as "metadata: Json<EntryMetadata>", created_at, updated_at
+            FROM journal_entries
+            WHERE account_id = $1 AND created_at BETWEEN $2 AND $3
+            ORDER BY created_at ASC, id ASC
+            "#,
+            account_id,
+            from,
+            to
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Returns up to `limit` entries posted to `account_id`, oldest first, starting
+    /// after the entry `cursor` points at, along with the token for the next page.
+    ///
+    /// Pass `None` for the first page and the returned token for each following page;
+    /// the token is `None` once there are no more entries. Pages are keyed on
+    /// `(created_at, id)` rather than an offset, so entries posted while paging never
+    /// cause entries to be repeated or skipped.
+    ///
+    /// Returns `Error::InvalidArgument` if `limit` is not positive and
+    /// `Error::BadRequest` if `cursor` is not a token returned by this method.
+    pub async fn list_entries_keyset(
+        &self,
+        account_id: Uuid,
+        limit: i64,
+        cursor: Option<String>,
+    ) -> Result<(Vec<JournalEntry>, Option<String>)> {
+        if limit <= 0 {
+            return Err(psc_error::Error::InvalidArgument(format!(
+                "Invalid page size: {limit}"
+            )));
+        }
+        let (after_created_at, after_id) = match cursor.as_deref().map(decode_entry_cursor) {
+            Some(cursor) => {
+                let (created_at, id) = cursor?;
+                (Some(created_at), Some(id))
+            }
+            None => (None, None),
+        };
+
+        // Fetch one extra entry to learn whether there is a next page
+        let mut entries = sqlx::query_as!(
+            JournalEntry,
+            r#"
+            SELECT id, journal_id, account_id, entry_type, amount_minor_units, metadata as "metadata: Json<EntryMetadata>", created_at, updated_at
+            FROM journal_entries
+            WHERE account_id = $1
+              AND ($2::TIMESTAMPTZ IS NULL OR (created_at, id) > ($2::TIMESTAMPTZ, $3::UUID))
+            ORDER BY created_at ASC, id ASC
+            LIMIT $4
+            "#,
+            account_id,
+            after_created_at,
+            after_id,
+            limit + 1
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let next_cursor = if entries.len() as i64 > limit {
+            entries.truncate(limit as usize);
+            entries.last().map(encode_entry_cursor)
+        } else {
+            None
+        };
+
+        Ok((entries, next_cursor))
+    }
+
+    pub async fn get_balance(&self, account_id: Uuid) -> Result<AccountBalance> {
+        let mut tx = self.pool.begin().await?;
+        let balance = Self::fetch_balance(&mut tx, account_id).await?;
+        tx.commit().await?;
+
+        Ok(balance)
+    }
+
+    async fn fetch_balance(
+        tx: &mut Transaction<'_, Postgres>,
+        account_id: Uuid,
+    ) -> Result<AccountBalance> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                type AS "account_type: AccountType",
+                (SELECT COALESCE(SUM(CASE WHEN entry_type = 'CREDIT' THEN amount_minor_units ELSE -amount_minor_units END), 0)
+                 FROM journal_entries
+                 WHERE account_id = $1)::BIGINT AS "credits_minus_debits!",
+                (SELECT COALESCE(SUM(amount_minor_units), 0)
+                 FROM balance_reservations
+                 WHERE account_id = $1 AND status = 'ACTIVE')::BIGINT AS "reserved_minor_units!"
+            FROM accounts
+            WHERE id = $1
+            "#,
+            account_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| psc_error::Error::NotFound(format!("Account {account_id} not found")))?;
+
+        let balance_minor_units = match row.account_type.normal_balance() {
+            EntryType::Credit => row.credits_minus_debits,
+            EntryType::Debit => -row.credits_minus_debits,
+        };
+
+        Ok(AccountBalance {
+            account_id,
+            balance_minor_units,
+            reserved_minor_units: row.reserved_minor_units,
+        })
+    }
+
+    /// Holds `amount_minor_units` of the account's available balance, e.g. for a
+    /// pending payout.
+    ///
+    /// The account row is locked for the duration of the transaction, so
+    /// concurrent reservations on the same account are checked one after another
+    /// and cannot both spend the same funds.
+    pub async fn reserve(
+        &self,
+        account_id: Uuid,
+        amount_minor_units: i64,
+    ) -> Result<ReservationId> {
+        if amount_minor_units <= 0 {
+            return Err(psc_error::Error::BadRequest(
+                "Reservation amount must be positive".to_string(),
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            SELECT id
+            FROM accounts
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            account_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| psc_error::Error::NotFound(format!("Account {account_id} not found")))?;
+
+        let balance = Self::fetch_balance(&mut tx, account_id).await?;
+        if amount_minor_units > balance.available_minor_units() {
+            return Err(psc_error::Error::BadRequest(format!(
+                "Insufficient available balance: requested {amount_minor_units}, available {}",
+                balance.available_minor_units()
+            )));
+        }
+
+        let reservation_id = ReservationId(Uuid::new_v4());
+        sqlx::query!(
+            r#"
+            INSERT INTO balance_reservations (id, account_id, amount_minor_units)
+            VALUES ($1, $2, $3)
+            "#,
+            reservation_id.0,
+            account_id,
+            amount_minor_units
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(reservation_id)
+    }
+
+    /// Cancels an active reservation, returning its funds to the available balance.
+    pub async fn release(&self, reservation_id: ReservationId) -> Result<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE balance_reservations
+            SET status = 'RELEASED'
+            WHERE id = $1 AND status = 'ACTIVE'
+            "#,
+            reservation_id.0
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(psc_error::Error::NotFound(format!(
+                "Active reservation {reservation_id} not found"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Consumes an active reservation by posting a journal that moves the reserved
+    /// amount from the reserved account to `destination_account_id`.
+    ///
+    /// The reserved account is posted on the side that decreases its balance (a
+    /// debit for liability, equity and revenue accounts, a credit for asset and
+    /// expense accounts) and the destination on the other side.
+    ///
+    /// The reservation is marked settled in the same transaction as the journal, so
+    /// the funds are never counted as both reserved and spent, or as neither.
+    pub async fn settle(
+        &self,
+        reservation_id: ReservationId,
+        destination_account_id: Uuid,
+        description: Option<String>,
+    ) -> Result<Journal> {
+        let mut tx = self.pool.begin().await?;
+
+        let reservation = sqlx::query!(
+            r#"
+            UPDATE balance_reservations
+            SET status = 'SETTLED'
+            WHERE id = $1 AND status = 'ACTIVE'
+            RETURNING account_id, amount_minor_units
+            "#,
+            reservation_id.0
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            psc_error::Error::NotFound(format!("Active reservation {reservation_id} not found"))
+        })?;
+
+        let account_type = sqlx::query_scalar!(
+            r#"
+            SELECT type AS "account_type: AccountType"
+            FROM accounts
+            WHERE id = $1
+            "#,
+            reservation.account_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let spend = account_type.normal_balance().opposite();
+
+        let journal = Self::insert_journal(
+            &mut tx,
+            description,
+            without_metadata(vec![
+                (
+                    reservation.account_id,
+                    spend.clone(),
+                    reservation.amount_minor_units,
+                ),
+                (
+                    destination_account_id,
+                    spend.opposite(),
+                    reservation.amount_minor_units,
+                ),
+            ]),
+        )
+        .await?;
+
         tx.commit().await?;
 
         Ok(journal)
     }
 }
+
+fn without_metadata(
+    entries: Vec<(Uuid, EntryType, i64)>,
+) -> Vec<(Uuid, EntryType, i64, EntryMetadata)> {
+    entries
+        .into_iter()
+        .map(|(account_id, entry_type, amount)| {
+            (account_id, entry_type, amount, EntryMetadata::new())
+        })
+        .collect()
+}
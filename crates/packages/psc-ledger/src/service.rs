@@ -1,6 +1,6 @@
+use crate::EntryMetadata;
 use crate::EntryType;
 use crate::LedgerRepository;
-use psc_error::Error;
 use sqlx::PgPool;
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
@@ -38,7 +38,7 @@ impl JournalServiceTrait for JournalService {
     ) -> Result<Response<PostJournalResponse>, Status> {
         let request = request.into_inner();
 
-        let entries_to_create: Vec<(Uuid, EntryType, i64)> = request
+        let entries_to_create: Vec<(Uuid, EntryType, i64, EntryMetadata)> = request
             .entries
             .into_iter()
             .filter_map(|entry| {
@@ -55,19 +55,16 @@ impl JournalServiceTrait for JournalService {
                     account_id_uuid,
                     entry_type,
                     entry.amount.unwrap().amount_minor_units,
+                    entry.metadata,
                 )) // Corrected field name
             })
             .collect();
 
-        // Convert the psc_error::Error to tonic::Status
+        // psc_error::Error converts into tonic::Status via `?`
         let journal = self
             .repository
-            .create_journal_with_entries(request.narrative.into(), entries_to_create) // Converted String to Option<String>
-            .await
-            .map_err(|e| match e {
-                Error::BadRequest(msg) => Status::invalid_argument(msg),
-                _ => Status::internal(e.to_string()),
-            })?;
+            .create_journal_with_entry_metadata(request.narrative.into(), entries_to_create) // Converted String to Option<String>
+            .await?;
 
         let response = PostJournalResponse {
             posted_entries: vec![], // TODO: Populate with actual posted entries
@@ -0,0 +1,156 @@
+use psc_error::Error;
+use psc_ledger::{AccountType, EntryType, LedgerRepository};
+use sqlx::PgPool;
+
+#[test]
+fn test_account_type_parses_known_types() {
+    assert_eq!("ASSET".parse::<AccountType>().unwrap(), AccountType::Asset);
+    assert_eq!(
+        "liability".parse::<AccountType>().unwrap(),
+        AccountType::Liability
+    );
+    assert_eq!(AccountType::Revenue.to_string(), "REVENUE");
+}
+
+#[test]
+fn test_account_type_rejects_unknown_type() {
+    assert!(matches!(
+        "ASSSET".parse::<AccountType>(),
+        Err(Error::BadRequest(_))
+    ));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_create_account_rejects_invalid_type(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+
+    let result = repository
+        .create_account("float".to_string(), "ASSSET".to_string(), "XAF".to_string())
+        .await;
+
+    assert!(matches!(result, Err(Error::BadRequest(_))));
+    assert!(
+        repository
+            .get_account_by_name("float")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_balance_sign_follows_account_type(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let float = repository
+        .create_account("float".to_string(), "ASSET".to_string(), "XAF".to_string())
+        .await
+        .unwrap();
+    let wallet = repository
+        .create_account(
+            "wallet".to_string(),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(float.account_type, AccountType::Asset);
+
+    repository
+        .create_journal_with_entries(
+            Some("Top up".to_string()),
+            vec![
+                (float.id, EntryType::Debit, 1_000),
+                (wallet.id, EntryType::Credit, 1_000),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let float_balance = repository.get_balance(float.id).await.unwrap();
+    let wallet_balance = repository.get_balance(wallet.id).await.unwrap();
+    assert_eq!(float_balance.balance_minor_units, 1_000);
+    assert_eq!(wallet_balance.balance_minor_units, 1_000);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_rename_account(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let float = repository
+        .create_account("float".to_string(), "ASSET".to_string(), "XAF".to_string())
+        .await
+        .unwrap();
+    repository
+        .create_account(
+            "wallet".to_string(),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let result = repository
+        .rename_account(float.id, "wallet".to_string())
+        .await;
+    assert!(matches!(result, Err(Error::BadRequest(_))));
+
+    let renamed = repository
+        .rename_account(float.id, "mtn-float".to_string())
+        .await
+        .unwrap();
+    assert_eq!(renamed.name, "mtn-float");
+    assert!(renamed.updated_at >= float.updated_at);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_list_accounts_filters_by_type_and_currency(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    for (name, account_type, currency) in [
+        ("float-xaf", "ASSET", "XAF"),
+        ("wallet-a-xaf", "LIABILITY", "XAF"),
+        ("wallet-b-xaf", "LIABILITY", "XAF"),
+        ("wallet-usd", "LIABILITY", "USD"),
+        ("fees-xaf", "REVENUE", "XAF"),
+    ] {
+        repository
+            .create_account(
+                name.to_string(),
+                account_type.to_string(),
+                currency.to_string(),
+            )
+            .await
+            .unwrap();
+    }
+    let names = |accounts: Vec<psc_ledger::Account>| -> Vec<String> {
+        accounts.into_iter().map(|account| account.name).collect()
+    };
+
+    let all = repository.list_accounts(None, None, 100, 0).await.unwrap();
+    assert_eq!(all.len(), 5);
+
+    let xaf_liabilities = repository
+        .list_accounts(Some("LIABILITY".to_string()), Some("XAF"), 100, 0)
+        .await
+        .unwrap();
+    assert_eq!(names(xaf_liabilities), vec!["wallet-a-xaf", "wallet-b-xaf"]);
+
+    let usd = repository
+        .list_accounts(None, Some("USD"), 100, 0)
+        .await
+        .unwrap();
+    assert_eq!(names(usd), vec!["wallet-usd"]);
+
+    let second_page = repository
+        .list_accounts(Some("liability".to_string()), None, 2, 2)
+        .await
+        .unwrap();
+    assert_eq!(names(second_page), vec!["wallet-usd"]);
+
+    let result = repository
+        .list_accounts(Some("ASSSET".to_string()), None, 100, 0)
+        .await;
+    assert!(matches!(result, Err(Error::BadRequest(_))));
+}
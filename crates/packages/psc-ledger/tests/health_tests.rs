@@ -0,0 +1,16 @@
+use psc_ledger::LedgerRepository;
+use psc_ledger::health::report_health;
+use sqlx::PgPool;
+use tonic_health::ServingStatus;
+use tonic_health::server::health_reporter;
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_healthy_pool_reports_serving(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let (reporter, _health_service) = health_reporter();
+
+    let status = report_health(&repository, &reporter).await;
+
+    assert_eq!(status, ServingStatus::Serving);
+}
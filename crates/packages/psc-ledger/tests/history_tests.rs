@@ -0,0 +1,167 @@
+use psc_error::Error;
+use psc_ledger::{EntryMetadata, EntryType, LedgerRepository};
+use sqlx::PgPool;
+use time::{Duration, OffsetDateTime};
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_entries_between_filters_by_date_range(pool: PgPool) {
+    let repository = LedgerRepository::new(pool.clone());
+    let float = repository
+        .create_account("float".to_string(), "ASSET".to_string(), "XAF".to_string())
+        .await
+        .unwrap();
+    let wallet = repository
+        .create_account(
+            "wallet".to_string(),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .unwrap();
+
+    // One top up per day, 4 days ago through today
+    let now = OffsetDateTime::now_utc();
+    for days_ago in (0..5).rev() {
+        let journal = repository
+            .create_journal_with_entries(
+                Some(format!("Top up {days_ago} days ago")),
+                vec![
+                    (float.id, EntryType::Debit, 100 * (days_ago + 1)),
+                    (wallet.id, EntryType::Credit, 100 * (days_ago + 1)),
+                ],
+            )
+            .await
+            .unwrap();
+
+        sqlx::query("UPDATE journal_entries SET created_at = $1 WHERE journal_id = $2")
+            .bind(now - Duration::days(days_ago))
+            .bind(journal.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let entries = repository
+        .entries_between(
+            wallet.id,
+            now - Duration::days(3) - Duration::hours(1),
+            now - Duration::days(1) + Duration::hours(1),
+        )
+        .await
+        .unwrap();
+
+    let amounts: Vec<i64> = entries.iter().map(|e| e.amount_minor_units).collect();
+    assert_eq!(amounts, vec![400, 300, 200]);
+    assert!(entries.iter().all(|e| e.account_id == wallet.id));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_entries_between_rejects_inverted_range(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let now = OffsetDateTime::now_utc();
+
+    let result = repository
+        .entries_between(uuid::Uuid::new_v4(), now, now - Duration::days(1))
+        .await;
+
+    assert!(matches!(result, Err(Error::InvalidArgument(_))));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_entry_metadata_round_trips(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let float = repository
+        .create_account("float".to_string(), "ASSET".to_string(), "XAF".to_string())
+        .await
+        .unwrap();
+    let wallet = repository
+        .create_account(
+            "wallet".to_string(),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .unwrap();
+    let metadata = EntryMetadata::from([
+        ("payment_id".to_string(), "pay-123".to_string()),
+        ("provider".to_string(), "mtn".to_string()),
+    ]);
+
+    let journal = repository
+        .create_journal_with_entry_metadata(
+            Some("Top up".to_string()),
+            vec![
+                (float.id, EntryType::Debit, 1_000, metadata.clone()),
+                (wallet.id, EntryType::Credit, 1_000, EntryMetadata::new()),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let entries = repository.get_journal_entries(journal.id).await.unwrap();
+    assert_eq!(entries.len(), 2);
+    let debit = entries.iter().find(|e| e.account_id == float.id).unwrap();
+    let credit = entries.iter().find(|e| e.account_id == wallet.id).unwrap();
+    assert_eq!(debit.metadata.0, metadata);
+    assert!(credit.metadata.0.is_empty());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_list_entries_keyset_pages_without_drift(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let float = repository
+        .create_account("float".to_string(), "ASSET".to_string(), "XAF".to_string())
+        .await
+        .unwrap();
+    let wallet = repository
+        .create_account(
+            "wallet".to_string(),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .unwrap();
+    let top_up = |amount: i64| {
+        repository.create_journal_with_entries(
+            Some(format!("Top up {amount}")),
+            vec![
+                (float.id, EntryType::Debit, amount),
+                (wallet.id, EntryType::Credit, amount),
+            ],
+        )
+    };
+    for amount in 1..=5 {
+        top_up(amount).await.unwrap();
+    }
+
+    let (first_page, cursor) = repository
+        .list_entries_keyset(wallet.id, 2, None)
+        .await
+        .unwrap();
+    assert_eq!(first_page.len(), 2);
+    assert!(cursor.is_some());
+
+    // An entry posted mid-scan shows up at the end instead of shifting the pages
+    top_up(6).await.unwrap();
+
+    let mut amounts: Vec<i64> = first_page.iter().map(|e| e.amount_minor_units).collect();
+    let mut cursor = cursor;
+    while let Some(token) = cursor {
+        let (page, next) = repository
+            .list_entries_keyset(wallet.id, 2, Some(token))
+            .await
+            .unwrap();
+        amounts.extend(page.iter().map(|e| e.amount_minor_units));
+        cursor = next;
+    }
+    assert_eq!(amounts, vec![1, 2, 3, 4, 5, 6]);
+
+    let result = repository
+        .list_entries_keyset(wallet.id, 2, Some("not-a-token".to_string()))
+        .await;
+    assert!(matches!(result, Err(Error::BadRequest(_))));
+}
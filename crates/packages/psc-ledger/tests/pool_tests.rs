@@ -0,0 +1,16 @@
+use psc_ledger::LedgerRepository;
+
+#[tokio::test]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_connect_with_small_pool_runs_queries() {
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let repository = LedgerRepository::connect(&database_url, 2)
+        .await
+        .expect("Failed to connect to the ledger database");
+
+    let account = repository
+        .get_account_by_name("missing-account")
+        .await
+        .unwrap();
+    assert!(account.is_none());
+}
@@ -0,0 +1,163 @@
+use psc_error::Error;
+use psc_ledger::{EntryType, LedgerRepository};
+use sqlx::PgPool;
+
+async fn funded_account(repository: &LedgerRepository, amount_minor_units: i64) -> uuid::Uuid {
+    let suffix = uuid::Uuid::new_v4();
+    let wallet = repository
+        .create_account(
+            format!("wallet-{suffix}"),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .expect("Failed to create wallet account");
+    let float = repository
+        .create_account(
+            format!("float-{suffix}"),
+            "ASSET".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .expect("Failed to create float account");
+
+    repository
+        .create_journal_with_entries(
+            Some("Top up".to_string()),
+            vec![
+                (float.id, EntryType::Debit, amount_minor_units),
+                (wallet.id, EntryType::Credit, amount_minor_units),
+            ],
+        )
+        .await
+        .expect("Failed to fund wallet");
+
+    wallet.id
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_concurrent_reservations_cannot_overspend(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let wallet = funded_account(&repository, 1_000).await;
+
+    let (first, second) = tokio::join!(
+        repository.reserve(wallet, 700),
+        repository.reserve(wallet, 700)
+    );
+
+    let results = [first, second];
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+    assert!(
+        results
+            .iter()
+            .any(|r| matches!(r, Err(Error::BadRequest(_))))
+    );
+
+    let balance = repository.get_balance(wallet).await.unwrap();
+    assert_eq!(balance.balance_minor_units, 1_000);
+    assert_eq!(balance.reserved_minor_units, 700);
+    assert_eq!(balance.available_minor_units(), 300);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_release_returns_funds(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let wallet = funded_account(&repository, 1_000).await;
+
+    let reservation = repository.reserve(wallet, 1_000).await.unwrap();
+    assert!(repository.reserve(wallet, 1).await.is_err());
+
+    repository.release(reservation).await.unwrap();
+    assert!(matches!(
+        repository.release(reservation).await,
+        Err(Error::NotFound(_))
+    ));
+    assert!(repository.reserve(wallet, 1_000).await.is_ok());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_settle_posts_journal_and_clears_reservation(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let wallet = funded_account(&repository, 1_000).await;
+    let merchant = repository
+        .create_account(
+            format!("merchant-{}", uuid::Uuid::new_v4()),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .unwrap();
+
+    let reservation = repository.reserve(wallet, 400).await.unwrap();
+    repository
+        .settle(reservation, merchant.id, Some("Payout".to_string()))
+        .await
+        .unwrap();
+
+    let balance = repository.get_balance(wallet).await.unwrap();
+    assert_eq!(balance.balance_minor_units, 600);
+    assert_eq!(balance.reserved_minor_units, 0);
+    assert_eq!(
+        repository
+            .get_balance(merchant.id)
+            .await
+            .unwrap()
+            .balance_minor_units,
+        400
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_settle_on_asset_account_spends_reserved_funds(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let suffix = uuid::Uuid::new_v4();
+    let mut accounts = Vec::new();
+    for (name, account_type) in [
+        ("float", "ASSET"),
+        ("bank", "ASSET"),
+        ("wallet", "LIABILITY"),
+    ] {
+        let account = repository
+            .create_account(
+                format!("{name}-{suffix}"),
+                account_type.to_string(),
+                "XAF".to_string(),
+            )
+            .await
+            .unwrap();
+        accounts.push(account.id);
+    }
+    let (float, bank, wallet) = (accounts[0], accounts[1], accounts[2]);
+    repository
+        .create_journal_with_entries(
+            Some("Top up".to_string()),
+            vec![
+                (float, EntryType::Debit, 1_000),
+                (wallet, EntryType::Credit, 1_000),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let reservation = repository.reserve(float, 400).await.unwrap();
+    repository
+        .settle(reservation, bank, Some("Sweep".to_string()))
+        .await
+        .unwrap();
+
+    let balance = repository.get_balance(float).await.unwrap();
+    assert_eq!(balance.balance_minor_units, 600);
+    assert_eq!(balance.reserved_minor_units, 0);
+    assert_eq!(
+        repository
+            .get_balance(bank)
+            .await
+            .unwrap()
+            .balance_minor_units,
+        400
+    );
+}
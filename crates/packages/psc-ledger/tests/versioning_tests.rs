@@ -0,0 +1,100 @@
+use psc_error::Error;
+use psc_ledger::{Account, EntryType, LedgerRepository};
+use sqlx::PgPool;
+
+async fn accounts(repository: &LedgerRepository) -> (Account, Account) {
+    let float = repository
+        .create_account("float".to_string(), "ASSET".to_string(), "XAF".to_string())
+        .await
+        .unwrap();
+    let wallet = repository
+        .create_account(
+            "wallet".to_string(),
+            "LIABILITY".to_string(),
+            "XAF".to_string(),
+        )
+        .await
+        .unwrap();
+
+    (float, wallet)
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_posting_bumps_account_versions(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let (float, wallet) = accounts(&repository).await;
+    assert_eq!(wallet.version, 0);
+
+    repository
+        .create_journal_with_entries(
+            None,
+            vec![
+                (float.id, EntryType::Debit, 100),
+                (wallet.id, EntryType::Credit, 100),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let wallet = repository
+        .get_account_by_id(wallet.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(wallet.version, 1);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_concurrent_versioned_posts_conflict(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let (float, wallet) = accounts(&repository).await;
+
+    // Both writers computed their journal from the same read of the wallet
+    let post = || {
+        repository.create_journal_with_entries_versioned(
+            None,
+            vec![
+                (float.id, EntryType::Debit, 100),
+                (wallet.id, EntryType::Credit, 100),
+            ],
+            vec![(wallet.id, wallet.version)],
+        )
+    };
+    let (first, second) = tokio::join!(post(), post());
+
+    let results = [first, second];
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+    assert!(results.iter().any(|r| matches!(r, Err(Error::Conflict(_)))));
+
+    let balance = repository.get_balance(wallet.id).await.unwrap();
+    assert_eq!(balance.balance_minor_units, 100);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+#[ignore] // This test requires a running Postgres instance (DATABASE_URL)
+async fn test_versioned_posts_lock_accounts_in_a_fixed_order(pool: PgPool) {
+    let repository = LedgerRepository::new(pool);
+    let (float, wallet) = accounts(&repository).await;
+
+    // The writers list the same accounts in opposite orders
+    let post = |expected_versions| {
+        repository.create_journal_with_entries_versioned(
+            None,
+            vec![
+                (float.id, EntryType::Debit, 100),
+                (wallet.id, EntryType::Credit, 100),
+            ],
+            expected_versions,
+        )
+    };
+    let (first, second) = tokio::join!(
+        post(vec![(float.id, float.version), (wallet.id, wallet.version)]),
+        post(vec![(wallet.id, wallet.version), (float.id, float.version)]),
+    );
+
+    let results = [first, second];
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+    assert!(results.iter().any(|r| matches!(r, Err(Error::Conflict(_)))));
+}
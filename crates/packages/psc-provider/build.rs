@@ -10,6 +10,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "../../../protos/psc/payout/v1/payout.proto",
                 "../../../protos/psc/journal/v1/journal.proto",
                 "../../../protos/psc/balance/v1/balance.proto",
+                "../../../protos/psc/refund/v1/refund.proto",
             ],
             &["../../../protos"], // Specify the root directory for proto imports
         )?;
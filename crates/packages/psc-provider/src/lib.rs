@@ -8,10 +8,16 @@
 //! allows deterministic testing of success, error and latency scenarios.
 
 use async_trait::async_trait;
+use futures::{StreamExt, stream};
+use std::collections::HashMap;
 use std::time::Duration;
 
 use psc_error::Error;
 
+pub mod router;
+
+pub use router::ProviderRouter;
+
 // Assuming these are generated by Tonic/Prost from the .proto files
 // You might need to adjust the paths based on your actual build setup
 pub mod pb {
@@ -40,66 +46,195 @@ pub mod pb {
             tonic::include_proto!("psc.balance.v1");
         }
     }
+    pub mod refund {
+        pub mod v1 {
+            tonic::include_proto!("psc.refund.v1");
+        }
+    }
 }
 
 use pb::balance::v1::{Balance, GetBalanceRequest};
 use pb::common::v1::{Id, Money, Pagination, PaginationRequest, Status, Timestamp};
-use pb::journal::v1::{JournalEntry, PostJournalRequest};
+use pb::journal::v1::PostJournalRequest;
 use pb::payment::v1::{CreatePaymentRequest, Payment, PaymentStatus};
 use pb::payout::v1::{CreatePayoutRequest, Payout, PayoutStatus};
+use pb::refund::v1::Refund;
+
+/// Request-scoped metadata passed to every provider call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ctx {
+    /// Instant after which the caller no longer waits for the result. Providers
+    /// abandon outbound calls still in flight at this point.
+    pub deadline: Option<tokio::time::Instant>,
+    /// Id tying the events and log lines of this request to the request that caused
+    /// it, propagated from the caller when it has one.
+    pub correlation_id: Option<String>,
+}
+
+impl Ctx {
+    /// Set the instant after which provider calls are abandoned.
+    pub fn with_deadline(mut self, deadline: tokio::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set the deadline to `timeout` from now, e.g. from an incoming `grpc-timeout`.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(tokio::time::Instant::now() + timeout)
+    }
+
+    /// Set the id correlating this request's events and log lines.
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+}
 
-/// Context alias for passing request-scoped metadata.
-pub type Ctx = ();
+/// Lifecycle state of a payment or payout on the provider side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionState {
+    Pending,
+    Successful,
+    Failed,
+}
+
+/// Status of a single transaction, as returned by [`Provider::get_transaction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionStatus {
+    /// Our reference for the transaction (the idempotency key sent to the provider).
+    pub reference: String,
+    pub state: TransactionState,
+    /// Provider-side transaction id, once the provider has assigned one.
+    pub provider_transaction_id: Option<String>,
+    /// Provider-supplied reason when the transaction failed.
+    pub reason: Option<String>,
+    /// Provider-specific fields, under keys documented by each provider.
+    pub metadata: HashMap<String, String>,
+}
+
+/// How many payouts [`Provider::withdraw_batch`] submits to the provider at once.
+pub const WITHDRAW_BATCH_CONCURRENCY: usize = 8;
 
 /// Provider trait that abstracts provider operations.
 #[async_trait]
 pub trait Provider: Send + Sync {
     async fn deposit(&self, ctx: &Ctx, req: CreatePaymentRequest) -> Result<Payment, Error>;
     async fn withdraw(&self, ctx: &Ctx, req: CreatePayoutRequest) -> Result<Payout, Error>;
-    async fn refund(&self, ctx: &Ctx, req: PostJournalRequest) -> Result<JournalEntry, Error>;
+    /// Submit many payouts, at most [`WITHDRAW_BATCH_CONCURRENCY`] at a time.
+    ///
+    /// Returns one result per request, in request order. A failed payout does not
+    /// stop the others.
+    async fn withdraw_batch(
+        &self,
+        ctx: &Ctx,
+        reqs: Vec<CreatePayoutRequest>,
+    ) -> Vec<Result<Payout, Error>> {
+        stream::iter(reqs)
+            .map(|req| self.withdraw(ctx, req))
+            .buffered(WITHDRAW_BATCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+    /// Return funds to a payer. The result reports the provider-side status only;
+    /// posting the refund to the ledger is up to the caller.
+    async fn refund(&self, ctx: &Ctx, req: PostJournalRequest) -> Result<Refund, Error>;
     async fn query(&self, ctx: &Ctx, req: GetBalanceRequest) -> Result<Balance, Error>;
+    /// Poll the provider for the current status of a previously submitted payment or
+    /// payout.
+    async fn get_transaction(&self, ctx: &Ctx, reference: &str)
+    -> Result<TransactionStatus, Error>;
+    /// Cancel a pending payment or payout, e.g. when the payer abandons the flow.
+    ///
+    /// Providers without a way to void a submitted transaction return
+    /// `Error::Unsupported`, which is the default.
+    async fn cancel(&self, _ctx: &Ctx, reference: &str) -> Result<(), Error> {
+        Err(Error::Unsupported(format!(
+            "cancelling transaction {reference}"
+        )))
+    }
+    /// Check a webhook's signature and, for providers that send one, the freshness of
+    /// its timestamp header. Returns `Ok(false)` for a bad signature or a stale or
+    /// missing timestamp.
     async fn verify_webhook(
         &self,
         ctx: &Ctx,
         payload: &[u8],
         signature_header: Option<&str>,
+        timestamp_header: Option<&str>,
     ) -> Result<bool, Error>;
 }
 
+#[cfg(feature = "mock")]
+pub use mock::{MOCK_WEBHOOK_SECRET, MockBehavior, MockProvider};
+
 #[cfg(feature = "mock")]
 mod mock {
     use super::*;
     use super::{
         Balance, CreatePaymentRequest, CreatePayoutRequest, Ctx, Error, GetBalanceRequest, Id,
-        JournalEntry, Money, Payment, PaymentStatus, Payout, PayoutStatus, PostJournalRequest,
-        Provider, Result, Timestamp, async_trait,
+        Money, Payment, PaymentStatus, Payout, PayoutStatus, PostJournalRequest, Provider, Refund,
+        Result, Timestamp, TransactionState, TransactionStatus, async_trait,
     };
+    use crate::pb::refund::v1::RefundStatus;
     use cuid::cuid;
+    use hmac::{Hmac, Mac};
+    use psc_error::ProviderErrorKind;
+    use sha2::Sha256;
+    use std::collections::HashSet;
     use std::sync::Arc;
     use std::time::Instant;
     use tokio::sync::Mutex;
 
+    /// Secret used to sign and verify webhooks unless overridden.
+    pub const MOCK_WEBHOOK_SECRET: &[u8] = b"mock-webhook-secret";
+
     /// Behavior modes for the MockProvider.
     #[derive(Debug, Clone)]
     pub enum MockBehavior {
         AlwaysSucceed,
         AlwaysFail(String),
         FailOnceThenSucceed,
+        /// Fail the first `n` calls (counted across all methods), then succeed.
+        FailNTimesThenSucceed(usize),
         Delay(Duration, Box<MockBehavior>),
     }
 
     /// Internal state for behaviors that need to record invocations.
     #[derive(Debug, Default)]
     struct MockState {
-        pub fail_once_consumed: bool,
+        pub failures_consumed: usize,
         pub last_invocation: Option<Instant>,
+        pub cancelled: HashSet<String>,
     }
 
+    impl MockState {
+        /// Records a failure if `behavior` still has failures left to inject.
+        fn consume_failure(&mut self, behavior: &MockBehavior) -> bool {
+            let failures = match behavior {
+                MockBehavior::FailOnceThenSucceed => 1,
+                MockBehavior::FailNTimesThenSucceed(n) => *n,
+                _ => 0,
+            };
+            if self.failures_consumed < failures {
+                self.failures_consumed += 1;
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    /// Derives the provider reference of a request from its idempotency key.
+    type ReferenceFn = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
     /// A configurable mock provider for tests and local development.
     #[derive(Clone)]
     pub struct MockProvider {
         behavior: MockBehavior,
         state: Arc<Mutex<MockState>>,
+        webhook_secret: Vec<u8>,
+        reference: Option<ReferenceFn>,
+        result_state: Option<TransactionState>,
     }
 
     impl MockProvider {
@@ -107,8 +242,132 @@ mod mock {
             Self {
                 behavior,
                 state: Arc::new(Mutex::new(MockState::default())),
+                webhook_secret: MOCK_WEBHOOK_SECRET.to_vec(),
+                reference: None,
+                result_state: None,
+            }
+        }
+
+        /// Report `reference(idempotency_key)` as the provider reference of every
+        /// payment, payout and refund, and as the provider transaction id in
+        /// `get_transaction`. By default the idempotency key itself is reported.
+        pub fn with_reference(
+            mut self,
+            reference: impl Fn(&str) -> String + Send + Sync + 'static,
+        ) -> Self {
+            self.reference = Some(Arc::new(reference));
+            self
+        }
+
+        /// Report every successful call as having reached `state`, e.g.
+        /// [`TransactionState::Pending`] to model asynchronous settlement. By default
+        /// payments and payouts complete immediately and refunds stay pending.
+        pub fn with_status(mut self, state: TransactionState) -> Self {
+            self.result_state = Some(state);
+            self
+        }
+
+        fn provider_reference(&self, idempotency_key: &str) -> String {
+            match &self.reference {
+                Some(reference) => reference(idempotency_key),
+                None => idempotency_key.to_string(),
+            }
+        }
+
+        fn payment(&self, req: CreatePaymentRequest) -> Payment {
+            let status = match self.result_state.unwrap_or(TransactionState::Successful) {
+                TransactionState::Pending => PaymentStatus::Pending,
+                TransactionState::Successful => PaymentStatus::Completed,
+                TransactionState::Failed => PaymentStatus::Failed,
+            };
+            Payment {
+                id: Some(Id {
+                    value: cuid().to_string(),
+                }),
+                amount: req.amount,
+                status: status as i32,
+                created_at: now(),
+                updated_at: now(),
+                metadata: req.metadata,
+                reference: self.provider_reference(&req.idempotency_key),
+            }
+        }
+
+        fn payout(&self, req: CreatePayoutRequest) -> Payout {
+            let status = match self.result_state.unwrap_or(TransactionState::Successful) {
+                TransactionState::Pending => PayoutStatus::Pending,
+                TransactionState::Successful => PayoutStatus::Sent,
+                TransactionState::Failed => PayoutStatus::Failed,
+            };
+            Payout {
+                id: Some(Id {
+                    value: cuid().to_string(),
+                }),
+                amount: req.amount,
+                status: status as i32,
+                created_at: now(),
+                updated_at: now(),
+                external_reference: self.provider_reference(&req.idempotency_key),
+                metadata: req.metadata,
             }
         }
+
+        fn refund_result(&self, req: PostJournalRequest) -> Refund {
+            let status = match self.result_state.unwrap_or(TransactionState::Pending) {
+                TransactionState::Pending => RefundStatus::Pending,
+                TransactionState::Successful => RefundStatus::Succeeded,
+                TransactionState::Failed => RefundStatus::Failed,
+            };
+            Refund {
+                id: Some(Id {
+                    value: cuid().to_string(),
+                }),
+                amount: req.entries.first().and_then(|e| e.amount.clone()),
+                status: status as i32,
+                provider_reference: self.provider_reference(&req.idempotency_key),
+                created_at: now(),
+                metadata: req.metadata,
+                ..Default::default()
+            }
+        }
+
+        /// Use `secret` instead of [`MOCK_WEBHOOK_SECRET`] for webhook signatures.
+        pub fn with_webhook_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+            self.webhook_secret = secret.into();
+            self
+        }
+
+        /// Sign `payload` the way a provider would: hex-encoded HMAC-SHA256 under the
+        /// webhook secret. The result is accepted by `verify_webhook`.
+        pub fn sign_webhook(&self, payload: &[u8]) -> String {
+            hex::encode(self.webhook_mac(payload).finalize().into_bytes())
+        }
+
+        fn webhook_mac(&self, payload: &[u8]) -> Hmac<Sha256> {
+            let mut mac = Hmac::<Sha256>::new_from_slice(&self.webhook_secret)
+                .expect("HMAC accepts keys of any length");
+            mac.update(payload);
+            mac
+        }
+
+        fn signature_matches(&self, payload: &[u8], signature_header: Option<&str>) -> bool {
+            let Some(signature) = signature_header.and_then(|s| hex::decode(s).ok()) else {
+                return false;
+            };
+            self.webhook_mac(payload).verify_slice(&signature).is_ok()
+        }
+
+        /// Reset the injected-failure counter, so a provider reused across logical
+        /// requests fails again as if newly created.
+        pub async fn reset(&self) {
+            *self.state.lock().await = MockState::default();
+        }
+    }
+
+    fn now() -> Option<Timestamp> {
+        Some(Timestamp {
+            value: Some(std::time::SystemTime::now().into()),
+        })
     }
 
     #[async_trait]
@@ -123,6 +382,7 @@ mod mock {
                         return Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
                             message: msg.clone(),
+                            kind: ProviderErrorKind::Terminal,
                         });
                     }
                     _ => {}
@@ -130,56 +390,21 @@ mod mock {
             }
 
             match self.behavior {
-                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => Ok(Payment {
-                    id: Some(Id {
-                        value: cuid().to_string(),
-                    }),
-                    wallet_id: req.wallet_id,
-                    amount: req.amount,
-                    status: PaymentStatus::Success as i32,
-                    r#type: req.r#type,
-                    reference_id: req.reference_id,
-                    created_at: Some(Timestamp {
-                        seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                        nanos: 0,
-                    }),
-                    updated_at: Some(Timestamp {
-                        seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                        nanos: 0,
-                    }),
-                    ..Default::default()
-                }),
+                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => Ok(self.payment(req)),
                 MockBehavior::AlwaysFail(ref msg) => Err(Error::Provider {
                     code: "MOCK_ERROR".to_string(),
                     message: msg.clone(),
+                    kind: ProviderErrorKind::Terminal,
                 }),
-                MockBehavior::FailOnceThenSucceed => {
-                    if !state.fail_once_consumed {
-                        state.fail_once_consumed = true;
+                MockBehavior::FailOnceThenSucceed | MockBehavior::FailNTimesThenSucceed(_) => {
+                    if state.consume_failure(&self.behavior) {
                         Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
-                            message: "Mock failure (FailOnceThenSucceed)".to_string(),
+                            message: format!("Mock failure ({:?})", self.behavior),
+                            kind: ProviderErrorKind::Transient,
                         })
                     } else {
-                        Ok(Payment {
-                            id: Some(Id {
-                                value: cuid().to_string(),
-                            }),
-                            wallet_id: req.wallet_id,
-                            amount: req.amount,
-                            status: PaymentStatus::Success as i32,
-                            r#type: req.r#type,
-                            reference_id: req.reference_id,
-                            created_at: Some(Timestamp {
-                                seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                                nanos: 0,
-                            }),
-                            updated_at: Some(Timestamp {
-                                seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                                nanos: 0,
-                            }),
-                            ..Default::default()
-                        })
+                        Ok(self.payment(req))
                     }
                 }
             }
@@ -195,6 +420,7 @@ mod mock {
                         return Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
                             message: msg.clone(),
+                            kind: ProviderErrorKind::Terminal,
                         });
                     }
                     _ => {}
@@ -202,62 +428,27 @@ mod mock {
             }
 
             match self.behavior {
-                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => Ok(Payout {
-                    id: Some(Id {
-                        value: cuid().to_string(),
-                    }),
-                    wallet_id: req.wallet_id,
-                    amount: req.amount,
-                    status: PayoutStatus::Success as i32,
-                    r#type: req.r#type,
-                    reference_id: req.reference_id,
-                    created_at: Some(Timestamp {
-                        seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                        nanos: 0,
-                    }),
-                    updated_at: Some(Timestamp {
-                        seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                        nanos: 0,
-                    }),
-                    ..Default::default()
-                }),
+                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => Ok(self.payout(req)),
                 MockBehavior::AlwaysFail(ref msg) => Err(Error::Provider {
                     code: "MOCK_ERROR".to_string(),
                     message: msg.clone(),
+                    kind: ProviderErrorKind::Terminal,
                 }),
-                MockBehavior::FailOnceThenSucceed => {
-                    if !state.fail_once_consumed {
-                        state.fail_once_consumed = true;
+                MockBehavior::FailOnceThenSucceed | MockBehavior::FailNTimesThenSucceed(_) => {
+                    if state.consume_failure(&self.behavior) {
                         Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
-                            message: "Mock failure (FailOnceThenSucceed)".to_string(),
+                            message: format!("Mock failure ({:?})", self.behavior),
+                            kind: ProviderErrorKind::Transient,
                         })
                     } else {
-                        Ok(Payout {
-                            id: Some(Id {
-                                value: cuid().to_string(),
-                            }),
-                            wallet_id: req.wallet_id,
-                            amount: req.amount,
-                            status: PayoutStatus::Success as i32,
-                            r#type: req.r#type,
-                            reference_id: req.reference_id,
-                            created_at: Some(Timestamp {
-                                seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                                nanos: 0,
-                            }),
-                            updated_at: Some(Timestamp {
-                                seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                                nanos: 0,
-                            }),
-                            ..Default::default()
-                        })
+                        Ok(self.payout(req))
                     }
                 }
             }
         }
 
-        async fn refund(&self, _ctx: &Ctx, req: PostJournalRequest) -> Result<JournalEntry, Error> {
+        async fn refund(&self, _ctx: &Ctx, req: PostJournalRequest) -> Result<Refund, Error> {
             let mut state = self.state.lock().await;
 
             if let MockBehavior::Delay(duration, ref inner_behavior) = self.behavior {
@@ -267,6 +458,7 @@ mod mock {
                         return Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
                             message: msg.clone(),
+                            kind: ProviderErrorKind::Terminal,
                         });
                     }
                     _ => {}
@@ -274,54 +466,23 @@ mod mock {
             }
 
             match self.behavior {
-                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => Ok(JournalEntry {
-                    id: Some(Id {
-                        value: cuid().to_string(),
-                    }),
-                    account_id: req.account_id,
-                    amount: req.amount,
-                    entry_type: req.entry_type,
-                    reference_id: req.reference_id,
-                    created_at: Some(Timestamp {
-                        seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                        nanos: 0,
-                    }),
-                    updated_at: Some(Timestamp {
-                        seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                        nanos: 0,
-                    }),
-                    ..Default::default()
-                }),
+                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => {
+                    Ok(self.refund_result(req))
+                }
                 MockBehavior::AlwaysFail(ref msg) => Err(Error::Provider {
                     code: "MOCK_ERROR".to_string(),
                     message: msg.clone(),
+                    kind: ProviderErrorKind::Terminal,
                 }),
-                MockBehavior::FailOnceThenSucceed => {
-                    if !state.fail_once_consumed {
-                        state.fail_once_consumed = true;
+                MockBehavior::FailOnceThenSucceed | MockBehavior::FailNTimesThenSucceed(_) => {
+                    if state.consume_failure(&self.behavior) {
                         Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
-                            message: "Mock failure (FailOnceThenSucceed)".to_string(),
+                            message: format!("Mock failure ({:?})", self.behavior),
+                            kind: ProviderErrorKind::Transient,
                         })
                     } else {
-                        Ok(JournalEntry {
-                            id: Some(Id {
-                                value: cuid().to_string(),
-                            }),
-                            account_id: req.account_id,
-                            amount: req.amount,
-                            entry_type: req.entry_type,
-                            reference_id: req.reference_id,
-                            created_at: Some(Timestamp {
-                                seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                                nanos: 0,
-                            }),
-                            updated_at: Some(Timestamp {
-                                seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
-                                nanos: 0,
-                            }),
-                            ..Default::default()
-                        })
+                        Ok(self.refund_result(req))
                     }
                 }
             }
@@ -337,6 +498,7 @@ mod mock {
                         return Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
                             message: msg.clone(),
+                            kind: ProviderErrorKind::Terminal,
                         });
                     }
                     _ => {}
@@ -363,13 +525,14 @@ mod mock {
                 MockBehavior::AlwaysFail(ref msg) => Err(Error::Provider {
                     code: "MOCK_ERROR".to_string(),
                     message: msg.clone(),
+                    kind: ProviderErrorKind::Terminal,
                 }),
-                MockBehavior::FailOnceThenSucceed => {
-                    if !state.fail_once_consumed {
-                        state.fail_once_consumed = true;
+                MockBehavior::FailOnceThenSucceed | MockBehavior::FailNTimesThenSucceed(_) => {
+                    if state.consume_failure(&self.behavior) {
                         Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
-                            message: "Mock failure (FailOnceThenSucceed)".to_string(),
+                            message: format!("Mock failure ({:?})", self.behavior),
+                            kind: ProviderErrorKind::Transient,
                         })
                     } else {
                         Ok(Balance {
@@ -393,11 +556,120 @@ mod mock {
             }
         }
 
+        async fn get_transaction(
+            &self,
+            _ctx: &Ctx,
+            reference: &str,
+        ) -> Result<TransactionStatus, Error> {
+            let mut state = self.state.lock().await;
+
+            if let MockBehavior::Delay(duration, ref inner_behavior) = self.behavior {
+                tokio::time::sleep(duration).await;
+                match **inner_behavior {
+                    MockBehavior::AlwaysFail(ref msg) => {
+                        return Err(Error::Provider {
+                            code: "MOCK_ERROR".to_string(),
+                            message: msg.clone(),
+                            kind: ProviderErrorKind::Terminal,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+
+            let cancelled = state.cancelled.contains(reference);
+            let state_reached = if cancelled {
+                TransactionState::Failed
+            } else {
+                self.result_state.unwrap_or(TransactionState::Successful)
+            };
+            let status = TransactionStatus {
+                reference: reference.to_string(),
+                state: state_reached,
+                provider_transaction_id: match &self.reference {
+                    Some(provider_reference) => Some(provider_reference(reference)),
+                    None => Some(cuid().to_string()),
+                },
+                reason: cancelled.then(|| "cancelled".to_string()),
+                metadata: HashMap::new(),
+            };
+
+            match self.behavior {
+                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => Ok(status),
+                MockBehavior::AlwaysFail(ref msg) => Err(Error::Provider {
+                    code: "MOCK_ERROR".to_string(),
+                    message: msg.clone(),
+                    kind: ProviderErrorKind::Terminal,
+                }),
+                MockBehavior::FailOnceThenSucceed | MockBehavior::FailNTimesThenSucceed(_) => {
+                    if state.consume_failure(&self.behavior) {
+                        Err(Error::Provider {
+                            code: "MOCK_ERROR".to_string(),
+                            message: format!("Mock failure ({:?})", self.behavior),
+                            kind: ProviderErrorKind::Transient,
+                        })
+                    } else {
+                        Ok(status)
+                    }
+                }
+            }
+        }
+
+        /// Cancels `reference` if calls are configured to stay pending (see
+        /// [`MockProvider::with_status`]); `get_transaction` then reports it as failed.
+        /// Anything else has already settled, so `Error::Conflict` is returned.
+        async fn cancel(&self, _ctx: &Ctx, reference: &str) -> Result<(), Error> {
+            let mut state = self.state.lock().await;
+
+            if let MockBehavior::Delay(duration, ref inner_behavior) = self.behavior {
+                tokio::time::sleep(duration).await;
+                match **inner_behavior {
+                    MockBehavior::AlwaysFail(ref msg) => {
+                        return Err(Error::Provider {
+                            code: "MOCK_ERROR".to_string(),
+                            message: msg.clone(),
+                            kind: ProviderErrorKind::Terminal,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+
+            match self.behavior {
+                MockBehavior::AlwaysFail(ref msg) => {
+                    return Err(Error::Provider {
+                        code: "MOCK_ERROR".to_string(),
+                        message: msg.clone(),
+                        kind: ProviderErrorKind::Terminal,
+                    });
+                }
+                MockBehavior::FailOnceThenSucceed | MockBehavior::FailNTimesThenSucceed(_) => {
+                    if state.consume_failure(&self.behavior) {
+                        return Err(Error::Provider {
+                            code: "MOCK_ERROR".to_string(),
+                            message: format!("Mock failure ({:?})", self.behavior),
+                            kind: ProviderErrorKind::Transient,
+                        });
+                    }
+                }
+                MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => {}
+            }
+
+            if self.result_state != Some(TransactionState::Pending) {
+                return Err(Error::Conflict(format!(
+                    "transaction {reference} is no longer pending"
+                )));
+            }
+            state.cancelled.insert(reference.to_string());
+            Ok(())
+        }
+
         async fn verify_webhook(
             &self,
             _ctx: &Ctx,
             payload: &[u8],
-            _signature_header: Option<&str>,
+            signature_header: Option<&str>,
+            _timestamp_header: Option<&str>,
         ) -> Result<bool, Error> {
             let mut state = self.state.lock().await;
 
@@ -408,6 +680,7 @@ mod mock {
                         return Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
                             message: msg.clone(),
+                            kind: ProviderErrorKind::Terminal,
                         });
                     }
                     _ => {}
@@ -416,25 +689,374 @@ mod mock {
 
             match self.behavior {
                 MockBehavior::AlwaysSucceed | MockBehavior::Delay(_, _) => {
-                    // Simple mock logic: if payload contains "valid", return true
-                    Ok(String::from_utf8_lossy(payload).contains("valid"))
+                    Ok(self.signature_matches(payload, signature_header))
                 }
                 MockBehavior::AlwaysFail(ref msg) => Err(Error::Provider {
                     code: "MOCK_ERROR".to_string(),
                     message: msg.clone(),
+                    kind: ProviderErrorKind::Terminal,
                 }),
-                MockBehavior::FailOnceThenSucceed => {
-                    if !state.fail_once_consumed {
-                        state.fail_once_consumed = true;
+                MockBehavior::FailOnceThenSucceed | MockBehavior::FailNTimesThenSucceed(_) => {
+                    if state.consume_failure(&self.behavior) {
                         Err(Error::Provider {
                             code: "MOCK_ERROR".to_string(),
-                            message: "Mock failure (FailOnceThenSucceed)".to_string(),
+                            message: format!("Mock failure ({:?})", self.behavior),
+                            kind: ProviderErrorKind::Transient,
                         })
                     } else {
-                        Ok(String::from_utf8_lossy(payload).contains("valid"))
+                        Ok(self.signature_matches(payload, signature_header))
                     }
                 }
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_get_transaction_succeeds() {
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed);
+
+            let status = provider
+                .get_transaction(&Ctx::default(), "ref-123")
+                .await
+                .unwrap();
+
+            assert_eq!(status.reference, "ref-123");
+            assert_eq!(status.state, TransactionState::Successful);
+            assert!(status.provider_transaction_id.is_some());
+            assert_eq!(status.reason, None);
+        }
+
+        #[tokio::test]
+        async fn test_with_reference_sets_provider_references() {
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed)
+                .with_reference(|key| format!("mtn-{key}"));
+
+            let payment = provider
+                .deposit(
+                    &Ctx::default(),
+                    CreatePaymentRequest {
+                        idempotency_key: "pay-1".to_string(),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(payment.reference, "mtn-pay-1");
+
+            let payout = provider
+                .withdraw(
+                    &Ctx::default(),
+                    CreatePayoutRequest {
+                        idempotency_key: "payout-1".to_string(),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(payout.external_reference, "mtn-payout-1");
+
+            let refund = provider
+                .refund(
+                    &Ctx::default(),
+                    PostJournalRequest {
+                        idempotency_key: "refund-1".to_string(),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(refund.provider_reference, "mtn-refund-1");
+
+            let status = provider
+                .get_transaction(&Ctx::default(), "pay-1")
+                .await
+                .unwrap();
+            assert_eq!(status.provider_transaction_id.as_deref(), Some("mtn-pay-1"));
+        }
+
+        #[tokio::test]
+        async fn test_with_status_models_pending_flow() {
+            let default = MockProvider::new(MockBehavior::AlwaysSucceed);
+            let payment = default
+                .deposit(&Ctx::default(), CreatePaymentRequest::default())
+                .await
+                .unwrap();
+            assert_eq!(payment.status(), PaymentStatus::Completed);
+
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed)
+                .with_status(TransactionState::Pending);
+
+            let payment = provider
+                .deposit(&Ctx::default(), CreatePaymentRequest::default())
+                .await
+                .unwrap();
+            assert_eq!(payment.status(), PaymentStatus::Pending);
+            let payout = provider
+                .withdraw(&Ctx::default(), CreatePayoutRequest::default())
+                .await
+                .unwrap();
+            assert_eq!(payout.status(), PayoutStatus::Pending);
+            let status = provider
+                .get_transaction(&Ctx::default(), "ref-123")
+                .await
+                .unwrap();
+            assert_eq!(status.state, TransactionState::Pending);
+
+            let refund = MockProvider::new(MockBehavior::AlwaysSucceed)
+                .with_status(TransactionState::Successful)
+                .refund(&Ctx::default(), PostJournalRequest::default())
+                .await
+                .unwrap();
+            assert_eq!(refund.status(), RefundStatus::Succeeded);
+        }
+
+        #[tokio::test]
+        async fn test_cancel_pending_transaction() {
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed)
+                .with_status(TransactionState::Pending);
+
+            provider.cancel(&Ctx::default(), "ref-123").await.unwrap();
+
+            let status = provider
+                .get_transaction(&Ctx::default(), "ref-123")
+                .await
+                .unwrap();
+            assert_eq!(status.state, TransactionState::Failed);
+            assert_eq!(status.reason.as_deref(), Some("cancelled"));
+            let other = provider
+                .get_transaction(&Ctx::default(), "ref-456")
+                .await
+                .unwrap();
+            assert_eq!(other.state, TransactionState::Pending);
+        }
+
+        #[tokio::test]
+        async fn test_cancel_settled_transaction_conflicts() {
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed);
+
+            let err = provider
+                .cancel(&Ctx::default(), "ref-123")
+                .await
+                .unwrap_err();
+
+            assert!(matches!(err, Error::Conflict(_)));
+        }
+
+        #[tokio::test]
+        async fn test_get_transaction_always_fail() {
+            let provider = MockProvider::new(MockBehavior::AlwaysFail("down".to_string()));
+
+            let err = provider
+                .get_transaction(&Ctx::default(), "ref-123")
+                .await
+                .unwrap_err();
+
+            assert!(matches!(err, Error::Provider { ref message, .. } if message == "down"));
+        }
+
+        #[tokio::test]
+        async fn test_get_transaction_fail_once_then_succeed() {
+            let provider = MockProvider::new(MockBehavior::FailOnceThenSucceed);
+
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-123")
+                    .await
+                    .is_err()
+            );
+            let status = provider
+                .get_transaction(&Ctx::default(), "ref-123")
+                .await
+                .unwrap();
+            assert_eq!(status.state, TransactionState::Successful);
+        }
+
+        #[tokio::test]
+        async fn test_withdraw_batch_reports_each_payout() {
+            let provider = MockProvider::new(MockBehavior::FailNTimesThenSucceed(2));
+            let reqs = (0..5)
+                .map(|i| CreatePayoutRequest {
+                    idempotency_key: format!("payout-{i}"),
+                    ..Default::default()
+                })
+                .collect();
+
+            let results = provider.withdraw_batch(&Ctx::default(), reqs).await;
+
+            assert_eq!(results.len(), 5);
+            assert_eq!(results.iter().filter(|r| r.is_err()).count(), 2);
+            assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 3);
+        }
+
+        #[tokio::test]
+        async fn test_fail_n_times_then_succeed() {
+            let provider = MockProvider::new(MockBehavior::FailNTimesThenSucceed(2));
+
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_ok()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_ok()
+            );
+        }
+
+        #[tokio::test]
+        async fn test_reset_restarts_failure_count() {
+            let provider = MockProvider::new(MockBehavior::FailNTimesThenSucceed(2));
+
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_ok()
+            );
+
+            provider.reset().await;
+
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-2")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-2")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-2")
+                    .await
+                    .is_ok()
+            );
+        }
+
+        #[tokio::test]
+        async fn test_reset_rearms_fail_once() {
+            let provider = MockProvider::new(MockBehavior::FailOnceThenSucceed);
+
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-1")
+                    .await
+                    .is_ok()
+            );
+
+            provider.reset().await;
+
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-2")
+                    .await
+                    .is_err()
+            );
+            assert!(
+                provider
+                    .get_transaction(&Ctx::default(), "ref-2")
+                    .await
+                    .is_ok()
+            );
+        }
+
+        #[tokio::test]
+        async fn test_signed_webhook_verifies() {
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed);
+            let payload = br#"{"externalId":"ref-123","status":"SUCCESSFUL"}"#;
+
+            let signature = provider.sign_webhook(payload);
+
+            assert!(
+                provider
+                    .verify_webhook(&Ctx::default(), payload, Some(&signature), None)
+                    .await
+                    .unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn test_tampered_webhook_fails_verification() {
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed);
+            let payload = br#"{"externalId":"ref-123","status":"SUCCESSFUL"}"#;
+            let tampered = br#"{"externalId":"ref-123","status":"FAILED"}"#;
+
+            let signature = provider.sign_webhook(payload);
+
+            assert!(
+                !provider
+                    .verify_webhook(&Ctx::default(), tampered, Some(&signature), None)
+                    .await
+                    .unwrap()
+            );
+            assert!(
+                !provider
+                    .verify_webhook(&Ctx::default(), payload, Some("not-hex"), None)
+                    .await
+                    .unwrap()
+            );
+            assert!(
+                !provider
+                    .verify_webhook(&Ctx::default(), payload, None, None)
+                    .await
+                    .unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn test_webhook_signed_with_other_secret_fails_verification() {
+            let provider = MockProvider::new(MockBehavior::AlwaysSucceed);
+            let other = MockProvider::new(MockBehavior::AlwaysSucceed).with_webhook_secret("other");
+            let payload = br#"{"externalId":"ref-123","status":"SUCCESSFUL"}"#;
+
+            let signature = other.sign_webhook(payload);
+
+            assert!(
+                !provider
+                    .verify_webhook(&Ctx::default(), payload, Some(&signature), None)
+                    .await
+                    .unwrap()
+            );
+        }
+    }
 }
@@ -0,0 +1,365 @@
+//! Routing operations across several providers by weight, with failover.
+
+use std::sync::Arc;
+
+use psc_error::{Error, ProviderErrorKind};
+use psc_retry::CircuitBreaker;
+use rand::Rng;
+
+use crate::pb::payment::v1::{CreatePaymentRequest, Payment};
+use crate::{Ctx, Provider};
+
+struct Route {
+    provider: Arc<dyn Provider>,
+    weight: u32,
+    circuit_breaker: CircuitBreaker,
+}
+
+/// Spreads operations across providers able to serve them, such as two MTN accounts
+/// or MTN and Orange.
+///
+/// Each call tries providers in a random order biased by weight, skipping those
+/// whose circuit breaker is open, and fails over to the next provider only when one
+/// rejects the request in a way that proves it was not accepted, see
+/// [`rejected_without_side_effects`]. Providers with a weight of zero are only used
+/// as fallbacks.
+/// Only retryable errors count towards opening a provider's circuit, so a payer's
+/// insufficient funds does not take a healthy provider out of rotation.
+#[derive(Default)]
+pub struct ProviderRouter {
+    routes: Vec<Route>,
+}
+
+impl ProviderRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a provider receiving traffic in proportion to `weight`, guarded by
+    /// `circuit_breaker`.
+    pub fn with_provider(
+        mut self,
+        provider: Arc<dyn Provider>,
+        weight: u32,
+        circuit_breaker: CircuitBreaker,
+    ) -> Self {
+        self.routes.push(Route {
+            provider,
+            weight,
+            circuit_breaker,
+        });
+        self
+    }
+
+    /// Create a payment with the first provider that accepts it.
+    ///
+    /// Returns the last provider's error if every eligible provider rejects the
+    /// payment, and a transient `NO_PROVIDER_AVAILABLE` provider error if none is
+    /// eligible. Any other error is returned straight away: a terminal error such
+    /// as insufficient funds would fail elsewhere too, and after a timeout or an
+    /// internal error the payment may have gone through, so the caller should check
+    /// with `get_transaction` rather than charge the payer again elsewhere.
+    pub async fn route_deposit(
+        &self,
+        ctx: &Ctx,
+        req: CreatePaymentRequest,
+    ) -> Result<Payment, Error> {
+        let mut last_error = None;
+        for index in self.order() {
+            let route = &self.routes[index];
+            if !route.circuit_breaker.can_execute().await {
+                continue;
+            }
+            match route.provider.deposit(ctx, req.clone()).await {
+                Ok(payment) => {
+                    route.circuit_breaker.record_success().await;
+                    return Ok(payment);
+                }
+                Err(e) => {
+                    // A non-retryable error is still an answer from a working provider
+                    if e.is_retryable() {
+                        route.circuit_breaker.record_failure().await;
+                    } else {
+                        route.circuit_breaker.record_success().await;
+                    }
+                    if !rejected_without_side_effects(&e) {
+                        return Err(e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::Provider {
+            code: "NO_PROVIDER_AVAILABLE".to_string(),
+            message: "Every provider's circuit breaker is open".to_string(),
+            kind: ProviderErrorKind::Transient,
+        }))
+    }
+
+    /// Indices of the routes in the order to try them: a weighted shuffle of the
+    /// weighted routes, followed by the zero-weight fallbacks.
+    fn order(&self) -> Vec<usize> {
+        let mut rng = rand::thread_rng();
+        let mut weighted: Vec<usize> = (0..self.routes.len())
+            .filter(|&i| self.routes[i].weight > 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.routes.len());
+        while !weighted.is_empty() {
+            let total: u64 = weighted
+                .iter()
+                .map(|&i| u64::from(self.routes[i].weight))
+                .sum();
+            let mut pick = rng.gen_range(0..total);
+            let position = weighted
+                .iter()
+                .position(|&i| {
+                    let weight = u64::from(self.routes[i].weight);
+                    if pick < weight {
+                        true
+                    } else {
+                        pick -= weight;
+                        false
+                    }
+                })
+                .unwrap_or(weighted.len() - 1);
+            order.push(weighted.remove(position));
+        }
+        order.extend((0..self.routes.len()).filter(|&i| self.routes[i].weight == 0));
+        order
+    }
+}
+
+/// Whether `error` shows the provider turned the request down before acting on it,
+/// so another provider can be tried: it is throttling requests, or it reported a
+/// transient failure with a code of its own.
+fn rejected_without_side_effects(error: &Error) -> bool {
+    match error {
+        Error::RateLimited { .. } => true,
+        Error::Provider { kind, .. } => *kind != ProviderErrorKind::Terminal,
+        _ => false,
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{
+        Balance, CreatePayoutRequest, GetBalanceRequest, MockBehavior, MockProvider, Payout,
+        PostJournalRequest, Refund, TransactionStatus,
+    };
+    use async_trait::async_trait;
+    use psc_retry::{CircuitBreakerConfig, CircuitState, MockClock};
+    use std::time::Duration;
+
+    /// A provider whose deposits fail with the error from the function; everything
+    /// else goes to a mock.
+    struct FailingProvider(fn() -> Error, MockProvider);
+
+    #[async_trait]
+    impl Provider for FailingProvider {
+        async fn deposit(&self, _ctx: &Ctx, _req: CreatePaymentRequest) -> Result<Payment, Error> {
+            Err((self.0)())
+        }
+
+        async fn withdraw(&self, ctx: &Ctx, req: CreatePayoutRequest) -> Result<Payout, Error> {
+            self.1.withdraw(ctx, req).await
+        }
+
+        async fn refund(&self, ctx: &Ctx, req: PostJournalRequest) -> Result<Refund, Error> {
+            self.1.refund(ctx, req).await
+        }
+
+        async fn query(&self, ctx: &Ctx, req: GetBalanceRequest) -> Result<Balance, Error> {
+            self.1.query(ctx, req).await
+        }
+
+        async fn get_transaction(
+            &self,
+            ctx: &Ctx,
+            reference: &str,
+        ) -> Result<TransactionStatus, Error> {
+            self.1.get_transaction(ctx, reference).await
+        }
+
+        async fn verify_webhook(
+            &self,
+            ctx: &Ctx,
+            payload: &[u8],
+            signature_header: Option<&str>,
+            timestamp_header: Option<&str>,
+        ) -> Result<bool, Error> {
+            self.1
+                .verify_webhook(ctx, payload, signature_header, timestamp_header)
+                .await
+        }
+    }
+
+    fn failing(error: fn() -> Error) -> Arc<dyn Provider> {
+        Arc::new(FailingProvider(
+            error,
+            MockProvider::new(MockBehavior::AlwaysSucceed),
+        ))
+    }
+
+    fn provider_error(code: &str, kind: ProviderErrorKind) -> Error {
+        Error::Provider {
+            code: code.to_string(),
+            message: code.to_string(),
+            kind,
+        }
+    }
+
+    fn circuit_breaker(failure_threshold: usize) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold,
+            timeout: Duration::from_secs(60),
+            success_threshold: 1,
+            ..Default::default()
+        })
+    }
+
+    /// A router trying the provider failing with `error` first, then one that succeeds.
+    fn router_failing_with(error: fn() -> Error) -> ProviderRouter {
+        ProviderRouter::new()
+            .with_provider(failing(error), 1, circuit_breaker(5))
+            .with_provider(
+                Arc::new(MockProvider::new(MockBehavior::AlwaysSucceed)),
+                0,
+                circuit_breaker(5),
+            )
+    }
+
+    #[tokio::test]
+    async fn test_route_deposit_fails_over_to_next_provider() {
+        let routers = [
+            router_failing_with(|| {
+                provider_error("SERVICE_UNAVAILABLE", ProviderErrorKind::Transient)
+            }),
+            router_failing_with(|| Error::RateLimited { retry_after: None }),
+        ];
+
+        for router in routers {
+            for _ in 0..3 {
+                let payment = router
+                    .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+                    .await;
+                assert!(payment.is_ok());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_deposit_returns_last_error_when_all_fail() {
+        let router = ProviderRouter::new()
+            .with_provider(
+                failing(|| provider_error("FIRST", ProviderErrorKind::Transient)),
+                1,
+                circuit_breaker(5),
+            )
+            .with_provider(
+                failing(|| provider_error("SECOND", ProviderErrorKind::Transient)),
+                0,
+                circuit_breaker(5),
+            );
+
+        let err = router
+            .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.provider_code(), Some("SECOND"));
+    }
+
+    #[tokio::test]
+    async fn test_route_deposit_does_not_fail_over_after_timeout() {
+        let router = router_failing_with(|| Error::Timeout("deposit".to_string()));
+
+        let err = router
+            .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_route_deposit_does_not_fail_over_after_terminal_error() {
+        let router =
+            router_failing_with(|| provider_error("NOT_ENOUGH_FUNDS", ProviderErrorKind::Terminal));
+
+        let err = router
+            .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.provider_code(), Some("NOT_ENOUGH_FUNDS"));
+    }
+
+    #[tokio::test]
+    async fn test_route_deposit_does_not_fail_over_after_internal_error() {
+        let router = router_failing_with(|| Error::Internal("connection reset".to_string()));
+
+        let err = router
+            .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Internal(_)));
+    }
+
+    #[tokio::test]
+    async fn test_route_deposit_skips_open_circuits() {
+        let router = ProviderRouter::new().with_provider(
+            Arc::new(MockProvider::new(MockBehavior::FailOnceThenSucceed)),
+            1,
+            circuit_breaker(1),
+        );
+
+        // The transient failure opens the circuit...
+        assert!(
+            router
+                .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+                .await
+                .is_err()
+        );
+        // ...so the provider is not called again, although it would now succeed
+        let err = router
+            .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.provider_code(), Some("NO_PROVIDER_AVAILABLE"));
+    }
+
+    #[tokio::test]
+    async fn test_terminal_error_closes_half_open_circuit() {
+        let clock = MockClock::new();
+        let breaker = circuit_breaker(1).with_clock(clock.clone());
+        breaker.record_failure().await;
+        clock.advance(Duration::from_secs(60));
+        assert!(breaker.can_execute().await);
+        let router = ProviderRouter::new().with_provider(
+            failing(|| provider_error("NOT_ENOUGH_FUNDS", ProviderErrorKind::Terminal)),
+            1,
+            breaker.clone(),
+        );
+
+        let err = router
+            .route_deposit(&Ctx::default(), CreatePaymentRequest::default())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.provider_code(), Some("NOT_ENOUGH_FUNDS"));
+        assert_eq!(*breaker.state.read().await, CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_zero_weight_providers_are_tried_last() {
+        let provider = || Arc::new(MockProvider::new(MockBehavior::AlwaysSucceed));
+        let router = ProviderRouter::new()
+            .with_provider(provider(), 0, circuit_breaker(5))
+            .with_provider(provider(), 3, circuit_breaker(5))
+            .with_provider(provider(), 1, circuit_breaker(5));
+
+        for _ in 0..20 {
+            let order = router.order();
+            assert_eq!(order.len(), 3);
+            assert_eq!(order[2], 0);
+        }
+    }
+}
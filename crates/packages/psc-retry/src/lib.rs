@@ -8,9 +8,21 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tokio::time::{Instant, sleep};
+pub use tokio_util::sync::CancellationToken;
 use tracing::{debug, warn};
 
+/// Counter of retries, labeled by `operation`, recorded with the `metrics` feature
+pub const RETRY_ATTEMPTS_METRIC: &str = "psc_retry_attempts_total";
+
+/// Counter of transitions to [`CircuitState::Open`], labeled by `operation`, recorded
+/// with the `metrics` feature
+pub const CIRCUIT_BREAKER_TRIPS_METRIC: &str = "psc_circuit_breaker_trips_total";
+
+/// Operation name used in metric labels when none is configured
+pub const DEFAULT_OPERATION: &str = "default";
+
 /// Errors that can occur during retry operations
 #[derive(Error, Debug, PartialEq)]
 pub enum RetryError<E> {
@@ -21,6 +33,25 @@ pub enum RetryError<E> {
     /// The circuit breaker is open, preventing further attempts
     #[error("Circuit breaker is open")]
     CircuitBreakerOpen,
+
+    /// The circuit breaker opened after a failed attempt, stopping the retries; holds
+    /// that attempt's error. See [`RetryPolicy::stop_on_open`]
+    #[error("Circuit breaker opened after: {0}")]
+    CircuitOpenedDuringRetry(E),
+
+    /// The operation failed with an error the caller classified as not worth retrying
+    #[error("Non-retryable error: {0}")]
+    NonRetryable(E),
+
+    /// The bulkhead had no free permit and its queue was full, so the operation was
+    /// not attempted. See [`do_with_retry_in_bulkhead`]
+    #[error("Bulkhead is full")]
+    BulkheadFull,
+
+    /// The cancellation token fired while backing off; holds the last attempt's error.
+    /// See [`do_with_retry_cancellable`]
+    #[error("Retry cancelled after: {0}")]
+    Cancelled(E),
 }
 
 /// Configuration for retry behavior
@@ -33,7 +64,19 @@ pub struct RetryPolicy {
     /// Maximum backoff duration
     pub max_backoff: Duration,
     /// Whether to use jitter in backoff calculations
+    ///
+    /// Jitter never pushes a backoff past `max_backoff`.
     pub jitter: bool,
+    /// Name of the retried operation, used to label metrics
+    pub operation: String,
+    /// Whether to stop retrying as soon as a failed attempt opens the circuit breaker
+    ///
+    /// When `true` the failing attempt's error is returned as
+    /// `RetryError::CircuitOpenedDuringRetry`. When `false` the breaker is only
+    /// consulted before the first attempt, so an operation that got in finishes its
+    /// retries (still recording each failure) and returns
+    /// `RetryError::AttemptsExhausted` if none succeeds.
+    pub stop_on_open: bool,
 }
 
 impl Default for RetryPolicy {
@@ -43,6 +86,8 @@ impl Default for RetryPolicy {
             initial_backoff: Duration::from_millis(100),
             max_backoff: Duration::from_secs(10),
             jitter: true,
+            operation: DEFAULT_OPERATION.to_string(),
+            stop_on_open: true,
         }
     }
 }
@@ -53,6 +98,52 @@ impl RetryPolicy {
         Self::default()
     }
 
+    /// Many quick retries, for cheap calls to nearby services such as Redis or another
+    /// internal service, where a blip usually clears within milliseconds and the
+    /// caller is waiting on the result
+    ///
+    /// 5 retries backing off from 10ms up to 500ms, with jitter.
+    pub fn aggressive() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(500),
+            jitter: true,
+            ..Self::default()
+        }
+    }
+
+    /// Few, widely spaced retries, for a dependency that is likely overloaded or rate
+    /// limiting, or background work where giving it time to recover matters more
+    /// than latency
+    ///
+    /// 2 retries backing off from 1s up to 30s, with jitter.
+    pub fn conservative() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            ..Self::default()
+        }
+    }
+
+    /// Retries for HTTP calls that are safe to repeat, such as GETs or provider
+    /// requests carrying an idempotency key. Do not use it for requests that could
+    /// be applied twice
+    ///
+    /// 4 retries backing off from 200ms up to 5s, with jitter so that clients failing
+    /// together do not retry in lockstep.
+    pub fn for_idempotent_http() -> Self {
+        Self {
+            max_retries: 4,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            jitter: true,
+            ..Self::default()
+        }
+    }
+
     /// Set the maximum number of retry attempts
     pub fn with_max_retries(mut self, max_retries: usize) -> Self {
         self.max_retries = max_retries;
@@ -77,8 +168,22 @@ impl RetryPolicy {
         self
     }
 
+    /// Set the operation name used to label metrics
+    pub fn with_operation(mut self, operation: impl Into<String>) -> Self {
+        self.operation = operation.into();
+        self
+    }
+
+    /// Set whether to stop retrying once a failed attempt opens the circuit breaker
+    pub fn with_stop_on_open(mut self, stop_on_open: bool) -> Self {
+        self.stop_on_open = stop_on_open;
+        self
+    }
+
     /// Calculate the backoff duration for a given attempt
-    fn calculate_backoff(&self, attempt: usize) -> Duration {
+    ///
+    /// The result never exceeds `max_backoff`, jitter included.
+    pub fn calculate_backoff(&self, attempt: usize) -> Duration {
         // Exponential backoff: initial_backoff * 2^attempt
         let exponential_backoff = self.initial_backoff.mul_f64(2f64.powi(attempt as i32));
 
@@ -87,10 +192,16 @@ impl RetryPolicy {
 
         // Add jitter if enabled
         if self.jitter {
-            // Add random jitter of up to 25% of the backoff time
+            // Random jitter of up to 25% of the backoff time, added below the cap and
+            // subtracted where adding it would overshoot max_backoff
             let jitter_amount = backoff.mul_f32(0.25);
-            let jitter = rand::random::<u64>() % (jitter_amount.as_millis() as u64 + 1);
-            backoff + Duration::from_millis(jitter)
+            let jitter = Duration::from_millis(
+                rand::random::<u64>() % (jitter_amount.as_millis() as u64 + 1),
+            );
+            match backoff.checked_add(jitter) {
+                Some(total) if total <= self.max_backoff => total,
+                _ => backoff.saturating_sub(jitter),
+            }
         } else {
             backoff
         }
@@ -108,6 +219,18 @@ pub enum CircuitState {
     HalfOpen,
 }
 
+/// How failures are counted towards `failure_threshold` while the circuit is closed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FailureCounting {
+    /// Any success resets the count, so only an unbroken run of failures opens the circuit
+    #[default]
+    Consecutive,
+    /// Failures are counted over a window of the given length starting at the first
+    /// failure. Successes do not reset the count; it decays once the window has passed,
+    /// so a service failing intermittently still opens the circuit
+    Windowed(Duration),
+}
+
 /// Circuit breaker configuration
 #[derive(Debug, Clone)]
 pub struct CircuitBreakerConfig {
@@ -117,6 +240,10 @@ pub struct CircuitBreakerConfig {
     pub timeout: Duration,
     /// Number of successful requests needed to close the circuit in half-open state
     pub success_threshold: usize,
+    /// How failures are counted while the circuit is closed
+    pub failure_counting: FailureCounting,
+    /// Name of the protected operation, used to label metrics
+    pub operation: String,
 }
 
 impl Default for CircuitBreakerConfig {
@@ -125,18 +252,71 @@ impl Default for CircuitBreakerConfig {
             failure_threshold: 5,
             timeout: Duration::from_secs(60),
             success_threshold: 3,
+            failure_counting: FailureCounting::default(),
+            operation: DEFAULT_OPERATION.to_string(),
         }
     }
 }
 
+/// Source of the current time for a [`CircuitBreaker`]
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    /// The current instant
+    fn now(&self) -> Instant;
+}
+
+/// Clock reading the real time, used by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when advanced, so tests can step past a breaker's timeout
+/// without sleeping. Clones share the same time
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<std::sync::Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Create a clock stopped at the current instant
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(std::sync::Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().expect("mock clock lock poisoned") += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().expect("mock clock lock poisoned")
+    }
+}
+
 /// Circuit breaker implementation
 #[derive(Debug, Clone)]
 pub struct CircuitBreaker {
     config: CircuitBreakerConfig,
+    clock: Arc<dyn Clock>,
     pub state: Arc<tokio::sync::RwLock<CircuitState>>,
     failure_count: Arc<AtomicUsize>,
     success_count: Arc<AtomicUsize>,
     last_failure_time: Arc<tokio::sync::RwLock<Option<Instant>>>,
+    failure_window_start: Arc<tokio::sync::RwLock<Option<Instant>>>,
 }
 
 impl CircuitBreaker {
@@ -144,10 +324,12 @@ impl CircuitBreaker {
     pub fn new(config: CircuitBreakerConfig) -> Self {
         Self {
             config,
+            clock: Arc::new(SystemClock),
             state: Arc::new(tokio::sync::RwLock::new(CircuitState::Closed)),
             failure_count: Arc::new(AtomicUsize::new(0)),
             success_count: Arc::new(AtomicUsize::new(0)),
             last_failure_time: Arc::new(tokio::sync::RwLock::new(None)),
+            failure_window_start: Arc::new(tokio::sync::RwLock::new(None)),
         }
     }
 
@@ -156,6 +338,13 @@ impl CircuitBreaker {
         Self::new(CircuitBreakerConfig::default())
     }
 
+    /// Replace the clock used to time the open state and failure windows, e.g. with
+    /// a [`MockClock`] in tests
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
     /// Check if the circuit breaker allows requests
     pub async fn can_execute(&self) -> bool {
         let state = *self.state.read().await;
@@ -167,7 +356,7 @@ impl CircuitBreaker {
                 // Check if timeout has elapsed
                 let last_failure = self.last_failure_time.read().await;
                 if let Some(last_failure_time) = *last_failure {
-                    if last_failure_time.elapsed() >= self.config.timeout {
+                    if self.clock.now().duration_since(last_failure_time) >= self.config.timeout {
                         // Move to half-open state
                         *self.state.write().await = CircuitState::HalfOpen;
                         self.success_count.store(0, Ordering::Relaxed);
@@ -182,9 +371,22 @@ impl CircuitBreaker {
         }
     }
 
+    /// Forget the failures counted in a window that started more than `window` ago
+    async fn expire_failure_window(&self, window: Duration) {
+        let mut window_start = self.failure_window_start.write().await;
+        let now = self.clock.now();
+        if window_start.is_some_and(|start| now.duration_since(start) >= window) {
+            self.failure_count.store(0, Ordering::Relaxed);
+            *window_start = None;
+        }
+    }
+
     /// Record a successful request
     pub async fn record_success(&self) {
-        self.failure_count.store(0, Ordering::Relaxed);
+        match self.config.failure_counting {
+            FailureCounting::Consecutive => self.failure_count.store(0, Ordering::Relaxed),
+            FailureCounting::Windowed(window) => self.expire_failure_window(window).await,
+        }
 
         let state = *self.state.read().await;
         match state {
@@ -198,6 +400,8 @@ impl CircuitBreaker {
                     // Close the circuit
                     *self.state.write().await = CircuitState::Closed;
                     self.success_count.store(0, Ordering::Relaxed);
+                    self.failure_count.store(0, Ordering::Relaxed);
+                    *self.failure_window_start.write().await = None;
                     debug!("Circuit breaker closed after successful requests");
                 }
             }
@@ -210,6 +414,13 @@ impl CircuitBreaker {
 
     /// Record a failed request
     pub async fn record_failure(&self) {
+        if let FailureCounting::Windowed(window) = self.config.failure_counting {
+            self.expire_failure_window(window).await;
+            self.failure_window_start
+                .write()
+                .await
+                .get_or_insert_with(|| self.clock.now());
+        }
         let new_failure_count = self.failure_count.fetch_add(1, Ordering::Relaxed) + 1;
 
         let state = *self.state.read().await;
@@ -218,7 +429,8 @@ impl CircuitBreaker {
                 if new_failure_count >= self.config.failure_threshold {
                     // Open the circuit
                     *self.state.write().await = CircuitState::Open;
-                    *self.last_failure_time.write().await = Some(Instant::now());
+                    *self.last_failure_time.write().await = Some(self.clock.now());
+                    record_circuit_breaker_trip(&self.config.operation);
                     warn!(
                         "Circuit breaker opened after {} failures",
                         new_failure_count
@@ -228,28 +440,129 @@ impl CircuitBreaker {
             CircuitState::HalfOpen => {
                 // Failed in half-open state, go back to open
                 *self.state.write().await = CircuitState::Open;
-                *self.last_failure_time.write().await = Some(Instant::now());
+                *self.last_failure_time.write().await = Some(self.clock.now());
                 self.success_count.store(0, Ordering::Relaxed);
+                record_circuit_breaker_trip(&self.config.operation);
                 warn!("Circuit breaker reopened after failure in half-open state");
             }
             CircuitState::Open => {
                 // Already open, update last failure time
-                *self.last_failure_time.write().await = Some(Instant::now());
+                *self.last_failure_time.write().await = Some(self.clock.now());
             }
         }
     }
 }
 
+/// Error returned by [`Bulkhead::execute`] when every permit is taken and the queue
+/// is full
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Bulkhead is full")]
+pub struct BulkheadFull;
+
+/// Caps the number of concurrent calls to a service, so a slow provider cannot tie
+/// up every connection or task
+///
+/// Up to `max_concurrent` operations run at once. Up to `max_queue` more wait for a
+/// permit; any call beyond that is rejected immediately with [`BulkheadFull`].
+/// Clones share the same permits.
+#[derive(Debug, Clone)]
+pub struct Bulkhead {
+    semaphore: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+    max_queue: usize,
+}
+
+impl Bulkhead {
+    /// Create a bulkhead running at most `max_concurrent` operations, with at most
+    /// `max_queue` more waiting
+    pub fn new(max_concurrent: usize, max_queue: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            queued: Arc::new(AtomicUsize::new(0)),
+            max_queue,
+        }
+    }
+
+    /// Number of operations that could start right now without waiting
+    pub fn available_permits(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    /// Number of operations waiting for a permit
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Run `operation` once a permit is available, or fail fast with [`BulkheadFull`]
+    /// if the queue is full
+    pub async fn execute<F, Fut>(&self, operation: F) -> Result<Fut::Output, BulkheadFull>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future,
+    {
+        let _permit = self.acquire().await?;
+        Ok(operation().await)
+    }
+
+    async fn acquire(&self) -> Result<SemaphorePermit<'_>, BulkheadFull> {
+        if let Ok(permit) = self.semaphore.try_acquire() {
+            return Ok(permit);
+        }
+
+        if self.queued.fetch_add(1, Ordering::Relaxed) >= self.max_queue {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(BulkheadFull);
+        }
+        // Leave the queue even if the caller stops waiting
+        let _queued = QueueSlot(&self.queued);
+        Ok(self
+            .semaphore
+            .acquire()
+            .await
+            .expect("bulkhead semaphore is never closed"))
+    }
+}
+
+/// A place in a [`Bulkhead`]'s queue, given up when dropped
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn record_retry_attempt(operation: &str) {
+    metrics::counter!(RETRY_ATTEMPTS_METRIC, "operation" => operation.to_string()).increment(1);
+}
+
+#[cfg(not(feature = "metrics"))]
+fn record_retry_attempt(_operation: &str) {}
+
+#[cfg(feature = "metrics")]
+fn record_circuit_breaker_trip(operation: &str) {
+    metrics::counter!(CIRCUIT_BREAKER_TRIPS_METRIC, "operation" => operation.to_string())
+        .increment(1);
+}
+
+#[cfg(not(feature = "metrics"))]
+fn record_circuit_breaker_trip(_operation: &str) {}
+
 /// Execute an operation with retry logic and circuit breaker
 ///
 /// # Arguments
-/// * `policy` - The retry policy to use
+/// * `policy` - The retry policy to use; its `operation` labels the retry metrics
 /// * `circuit_breaker` - The circuit breaker to use (optional)
 /// * `operation` - The operation to execute, which should return a Result
 ///
 /// # Returns
 /// * `Ok(T)` if the operation succeeds
 /// * `Err(RetryError<E>)` if the operation fails after all retries or if the circuit breaker is open
+///
+/// If a failed attempt opens the circuit breaker, retrying stops and that attempt's
+/// error is returned as `RetryError::CircuitOpenedDuringRetry`, unless the policy's
+/// `stop_on_open` is off.
 pub async fn do_with_retry<T, E, F, Fut>(
     policy: &RetryPolicy,
     circuit_breaker: Option<&CircuitBreaker>,
@@ -258,6 +571,123 @@ pub async fn do_with_retry<T, E, F, Fut>(
 where
     F: FnMut() -> Fut,
     Fut: Future<Output = Result<T, E>>,
+{
+    do_with_retry_if(policy, circuit_breaker, operation, |_| true).await
+}
+
+/// Execute an operation with retry logic and circuit breaker, retrying only the errors
+/// accepted by `should_retry`
+///
+/// Errors rejected by `should_retry` (e.g. invalid input, a Redis `WRONGTYPE`) are
+/// returned immediately as `RetryError::NonRetryable` without backing off. The
+/// service did answer them, so the circuit breaker records them as successes: they
+/// do not count towards tripping it, and they complete a half-open probe.
+///
+/// # Arguments
+/// * `policy` - The retry policy to use
+/// * `circuit_breaker` - The circuit breaker to use (optional)
+/// * `operation` - The operation to execute, which should return a Result
+/// * `should_retry` - Classifies an error as transient (`true`) or permanent (`false`)
+pub async fn do_with_retry_if<T, E, F, Fut, P>(
+    policy: &RetryPolicy,
+    circuit_breaker: Option<&CircuitBreaker>,
+    operation: F,
+    should_retry: P,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
+{
+    retry_loop(policy, circuit_breaker, None, None, operation, should_retry).await
+}
+
+/// Execute an operation with retry logic and circuit breaker, giving up as soon as
+/// `cancel` fires
+///
+/// Behaves like [`do_with_retry_if`], but the backoff sleep races the cancellation
+/// token, so a shutdown does not have to wait out the remaining backoff. Cancellation
+/// is noticed between attempts only: an attempt in flight runs to completion, and
+/// the retry loop then returns `RetryError::Cancelled` with that attempt's error.
+///
+/// # Arguments
+/// * `policy` - The retry policy to use
+/// * `circuit_breaker` - The circuit breaker to use (optional)
+/// * `cancel` - Token that aborts the retry loop when cancelled
+/// * `operation` - The operation to execute, which should return a Result
+/// * `should_retry` - Classifies an error as transient (`true`) or permanent (`false`)
+pub async fn do_with_retry_cancellable<T, E, F, Fut, P>(
+    policy: &RetryPolicy,
+    circuit_breaker: Option<&CircuitBreaker>,
+    cancel: &CancellationToken,
+    operation: F,
+    should_retry: P,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
+{
+    retry_loop(
+        policy,
+        circuit_breaker,
+        None,
+        Some(cancel),
+        operation,
+        should_retry,
+    )
+    .await
+}
+
+/// Execute an operation with retry logic and circuit breaker, running each attempt
+/// inside `bulkhead`
+///
+/// Behaves like [`do_with_retry_if`], but every attempt takes a permit from the
+/// bulkhead and gives it back before backing off, so waiting retries do not hold
+/// permits. If the bulkhead is full, retrying stops with `RetryError::BulkheadFull`
+/// rather than adding load to a saturated service.
+///
+/// # Arguments
+/// * `policy` - The retry policy to use
+/// * `circuit_breaker` - The circuit breaker to use (optional)
+/// * `bulkhead` - Limits how many attempts run concurrently
+/// * `operation` - The operation to execute, which should return a Result
+/// * `should_retry` - Classifies an error as transient (`true`) or permanent (`false`)
+pub async fn do_with_retry_in_bulkhead<T, E, F, Fut, P>(
+    policy: &RetryPolicy,
+    circuit_breaker: Option<&CircuitBreaker>,
+    bulkhead: &Bulkhead,
+    operation: F,
+    should_retry: P,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
+{
+    retry_loop(
+        policy,
+        circuit_breaker,
+        Some(bulkhead),
+        None,
+        operation,
+        should_retry,
+    )
+    .await
+}
+
+async fn retry_loop<T, E, F, Fut, P>(
+    policy: &RetryPolicy,
+    circuit_breaker: Option<&CircuitBreaker>,
+    bulkhead: Option<&Bulkhead>,
+    cancel: Option<&CancellationToken>,
+    operation: F,
+    should_retry: P,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
 {
     // Check circuit breaker if provided
     if let Some(cb) = circuit_breaker {
@@ -269,7 +699,14 @@ where
     let mut attempt = 0;
     let mut op = operation;
     loop {
-        match op().await {
+        let result = match bulkhead {
+            Some(bulkhead) => bulkhead
+                .execute(&mut op)
+                .await
+                .map_err(|BulkheadFull| RetryError::BulkheadFull)?,
+            None => op().await,
+        };
+        match result {
             Ok(result) => {
                 // Record success in circuit breaker if provided
                 if let Some(cb) = circuit_breaker {
@@ -278,13 +715,20 @@ where
                 return Ok(result);
             }
             Err(error) => {
+                if !should_retry(&error) {
+                    if let Some(cb) = circuit_breaker {
+                        cb.record_success().await;
+                    }
+                    return Err(RetryError::NonRetryable(error));
+                }
+
                 // Record failure in circuit breaker if provided
                 if let Some(cb) = circuit_breaker {
                     cb.record_failure().await;
 
                     // Check if circuit breaker is now open
-                    if !cb.can_execute().await {
-                        return Err(RetryError::CircuitBreakerOpen);
+                    if policy.stop_on_open && !cb.can_execute().await {
+                        return Err(RetryError::CircuitOpenedDuringRetry(error));
                     }
                 }
 
@@ -293,10 +737,82 @@ where
                     return Err(RetryError::AttemptsExhausted(error));
                 }
 
+                record_retry_attempt(&policy.operation);
+
                 // Calculate backoff and sleep
                 let backoff = policy.calculate_backoff(attempt);
                 debug!("Attempt {} failed, retrying in {:?}", attempt, backoff);
-                sleep(backoff).await;
+                match cancel {
+                    Some(token) => tokio::select! {
+                        _ = sleep(backoff) => {}
+                        _ = token.cancelled() => {
+                            debug!("Retry cancelled during backoff after attempt {}", attempt);
+                            return Err(RetryError::Cancelled(error));
+                        }
+                    },
+                    None => sleep(backoff).await,
+                }
+            }
+        }
+    }
+}
+
+/// Execute a blocking operation with retry logic, for synchronous code such as batch
+/// jobs
+///
+/// Backs off with `std::thread::sleep` using the same schedule as [`do_with_retry`].
+/// There is no circuit breaker support, as [`CircuitBreaker`] is async.
+///
+/// # Arguments
+/// * `policy` - The retry policy to use
+/// * `operation` - The operation to execute, which should return a Result
+pub fn do_with_retry_blocking<T, E, F>(
+    policy: &RetryPolicy,
+    operation: F,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Result<T, E>,
+{
+    do_with_retry_blocking_if(policy, operation, |_| true)
+}
+
+/// Execute a blocking operation with retry logic, retrying only the errors accepted by
+/// `should_retry`
+///
+/// See [`do_with_retry_if`] for how errors are classified.
+///
+/// # Arguments
+/// * `policy` - The retry policy to use
+/// * `operation` - The operation to execute, which should return a Result
+/// * `should_retry` - Classifies an error as transient (`true`) or permanent (`false`)
+pub fn do_with_retry_blocking_if<T, E, F, P>(
+    policy: &RetryPolicy,
+    mut operation: F,
+    should_retry: P,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Result<T, E>,
+    P: Fn(&E) -> bool,
+{
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Ok(result) => return Ok(result),
+            Err(error) => {
+                if !should_retry(&error) {
+                    return Err(RetryError::NonRetryable(error));
+                }
+
+                attempt += 1;
+                if attempt > policy.max_retries {
+                    return Err(RetryError::AttemptsExhausted(error));
+                }
+
+                record_retry_attempt(&policy.operation);
+
+                let backoff = policy.calculate_backoff(attempt);
+                debug!("Attempt {} failed, retrying in {:?}", attempt, backoff);
+                std::thread::sleep(backoff);
             }
         }
     }
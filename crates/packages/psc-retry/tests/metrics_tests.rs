@@ -0,0 +1,95 @@
+#![cfg(feature = "metrics")]
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use psc_retry::*;
+use std::time::Duration;
+
+/// Value of the counter `name` with the given `operation` label, if recorded
+fn counter(snapshotter: &Snapshotter, name: &str, operation: &str) -> Option<u64> {
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .find_map(|(key, _, _, value)| {
+            let key = key.key();
+            let labeled = key
+                .labels()
+                .any(|label| label.key() == "operation" && label.value() == operation);
+            match value {
+                DebugValue::Counter(count) if key.name() == name && labeled => Some(count),
+                _ => None,
+            }
+        })
+}
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn test_retry_attempts_are_counted_per_operation() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let policy = RetryPolicy::new()
+        .with_max_retries(3)
+        .with_initial_backoff(Duration::from_millis(1))
+        .with_jitter(false)
+        .with_operation("mtn.deposit");
+
+    metrics::with_local_recorder(&recorder, || {
+        let mut attempts = 0;
+        let result = block_on(do_with_retry(&policy, None, || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt < 3 {
+                    Err("temporary failure")
+                } else {
+                    Ok("success")
+                }
+            }
+        }));
+        assert_eq!(result, Ok("success"));
+    });
+
+    assert_eq!(
+        counter(&snapshotter, RETRY_ATTEMPTS_METRIC, "mtn.deposit"),
+        Some(2)
+    );
+}
+
+#[test]
+fn test_circuit_breaker_trips_are_counted_per_operation() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let cb = CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 2,
+        timeout: Duration::from_millis(10),
+        success_threshold: 1,
+        operation: "mtn.withdraw".to_string(),
+        ..Default::default()
+    });
+
+    metrics::with_local_recorder(&recorder, || {
+        block_on(async {
+            cb.record_failure().await;
+            cb.record_failure().await;
+            assert_eq!(*cb.state.read().await, CircuitState::Open);
+
+            // A failure while half-open trips the circuit again
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert!(cb.can_execute().await);
+            cb.record_failure().await;
+            assert_eq!(*cb.state.read().await, CircuitState::Open);
+        })
+    });
+
+    assert_eq!(
+        counter(&snapshotter, CIRCUIT_BREAKER_TRIPS_METRIC, "mtn.withdraw"),
+        Some(2)
+    );
+}
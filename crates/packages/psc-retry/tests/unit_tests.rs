@@ -25,6 +25,37 @@ async fn test_retry_policy_builder() {
     assert_eq!(policy.jitter, false);
 }
 
+#[test]
+fn test_aggressive_preset() {
+    let policy = RetryPolicy::aggressive();
+    assert_eq!(policy.max_retries, 5);
+    assert_eq!(policy.initial_backoff, Duration::from_millis(10));
+    assert_eq!(policy.max_backoff, Duration::from_millis(500));
+    assert!(policy.jitter);
+    assert!(policy.stop_on_open);
+}
+
+#[test]
+fn test_conservative_preset() {
+    let policy = RetryPolicy::conservative();
+    assert_eq!(policy.max_retries, 2);
+    assert_eq!(policy.initial_backoff, Duration::from_secs(1));
+    assert_eq!(policy.max_backoff, Duration::from_secs(30));
+    assert!(policy.jitter);
+    assert!(policy.stop_on_open);
+}
+
+#[test]
+fn test_idempotent_http_preset() {
+    let policy = RetryPolicy::for_idempotent_http();
+    assert_eq!(policy.max_retries, 4);
+    assert_eq!(policy.initial_backoff, Duration::from_millis(200));
+    assert_eq!(policy.max_backoff, Duration::from_secs(5));
+    assert!(policy.jitter);
+    assert!(policy.stop_on_open);
+    assert_eq!(policy.operation, DEFAULT_OPERATION);
+}
+
 #[tokio::test]
 async fn test_successful_operation_no_retries() {
     let policy = RetryPolicy::new();
@@ -93,6 +124,7 @@ async fn test_circuit_breaker_open_and_close() {
         failure_threshold: 2,
         timeout: Duration::from_millis(100),
         success_threshold: 2,
+        ..Default::default()
     };
     let cb = CircuitBreaker::new(config);
 
@@ -135,3 +167,529 @@ async fn test_circuit_breaker_open_and_close() {
     // Circuit should now be closed
     assert_eq!(*cb.state.read().await, CircuitState::Closed);
 }
+
+#[tokio::test]
+async fn test_circuit_breaker_half_opens_when_mock_clock_passes_timeout() {
+    let clock = MockClock::new();
+    let cb = CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 1,
+        timeout: Duration::from_secs(60),
+        success_threshold: 1,
+        ..Default::default()
+    })
+    .with_clock(clock.clone());
+
+    cb.record_failure().await;
+    assert!(!cb.can_execute().await);
+
+    clock.advance(Duration::from_secs(59));
+    assert!(!cb.can_execute().await);
+    assert_eq!(*cb.state.read().await, CircuitState::Open);
+
+    clock.advance(Duration::from_secs(1));
+    assert!(cb.can_execute().await);
+    assert_eq!(*cb.state.read().await, CircuitState::HalfOpen);
+
+    cb.record_success().await;
+    assert_eq!(*cb.state.read().await, CircuitState::Closed);
+}
+
+async fn alternate_outcomes(cb: &CircuitBreaker, rounds: usize) {
+    for _ in 0..rounds {
+        cb.record_failure().await;
+        cb.record_success().await;
+    }
+}
+
+#[tokio::test]
+async fn test_consecutive_counting_never_trips_on_alternating_outcomes() {
+    let cb = CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 3,
+        ..Default::default()
+    });
+
+    alternate_outcomes(&cb, 10).await;
+
+    assert_eq!(*cb.state.read().await, CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn test_windowed_counting_trips_on_alternating_outcomes() {
+    let cb = CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 3,
+        failure_counting: FailureCounting::Windowed(Duration::from_secs(60)),
+        ..Default::default()
+    });
+
+    alternate_outcomes(&cb, 2).await;
+    assert_eq!(*cb.state.read().await, CircuitState::Closed);
+    alternate_outcomes(&cb, 1).await;
+    assert_eq!(*cb.state.read().await, CircuitState::Open);
+}
+
+#[tokio::test]
+async fn test_windowed_counting_forgets_failures_after_window() {
+    let cb = CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 3,
+        failure_counting: FailureCounting::Windowed(Duration::from_millis(50)),
+        ..Default::default()
+    });
+
+    alternate_outcomes(&cb, 2).await;
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    alternate_outcomes(&cb, 2).await;
+
+    assert_eq!(*cb.state.read().await, CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn test_retry_if_stops_on_non_retryable_error() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(3)
+        .with_initial_backoff(Duration::from_millis(1));
+    let mut call_count = 0;
+
+    let result = do_with_retry_if(
+        &policy,
+        None,
+        || {
+            call_count += 1;
+            async { Err::<String, String>("permanent error".to_string()) }
+        },
+        |error: &String| error.starts_with("temporary"),
+    )
+    .await;
+
+    assert_eq!(
+        result,
+        Err(RetryError::NonRetryable("permanent error".to_string()))
+    );
+    assert_eq!(call_count, 1);
+}
+
+#[tokio::test]
+async fn test_only_transient_errors_count_towards_circuit_breaker() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(0)
+        .with_initial_backoff(Duration::from_millis(1));
+    let cb = CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 3,
+        ..Default::default()
+    });
+    let is_transient = |error: &String| error.starts_with("temporary");
+
+    for _ in 0..10 {
+        let result = do_with_retry_if(
+            &policy,
+            Some(&cb),
+            || async { Err::<String, String>("invalid argument".to_string()) },
+            is_transient,
+        )
+        .await;
+        assert_eq!(
+            result,
+            Err(RetryError::NonRetryable("invalid argument".to_string()))
+        );
+    }
+    assert_eq!(*cb.state.read().await, CircuitState::Closed);
+
+    for _ in 0..3 {
+        let _ = do_with_retry_if(
+            &policy,
+            Some(&cb),
+            || async { Err::<String, String>("temporary error".to_string()) },
+            is_transient,
+        )
+        .await;
+    }
+    assert_eq!(*cb.state.read().await, CircuitState::Open);
+}
+
+#[tokio::test]
+async fn test_non_retryable_error_completes_half_open_probe() {
+    let clock = MockClock::new();
+    let cb = CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold: 1,
+        timeout: Duration::from_secs(60),
+        success_threshold: 1,
+        ..Default::default()
+    })
+    .with_clock(clock.clone());
+    cb.record_failure().await;
+    clock.advance(Duration::from_secs(60));
+    assert!(cb.can_execute().await);
+    assert_eq!(*cb.state.read().await, CircuitState::HalfOpen);
+
+    let result = do_with_retry_if(
+        &RetryPolicy::new().with_max_retries(0),
+        Some(&cb),
+        || async { Err::<String, String>("invalid argument".to_string()) },
+        |error: &String| error.starts_with("temporary"),
+    )
+    .await;
+
+    assert_eq!(
+        result,
+        Err(RetryError::NonRetryable("invalid argument".to_string()))
+    );
+    assert_eq!(*cb.state.read().await, CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn test_retry_if_retries_transient_errors() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(3)
+        .with_initial_backoff(Duration::from_millis(1));
+    let mut call_count = 0;
+
+    let result = do_with_retry_if(
+        &policy,
+        None,
+        || {
+            let count = call_count;
+            call_count += 1;
+            async move {
+                if count < 2 {
+                    Err::<String, String>("temporary error".to_string())
+                } else {
+                    Ok("success".to_string())
+                }
+            }
+        },
+        |error: &String| error.starts_with("temporary"),
+    )
+    .await;
+
+    assert_eq!(result, Ok("success".to_string()));
+    assert_eq!(call_count, 3);
+}
+
+#[test]
+fn test_jittered_backoff_never_exceeds_max_backoff() {
+    let policy = RetryPolicy::new()
+        .with_initial_backoff(Duration::from_millis(100))
+        .with_max_backoff(Duration::from_millis(500))
+        .with_jitter(true);
+
+    for attempt in 0..10 {
+        for _ in 0..1_000 {
+            assert!(policy.calculate_backoff(attempt) <= policy.max_backoff);
+        }
+    }
+}
+
+#[test]
+fn test_jittered_backoff_stays_within_25_percent() {
+    let policy = RetryPolicy::new()
+        .with_initial_backoff(Duration::from_millis(100))
+        .with_max_backoff(Duration::from_millis(500))
+        .with_jitter(true);
+
+    for _ in 0..1_000 {
+        // Below the cap jitter is added
+        let backoff = policy.calculate_backoff(1);
+        assert!(backoff >= Duration::from_millis(200) && backoff <= Duration::from_millis(250));
+
+        // At the cap it is subtracted
+        let backoff = policy.calculate_backoff(5);
+        assert!(backoff >= Duration::from_millis(375) && backoff <= Duration::from_millis(500));
+    }
+}
+
+#[test]
+fn test_blocking_retry_until_success() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(3)
+        .with_initial_backoff(Duration::from_millis(1));
+    let mut call_count = 0;
+
+    let result = do_with_retry_blocking(&policy, || {
+        call_count += 1;
+        if call_count < 3 {
+            Err::<String, String>("temporary error".to_string())
+        } else {
+            Ok("success".to_string())
+        }
+    });
+
+    assert_eq!(result, Ok("success".to_string()));
+    assert_eq!(call_count, 3);
+}
+
+#[test]
+fn test_blocking_retry_exhausted() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(2)
+        .with_initial_backoff(Duration::from_millis(1));
+    let mut call_count = 0;
+
+    let result = do_with_retry_blocking(&policy, || {
+        call_count += 1;
+        Err::<String, String>("permanent error".to_string())
+    });
+
+    assert_eq!(
+        result,
+        Err(RetryError::AttemptsExhausted("permanent error".to_string()))
+    );
+    assert_eq!(call_count, 3); // Initial attempt + 2 retries
+}
+
+#[test]
+fn test_blocking_retry_if_stops_on_non_retryable_error() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(3)
+        .with_initial_backoff(Duration::from_millis(1));
+    let mut call_count = 0;
+
+    let result = do_with_retry_blocking_if(
+        &policy,
+        || {
+            call_count += 1;
+            Err::<String, String>("invalid input".to_string())
+        },
+        |error: &String| error.starts_with("temporary"),
+    );
+
+    assert_eq!(
+        result,
+        Err(RetryError::NonRetryable("invalid input".to_string()))
+    );
+    assert_eq!(call_count, 1);
+}
+
+fn breaker_tripping_after(failure_threshold: usize) -> CircuitBreaker {
+    CircuitBreaker::new(CircuitBreakerConfig {
+        failure_threshold,
+        timeout: Duration::from_secs(60),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_circuit_opening_mid_retry_surfaces_last_error() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(10)
+        .with_initial_backoff(Duration::from_millis(1))
+        .with_jitter(false);
+    let cb = breaker_tripping_after(2);
+    let mut call_count = 0;
+
+    let result = do_with_retry(&policy, Some(&cb), || {
+        call_count += 1;
+        let attempt = call_count;
+        async move { Err::<String, String>(format!("temporary error {attempt}")) }
+    })
+    .await;
+
+    assert_eq!(
+        result,
+        Err(RetryError::CircuitOpenedDuringRetry(
+            "temporary error 2".to_string()
+        ))
+    );
+    assert_eq!(call_count, 2);
+    assert_eq!(*cb.state.read().await, CircuitState::Open);
+
+    // Later calls are rejected before any attempt
+    let result = do_with_retry(&policy, Some(&cb), || async {
+        Ok::<String, String>("success".to_string())
+    })
+    .await;
+    assert_eq!(result, Err(RetryError::CircuitBreakerOpen));
+}
+
+#[tokio::test]
+async fn test_without_stop_on_open_retries_run_to_completion() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(4)
+        .with_initial_backoff(Duration::from_millis(1))
+        .with_jitter(false)
+        .with_stop_on_open(false);
+    let cb = breaker_tripping_after(2);
+    let mut call_count = 0;
+
+    let result = do_with_retry(&policy, Some(&cb), || {
+        call_count += 1;
+        let attempt = call_count;
+        async move {
+            if attempt < 4 {
+                Err(format!("temporary error {attempt}"))
+            } else {
+                Ok("success".to_string())
+            }
+        }
+    })
+    .await;
+
+    assert_eq!(result, Ok("success".to_string()));
+    assert_eq!(call_count, 4);
+
+    let result = do_with_retry(
+        &RetryPolicy::new()
+            .with_max_retries(1)
+            .with_initial_backoff(Duration::from_millis(1))
+            .with_stop_on_open(false),
+        Some(&breaker_tripping_after(1)),
+        || async { Err::<String, String>("temporary error".to_string()) },
+    )
+    .await;
+    assert_eq!(
+        result,
+        Err(RetryError::AttemptsExhausted("temporary error".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn test_cancel_during_backoff_returns_promptly() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(5)
+        .with_initial_backoff(Duration::from_secs(60))
+        .with_jitter(false);
+    let cancel = CancellationToken::new();
+    let canceller = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        canceller.cancel();
+    });
+    let mut call_count = 0;
+
+    let result = timeout(
+        Duration::from_secs(1),
+        do_with_retry_cancellable(
+            &policy,
+            None,
+            &cancel,
+            || {
+                call_count += 1;
+                async { Err::<String, String>("temporary error".to_string()) }
+            },
+            |_| true,
+        ),
+    )
+    .await
+    .expect("retry loop should stop as soon as it is cancelled");
+
+    assert_eq!(
+        result,
+        Err(RetryError::Cancelled("temporary error".to_string()))
+    );
+    assert_eq!(call_count, 1);
+}
+
+#[tokio::test]
+async fn test_uncancelled_token_does_not_affect_retries() {
+    let policy = RetryPolicy::new()
+        .with_max_retries(3)
+        .with_initial_backoff(Duration::from_millis(1))
+        .with_jitter(false);
+    let cancel = CancellationToken::new();
+    let mut call_count = 0;
+
+    let result = do_with_retry_cancellable(
+        &policy,
+        None,
+        &cancel,
+        || {
+            call_count += 1;
+            let attempt = call_count;
+            async move {
+                if attempt < 3 {
+                    Err("temporary error".to_string())
+                } else {
+                    Ok("success".to_string())
+                }
+            }
+        },
+        |_| true,
+    )
+    .await;
+
+    assert_eq!(result, Ok("success".to_string()));
+    assert_eq!(call_count, 3);
+}
+
+/// Start an operation on `bulkhead` that holds its permit until `release` fires
+async fn hold_permit(
+    bulkhead: &Bulkhead,
+    release: tokio::sync::oneshot::Receiver<()>,
+) -> tokio::task::JoinHandle<Result<(), BulkheadFull>> {
+    let bulkhead = bulkhead.clone();
+    let handle = tokio::spawn(async move {
+        bulkhead
+            .execute(|| async {
+                release.await.ok();
+            })
+            .await
+    });
+    tokio::task::yield_now().await;
+    handle
+}
+
+#[tokio::test]
+async fn test_bulkhead_rejects_past_queue_limit() {
+    let bulkhead = Bulkhead::new(1, 1);
+    let (release, released) = tokio::sync::oneshot::channel();
+    let running = hold_permit(&bulkhead, released).await;
+    assert_eq!(bulkhead.available_permits(), 0);
+
+    // The second call waits in the queue
+    let queued = tokio::spawn({
+        let bulkhead = bulkhead.clone();
+        async move { bulkhead.execute(|| async { "queued" }).await }
+    });
+    tokio::task::yield_now().await;
+    assert_eq!(bulkhead.queued(), 1);
+
+    // The third is rejected without waiting
+    let rejected = timeout(
+        Duration::from_millis(100),
+        bulkhead.execute(|| async { "rejected" }),
+    )
+    .await
+    .expect("a full bulkhead should reject immediately");
+    assert_eq!(rejected, Err(BulkheadFull));
+
+    release.send(()).unwrap();
+    assert_eq!(running.await.unwrap(), Ok(()));
+    assert_eq!(queued.await.unwrap(), Ok("queued"));
+    assert_eq!(bulkhead.queued(), 0);
+    assert_eq!(bulkhead.available_permits(), 1);
+}
+
+#[tokio::test]
+async fn test_retry_in_full_bulkhead_fails_fast() {
+    let bulkhead = Bulkhead::new(1, 0);
+    let (release, released) = tokio::sync::oneshot::channel();
+    let running = hold_permit(&bulkhead, released).await;
+    let policy = RetryPolicy::new()
+        .with_max_retries(3)
+        .with_initial_backoff(Duration::from_millis(1));
+    let mut call_count = 0;
+
+    let result = do_with_retry_in_bulkhead(
+        &policy,
+        None,
+        &bulkhead,
+        || {
+            call_count += 1;
+            async { Ok::<String, String>("success".to_string()) }
+        },
+        |_| true,
+    )
+    .await;
+    assert_eq!(result, Err(RetryError::BulkheadFull));
+    assert_eq!(call_count, 0);
+
+    release.send(()).unwrap();
+    running.await.unwrap().unwrap();
+    let result = do_with_retry_in_bulkhead(
+        &policy,
+        None,
+        &bulkhead,
+        || async { Ok::<String, String>("success".to_string()) },
+        |_| true,
+    )
+    .await;
+    assert_eq!(result, Ok("success".to_string()));
+}
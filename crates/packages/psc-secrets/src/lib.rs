@@ -3,12 +3,20 @@
 
 //! A shared client for securely retrieving secrets from HashiCorp Vault or a cloud Key Management Service (KMS).
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use async_trait::async_trait;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use url::Url;
 
+/// How long to wait before retrying a failed token renewal.
+const RENEWAL_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Error types for secret management operations.
 #[derive(thiserror::Error, Debug)]
 pub enum SecretError {
@@ -26,6 +34,12 @@ pub enum SecretError {
     JsonParse(#[from] serde_json::Error),
     #[error("Authentication error: {0}")]
     Authentication(String),
+    #[error("Environment variable '{0}' is not set")]
+    EnvVarNotSet(String),
+    #[error("Configuration is nested deeper than the maximum of {0} levels")]
+    MaxDepthExceeded(usize),
+    #[error("Listing the keys at path '{0}' is not supported")]
+    ListingNotSupported(String),
 }
 
 /// Trait for abstracting secret management operations.
@@ -42,6 +56,24 @@ pub trait SecretManager: Send + Sync {
     ///
     /// The secret value as a String, or a `SecretError` if retrieval fails.
     async fn get_secret(&self, path: &str, key: &str) -> Result<String, SecretError>;
+
+    /// Retrieves every key stored at the specified path in a single request.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the secret (e.g., "secret/data/my-app/config").
+    ///
+    /// # Returns
+    ///
+    /// The secret's keys and values, or a `SecretError` if retrieval fails. If nothing
+    /// is stored at `path` the error is `SecretError::SecretNotFound` with an empty
+    /// `key`.
+    ///
+    /// The default returns `SecretError::ListingNotSupported`, for managers that can
+    /// only look keys up one at a time with [`get_secret`](Self::get_secret).
+    async fn get_all(&self, path: &str) -> Result<HashMap<String, String>, SecretError> {
+        Err(SecretError::ListingNotSupported(path.to_string()))
+    }
 }
 
 /// Configuration for the Vault client.
@@ -57,26 +89,143 @@ pub struct VaultConfig {
 pub struct VaultSecretManager {
     client: reqwest::Client,
     config: VaultConfig,
+    token: Arc<RwLock<Option<String>>>, // Current token, replaced on renewal
+}
+
+/// Handle to the background task started by [`VaultSecretManager::start_renewal`].
+#[derive(Debug)]
+pub struct RenewalHandle {
+    task: JoinHandle<Result<(), SecretError>>,
+}
+
+impl RenewalHandle {
+    /// Stop renewing the token. The token keeps working until its lease expires.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    /// Wait for the renewal task to end on its own.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that made the task give up renewing the token.
+    pub async fn wait(self) -> Result<(), SecretError> {
+        self.task.await.unwrap_or_else(|e| {
+            Err(SecretError::VaultApi(format!(
+                "Token renewal task failed: {}",
+                e
+            )))
+        })
+    }
+}
+
+/// Whether a failed renewal will fail again however often it is retried, e.g.
+/// because the token was revoked.
+fn is_permanent_renewal_error(err: &SecretError) -> bool {
+    match err {
+        SecretError::Network(e) => e.status().is_some_and(|status| {
+            status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        }),
+        SecretError::Authentication(_) => true,
+        _ => false,
+    }
 }
 
 impl VaultSecretManager {
     pub fn new(config: VaultConfig) -> Self {
         Self {
             client: reqwest::Client::new(),
+            token: Arc::new(RwLock::new(config.token.clone())),
             config,
         }
     }
 
-    /// Authenticates with Vault using a token.
-    async fn authenticate_token(&self) -> Result<(), SecretError> {
-        if self.config.token.is_none() {
-            return Err(SecretError::Authentication(
-                "No Vault token provided".to_string(),
-            ));
-        }
+    /// Authenticates with Vault using a token, returning the current token.
+    async fn authenticate_token(&self) -> Result<String, SecretError> {
         // In a real application, you'd validate the token or perform a login.
         // For simplicity, we assume the provided token is valid for direct use.
-        Ok(())
+        self.token
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| SecretError::Authentication("No Vault token provided".to_string()))
+    }
+
+    /// Spawn a task that keeps the Vault token from expiring.
+    ///
+    /// The token is renewed immediately and then again once two thirds of each new
+    /// lease has elapsed, and the renewed token is used by this manager and all its
+    /// clones. Failed renewals are retried every few seconds. The task ends on its
+    /// own if Vault reports the token is not renewable, and gives up with an error
+    /// (see [`RenewalHandle::wait`]) once Vault rejects the token or its lease has
+    /// expired.
+    pub fn start_renewal(&self) -> RenewalHandle {
+        let manager = self.clone();
+        let task = tokio::spawn(async move {
+            let mut lease_expires_at = None;
+            loop {
+                let delay = match manager.renew_token().await {
+                    Ok(Some(lease)) if !lease.is_zero() => {
+                        lease_expires_at = Some(Instant::now() + lease);
+                        lease * 2 / 3
+                    }
+                    Ok(_) => return Ok(()),
+                    Err(e)
+                        if is_permanent_renewal_error(&e)
+                            || lease_expires_at.is_some_and(|at| at <= Instant::now()) =>
+                    {
+                        tracing::error!(error = %e, "Giving up renewing the Vault token");
+                        return Err(e);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            error = %e,
+                            retry_in = ?RENEWAL_RETRY_INTERVAL,
+                            "Failed to renew the Vault token"
+                        );
+                        RENEWAL_RETRY_INTERVAL
+                    }
+                };
+                tokio::time::sleep(delay).await;
+            }
+        });
+        RenewalHandle { task }
+    }
+
+    /// Renew the current token, storing the token Vault returns.
+    ///
+    /// Returns the new lease duration, or `None` if the token is not renewable.
+    async fn renew_token(&self) -> Result<Option<Duration>, SecretError> {
+        let token = self.authenticate_token().await?;
+        let url = self.config.addr.join("auth/token/renew-self")?;
+
+        let response = self
+            .client
+            .post(url)
+            .header("X-Vault-Token", token)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        #[derive(Deserialize)]
+        struct VaultAuth {
+            client_token: String,
+            lease_duration: u64,
+            renewable: bool,
+        }
+
+        #[derive(Deserialize)]
+        struct VaultRenewResponse {
+            auth: VaultAuth,
+        }
+
+        let renewed: VaultRenewResponse = response.json().await?;
+        *self.token.write().await = Some(renewed.auth.client_token);
+
+        Ok(renewed
+            .auth
+            .renewable
+            .then(|| Duration::from_secs(renewed.auth.lease_duration)))
     }
 
     /// Builds the full URL for a Vault secret.
@@ -92,17 +241,36 @@ impl VaultSecretManager {
 #[async_trait]
 impl SecretManager for VaultSecretManager {
     async fn get_secret(&self, path: &str, key: &str) -> Result<String, SecretError> {
-        self.authenticate_token().await?;
+        let not_found = || SecretError::SecretNotFound {
+            path: path.to_string(),
+            key: key.to_string(),
+        };
+        match self.get_all(path).await {
+            Ok(mut secrets) => secrets.remove(key).ok_or_else(not_found),
+            Err(SecretError::SecretNotFound { .. }) => Err(not_found()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Values that are not strings are left out.
+    async fn get_all(&self, path: &str) -> Result<HashMap<String, String>, SecretError> {
+        let token = self.authenticate_token().await?;
 
         let url = self.build_secret_url(path)?;
 
-        let mut request = self.client.get(url);
-        if let Some(token) = &self.config.token {
-            request = request.header("X-Vault-Token", token);
+        let response = self
+            .client
+            .get(url)
+            .header("X-Vault-Token", token)
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(SecretError::SecretNotFound {
+                path: path.to_string(),
+                key: String::new(),
+            });
         }
-
-        let response = request.send().await?.error_for_status()?;
-        let json_response: serde_json::Value = response.json().await?;
+        let json_response: serde_json::Value = response.error_for_status()?.json().await?;
 
         #[derive(Deserialize)]
         struct VaultData {
@@ -118,14 +286,110 @@ impl SecretManager for VaultSecretManager {
             SecretError::InvalidSecretData(format!("Failed to parse Vault response: {}", e))
         })?;
 
-        vault_response
+        Ok(vault_response
             .data
             .data
-            .get(key)
-            .and_then(|v| v.as_str().map(|s| s.to_string()))
-            .ok_or_else(|| SecretError::SecretNotFound {
-                path: path.to_string(),
-                key: key.to_string(),
+            .into_iter()
+            .filter_map(|(key, value)| match value {
+                serde_json::Value::String(value) => Some((key, value)),
+                _ => None,
             })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn vault_config(server: &MockServer) -> VaultConfig {
+        VaultConfig {
+            addr: Url::parse(&format!("{}/v1/", server.uri())).unwrap(),
+            token: Some("initial-token".to_string()),
+            mount_path: "secret".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_is_renewed_before_lease_expires() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/auth/token/renew-self"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "auth": {
+                    "client_token": "renewed-token",
+                    "lease_duration": 1,
+                    "renewable": true,
+                }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/db"))
+            .and(header("X-Vault-Token", "renewed-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "data": { "password": "hunter2" } }
+            })))
+            .mount(&server)
+            .await;
+
+        let manager = VaultSecretManager::new(vault_config(&server));
+        let renewal = manager.start_renewal();
+
+        // The first renewal is immediate, the next due before the 1s lease expires
+        tokio::time::sleep(Duration::from_millis(900)).await;
+        renewal.stop();
+
+        let renewals = server.received_requests().await.unwrap();
+        assert_eq!(renewals[0].headers["X-Vault-Token"], "initial-token");
+        assert!(renewals.len() >= 2);
+        assert_eq!(
+            manager.get_secret("db", "password").await.unwrap(),
+            "hunter2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_renewal_gives_up_when_vault_rejects_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/auth/token/renew-self"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+                "errors": ["permission denied"]
+            })))
+            .mount(&server)
+            .await;
+
+        let manager = VaultSecretManager::new(vault_config(&server));
+        let result = tokio::time::timeout(Duration::from_secs(1), manager.start_renewal().wait())
+            .await
+            .expect("renewal should give up instead of retrying");
+
+        assert!(matches!(result, Err(SecretError::Network(_))));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_missing_path_is_secret_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+                "errors": []
+            })))
+            .mount(&server)
+            .await;
+
+        let manager = VaultSecretManager::new(vault_config(&server));
+
+        let err = manager.get_all("missing").await.unwrap_err();
+        assert!(matches!(err, SecretError::SecretNotFound { ref path, .. } if path == "missing"));
+        let err = manager.get_secret("missing", "password").await.unwrap_err();
+        assert!(matches!(
+            err,
+            SecretError::SecretNotFound { ref key, .. } if key == "password"
+        ));
     }
 }
@@ -1,55 +1,191 @@
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
 use tracing_subscriber::EnvFilter;
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use axum_otel_metrics::{HttpMetricsLayer, HttpMetricsLayerBuilder, PathSkipper};
-use opentelemetry::global;
+use opentelemetry::propagation::Injector;
+use opentelemetry::trace::{SpanBuilder, SpanId, TraceId, Tracer, TracerProvider as _};
+use opentelemetry::{Context, global};
 use opentelemetry_otlp::{Compression, Protocol, SpanExporter, WithExportConfig, WithTonicConfig};
 use opentelemetry_sdk::metrics::SdkMeterProvider;
-use opentelemetry_sdk::trace::{RandomIdGenerator, Sampler, TracerProviderBuilder};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{
+    RandomIdGenerator, Sampler, SdkTracer, SdkTracerProvider, TracerProviderBuilder,
+};
 use opentelemetry_sdk::Resource;
+use reqwest::RequestBuilder;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use std::time::Duration;
+use tracing_opentelemetry::{OpenTelemetrySpanExt, OtelData, PreSampledTracer};
 use tracing_subscriber::layer::SubscriberExt;
 
-/// Minimal telemetry shim compatible with newer OpenTelemetry crates.
+/// Per-service telemetry settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryConfig {
+    /// Service name reported on every span and metric.
+    pub service_name: String,
+    /// OTLP collector endpoint (e.g. `http://otel-collector:4317`). When `None`, the
+    /// exporter falls back to `OTEL_EXPORTER_OTLP_ENDPOINT` or its built-in default.
+    pub otlp_endpoint: Option<String>,
+    /// Fraction of traces to sample, between `0.0` and `1.0` inclusive.
+    pub sample_ratio: f64,
+}
+
+impl TelemetryConfig {
+    /// Create a config for `service_name` that samples every trace and takes the
+    /// endpoint from the environment.
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self {
+            service_name: service_name.into(),
+            otlp_endpoint: None,
+            sample_ratio: 1.0,
+        }
+    }
+
+    /// Set the OTLP collector endpoint
+    pub fn with_otlp_endpoint(mut self, otlp_endpoint: impl Into<String>) -> Self {
+        self.otlp_endpoint = Some(otlp_endpoint.into());
+        self
+    }
+
+    /// Set the fraction of traces to sample
+    pub fn with_sample_ratio(mut self, sample_ratio: f64) -> Self {
+        self.sample_ratio = sample_ratio;
+        self
+    }
+
+    /// Checks that the sample ratio is within `[0, 1]`.
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.sample_ratio) {
+            anyhow::bail!(
+                "invalid telemetry sample_ratio {}: must be between 0.0 and 1.0",
+                self.sample_ratio
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the telemetry pipeline alive.
+///
+/// Dropping the guard calls [`shutdown_telemetry`], so hold it for the lifetime of
+/// the process (e.g. bind it in `main`). A guard from an earlier
+/// [`setup_telemetry`] call leaves the pipeline alone once a newer one exists.
+#[must_use = "dropping the guard shuts down the telemetry pipeline"]
+pub struct TelemetryGuard {
+    generation: u64,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        // Nowhere to report a failure from here; call shutdown_telemetry to see it
+        let _ = shutdown(Some(self.generation));
+    }
+}
+
+/// Providers owned by the process-wide pipeline.
+///
+/// OpenTelemetry 0.29 no longer offers `global::shutdown_tracer_provider`, so the
+/// handles are kept here to be flushed on shutdown.
+struct TelemetryState {
+    /// Incremented by every [`setup_telemetry`] call, to tell stale guards apart.
+    generation: u64,
+    tracer: SwappableTracer,
+    tracer_provider: Option<SdkTracerProvider>,
+    meter_provider: SdkMeterProvider,
+}
+
+static TELEMETRY: Mutex<Option<TelemetryState>> = Mutex::new(None);
+
+fn telemetry_state() -> MutexGuard<'static, Option<TelemetryState>> {
+    TELEMETRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Tracer handed to the `tracing` layer.
 ///
-/// NOTE:
-/// This is a conservative temporary implementation to keep the workspace
-/// compiling while we upgrade the OpenTelemetry stack to 0.30+. The previous
-/// implementation used older opentelemetry_otlp APIs that changed in the 0.30
-/// series. A full port (with OTLP exporter and batch pipeline) should replace
-/// this shim later.
+/// A global subscriber can only be installed once per process, so the layer
+/// delegates to whichever SDK tracer is current. This lets telemetry be shut down
+/// and set up again without reinstalling the subscriber.
+#[derive(Clone)]
+struct SwappableTracer(Arc<RwLock<SdkTracer>>);
+
+impl SwappableTracer {
+    fn new(tracer: SdkTracer) -> Self {
+        Self(Arc::new(RwLock::new(tracer)))
+    }
+
+    fn current(&self) -> SdkTracer {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn replace(&self, tracer: SdkTracer) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = tracer;
+    }
+}
+
+impl Tracer for SwappableTracer {
+    type Span = opentelemetry_sdk::trace::Span;
+
+    fn build_with_context(&self, builder: SpanBuilder, parent_cx: &Context) -> Self::Span {
+        self.current().build_with_context(builder, parent_cx)
+    }
+}
+
+impl PreSampledTracer for SwappableTracer {
+    fn sampled_context(&self, data: &mut OtelData) -> Context {
+        self.current().sampled_context(data)
+    }
+
+    fn new_trace_id(&self) -> TraceId {
+        self.current().new_trace_id()
+    }
+
+    fn new_span_id(&self) -> SpanId {
+        self.current().new_span_id()
+    }
+}
+
+/// Installs the global `tracing` subscriber.
 ///
-/// Public surface kept the same:
-/// - init_subscriber(service_name) -> impl Subscriber
-/// - init_tracer(service_name) -> Result<..., _>
-/// - setup_telemetry(service_name) -> Result<(), Box<dyn Error>>
-pub fn init_subscriber(_service_name: &str) -> Result<(), Box<dyn Error>> {
-    let subscriber = tracing_subscriber::registry().with(EnvFilter::from_default_env());
+/// Spans and events are filtered with `RUST_LOG`, printed locally through a `fmt`
+/// layer, and exported through an OpenTelemetry layer backed by `tracer`.
+pub fn init_subscriber<T>(tracer: T) -> Result<(), Box<dyn Error>>
+where
+    T: Tracer + PreSampledTracer + Send + Sync + 'static,
+    T::Span: Send + Sync,
+{
+    let subscriber = tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_opentelemetry::layer().with_tracer(tracer));
     tracing::subscriber::set_global_default(subscriber)?;
 
     Ok(())
 }
 
-/// init_tracer currently returns an error indicating telemetry is not yet wired.
-///
-/// This intentionally avoids depending on unstable/private SDK internals.
-/// Replace this with a real Tracer creation when porting to the newer APIs.
-pub fn init_tracer_provider(service_name: &str) -> Result<(), Box<dyn Error>> {
-    let exporter = SpanExporter::builder()
+/// Builds the OTLP tracer provider with a batch exporter and installs it globally,
+/// together with the W3C trace-context propagator.
+pub fn init_tracer_provider(config: &TelemetryConfig) -> Result<SdkTracerProvider, Box<dyn Error>> {
+    let mut exporter = SpanExporter::builder()
         .with_tonic()
         .with_compression(Compression::Gzip)
-        .with_timeout(Duration::from_secs(3))
-        .build()?;
+        .with_timeout(Duration::from_secs(3));
+    if let Some(endpoint) = &config.otlp_endpoint {
+        exporter = exporter.with_endpoint(endpoint.clone());
+    }
+    let exporter = exporter.build()?;
 
     let resource = Resource::builder()
-        .with_service_name(service_name.to_string())
+        .with_service_name(config.service_name.clone())
         .build();
 
     let tracer_provider = TracerProviderBuilder::default()
         .with_batch_exporter(exporter)
-        .with_sampler(Sampler::AlwaysOn)
+        .with_sampler(Sampler::TraceIdRatioBased(config.sample_ratio))
         .with_id_generator(RandomIdGenerator::default())
         .with_max_events_per_span(16)
         .with_max_attributes_per_span(16)
@@ -57,21 +193,25 @@ pub fn init_tracer_provider(service_name: &str) -> Result<(), Box<dyn Error>> {
         .build();
 
     global::set_tracer_provider(tracer_provider.clone());
+    global::set_text_map_propagator(TraceContextPropagator::new());
 
-    Ok(())
+    Ok(tracer_provider)
 }
 
-pub fn init_meter_provider(service_name: &str) -> Result<()> {
+pub fn init_meter_provider(config: &TelemetryConfig) -> Result<SdkMeterProvider> {
     let prometheus_exporter = opentelemetry_prometheus::exporter()
         .with_registry(prometheus::default_registry().clone())
         .build()?;
 
-    let exporter = opentelemetry_otlp::MetricExporter::builder()
+    let mut exporter = opentelemetry_otlp::MetricExporter::builder()
         .with_tonic()
         .with_compression(Compression::Gzip)
         .with_protocol(Protocol::Grpc)
-        .with_timeout(Duration::from_secs(3))
-        .build()?;
+        .with_timeout(Duration::from_secs(3));
+    if let Some(endpoint) = &config.otlp_endpoint {
+        exporter = exporter.with_endpoint(endpoint.clone());
+    }
+    let exporter = exporter.build()?;
 
     let reader = opentelemetry_sdk::metrics::PeriodicReader::builder(exporter)
         .with_interval(Duration::from_secs(3))
@@ -82,27 +222,118 @@ pub fn init_meter_provider(service_name: &str) -> Result<()> {
         .with_reader(reader)
         .with_resource(
             Resource::builder()
-                .with_service_name(service_name.to_string())
+                .with_service_name(config.service_name.clone())
                 .build(),
         )
         .build();
 
     global::set_meter_provider(meter_provider.clone());
 
-    Ok(())
+    Ok(meter_provider)
+}
+
+/// Sets up OTLP tracing and metrics and installs the global subscriber.
+///
+/// The returned guard must be kept alive; dropping it flushes and shuts down the
+/// pipeline.
+///
+/// Calling this again after [`shutdown_telemetry`] builds a fresh tracer provider
+/// behind the already installed subscriber. The meter provider is created on the
+/// first call only, because its Prometheus exporter registers with the process-wide
+/// default registry.
+pub fn setup_telemetry(config: &TelemetryConfig) -> Result<TelemetryGuard, Box<dyn Error>> {
+    config.validate()?;
+
+    let mut state = telemetry_state();
+    let tracer_provider = init_tracer_provider(config)?;
+    let tracer = tracer_provider.tracer(config.service_name.clone());
+
+    let generation = match state.as_mut() {
+        Some(state) => {
+            state.generation += 1;
+            state.tracer.replace(tracer);
+            if let Some(previous) = state.tracer_provider.replace(tracer_provider)
+                && let Err(e) = previous.shutdown()
+            {
+                tracing::warn!(error = %e, "failed to shut down previous tracer provider");
+            }
+            state.generation
+        }
+        None => {
+            let meter_provider = init_meter_provider(config)?;
+            let tracer = SwappableTracer::new(tracer);
+            init_subscriber(tracer.clone())?;
+            *state = Some(TelemetryState {
+                generation: 0,
+                tracer,
+                tracer_provider: Some(tracer_provider),
+                meter_provider,
+            });
+            0
+        }
+    };
+
+    Ok(TelemetryGuard { generation })
+}
+
+/// Flushes buffered spans and metrics and shuts down the tracer provider.
+///
+/// Spans are exported in batches, so anything still buffered when the process
+/// exits is lost. Call this (or drop the [`TelemetryGuard`]) before returning from
+/// `main`. Calling it more than once, or before [`setup_telemetry`], is a no-op.
+///
+/// Both providers are flushed even if one fails; the first failure is returned.
+pub fn shutdown_telemetry() -> Result<()> {
+    shutdown(None)
 }
 
-/// Set up global subscriber. For now we set a simple env-filter subscriber so
-/// logs/traces are routed through tracing without an OpenTelemetry exporter.
-/// Replace with an OTLP + tracing integration during a proper port.
-pub fn setup_telemetry(service_name: &str) -> Result<(), Box<dyn Error>> {
-    let _ = init_tracer_provider(service_name)?;
-    let _ = init_meter_provider(service_name)?;
-    let _ = init_subscriber(service_name)?;
+/// Shuts down the pipeline, or only if it is still at `generation` when given.
+fn shutdown(generation: Option<u64>) -> Result<()> {
+    let mut state = telemetry_state();
+    let Some(state) = state.as_mut() else {
+        return Ok(());
+    };
+    if generation.is_some_and(|generation| generation != state.generation) {
+        return Ok(());
+    }
 
+    let traces = match state.tracer_provider.take() {
+        Some(tracer_provider) => tracer_provider.shutdown(),
+        None => Ok(()),
+    };
+    let metrics = state.meter_provider.force_flush();
+    traces.context("failed to shut down tracer provider")?;
+    metrics.context("failed to flush meter provider")?;
     Ok(())
 }
 
+/// Adds the current span's trace context to an outbound request.
+///
+/// Headers (`traceparent`, `tracestate`) come from the global propagator, so the
+/// trace continues in the downstream service instead of stopping at our boundary.
+pub fn inject_trace_context(req_builder: RequestBuilder) -> RequestBuilder {
+    let context = tracing::Span::current().context();
+    let mut headers = HeaderMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers));
+    });
+
+    req_builder.headers(headers)
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
 pub fn metric_layers(skip: Arc<dyn Fn(&str) -> bool + 'static + Send + Sync>) -> HttpMetricsLayer {
     let metrics = HttpMetricsLayerBuilder::default()
         .with_skipper(PathSkipper::new_with_fn(skip))
@@ -110,3 +341,113 @@ pub fn metric_layers(skip: Arc<dyn Fn(&str) -> bool + 'static + Send + Sync>) ->
 
     metrics
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serializes tests that set up or shut down the process-wide pipeline.
+    static PIPELINE: Mutex<()> = Mutex::new(());
+
+    fn pipeline_lock() -> MutexGuard<'static, ()> {
+        PIPELINE.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[tokio::test]
+    async fn test_setup_telemetry_emits_span() {
+        let _pipeline = pipeline_lock();
+        let config = TelemetryConfig::new("psc-telemetry-test")
+            .with_otlp_endpoint("http://localhost:4317")
+            .with_sample_ratio(0.5);
+        let guard = setup_telemetry(&config).expect("telemetry pipeline builds");
+
+        let span = tracing::info_span!("test_span", test = true);
+        span.in_scope(|| tracing::info!("inside test span"));
+
+        drop(guard);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_telemetry_allows_reinit() {
+        let _pipeline = pipeline_lock();
+        let config = TelemetryConfig::new("psc-telemetry-reinit-test")
+            .with_otlp_endpoint("http://localhost:4317");
+
+        let guard = setup_telemetry(&config).expect("telemetry pipeline builds");
+        tracing::info_span!("before_shutdown").in_scope(|| tracing::info!("first pipeline"));
+        let _ = shutdown_telemetry();
+        assert!(shutdown_telemetry().is_ok());
+        drop(guard);
+
+        let guard = setup_telemetry(&config).expect("telemetry pipeline rebuilds");
+        tracing::info_span!("after_reinit").in_scope(|| tracing::info!("second pipeline"));
+        drop(guard);
+    }
+
+    #[tokio::test]
+    async fn test_stale_guard_does_not_shut_down_newer_pipeline() {
+        let _pipeline = pipeline_lock();
+        let config = TelemetryConfig::new("psc-telemetry-generation-test")
+            .with_otlp_endpoint("http://localhost:4317");
+        let is_running = || {
+            telemetry_state()
+                .as_ref()
+                .is_some_and(|state| state.tracer_provider.is_some())
+        };
+
+        let first = setup_telemetry(&config).expect("telemetry pipeline builds");
+        let second = setup_telemetry(&config).expect("telemetry pipeline rebuilds");
+        drop(first);
+        assert!(is_running());
+
+        drop(second);
+        assert!(!is_running());
+    }
+
+    #[test]
+    fn test_inject_trace_context_sets_traceparent() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let request = tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("outbound_call");
+            let _entered = span.enter();
+            inject_trace_context(reqwest::Client::new().get("http://localhost/"))
+                .build()
+                .expect("request builds")
+        });
+
+        let traceparent = request
+            .headers()
+            .get("traceparent")
+            .expect("traceparent header is set");
+        assert!(traceparent.to_str().unwrap().starts_with("00-"));
+    }
+
+    #[test]
+    fn test_sample_ratio_bounds_accepted() {
+        for ratio in [0.0, 0.25, 1.0] {
+            let config = TelemetryConfig::new("svc").with_sample_ratio(ratio);
+            assert!(config.validate().is_ok(), "ratio {ratio} should be valid");
+        }
+    }
+
+    #[test]
+    fn test_sample_ratio_out_of_range_rejected() {
+        for ratio in [-0.1, 1.5, f64::NAN] {
+            let config = TelemetryConfig::new("svc").with_sample_ratio(ratio);
+            assert!(
+                config.validate().is_err(),
+                "ratio {ratio} should be rejected"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_setup_telemetry_rejects_invalid_config() {
+        let config = TelemetryConfig::new("svc").with_sample_ratio(2.0);
+        assert!(setup_telemetry(&config).is_err());
+    }
+}
@@ -7,41 +7,755 @@
 //! abstracting interactions with various mobile money providers.
 
 use async_trait::async_trait;
-use psc_error::{Error, Result};
+use psc_error::{Error, ErrorBody, ProviderErrorKind, Result};
 use psc_provider::{
     pb::{
         balance::v1::{Balance, GetBalanceRequest},
         common::v1::{Id, Money, Timestamp},
-        journal::v1::{JournalEntry, PostJournalRequest},
+        journal::v1::PostJournalRequest,
         payment::v1::{CreatePaymentRequest, Payment, PaymentStatus},
         payout::v1::{CreatePayoutRequest, Payout, PayoutStatus},
+        refund::v1::{Refund, RefundStatus},
     },
-    Ctx, Provider,
+    Ctx, Provider, TransactionState, TransactionStatus,
 };
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use cuid::cuid2;
 use time;
 use std::str::FromStr;
 use rust_decimal::prelude::ToPrimitive;
-// Idempotency and Redis caching are currently disabled until types implement serde
+use psc_domain::currency_exponent;
 use nats::asynk::Connection as NatsClient; // NATS client
+use prost::Message;
+use psc_idempotency::{Cache, FailurePolicy, IdempotencyStore, Outcome, RedisCache, RedisIdempotencyStore, RedisReservations, Reservations};
+use tracing::{Instrument, Span};
+
+/// How long a published status event is remembered, so that a replayed request
+/// within this window does not publish it again.
+const EVENT_DEDUP_TTL_SECONDS: usize = 24 * 60 * 60;
+
+/// How long a status event is claimed while it is being published, so a concurrent
+/// replay does not publish it too. Outlasts [`JETSTREAM_ACK_TIMEOUT`].
+const EVENT_PUBLISH_LOCK_TTL_SECONDS: usize = 30;
+
+/// How long the refunds issued against a payment are remembered, so that later
+/// refunds cannot add up to more than the payment.
+const REFUND_RESERVATION_TTL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// How long a JetStream publish waits for the stream to acknowledge the event.
+const JETSTREAM_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Key in `PostJournalRequest::metadata` naming the payment a refund is issued against.
+pub const ORIGINAL_REFERENCE_KEY: &str = "original_reference";
+
+/// Key in `Payment::metadata` and `Payout::metadata` holding the `X-Reference-Id`
+/// MTN tracks the transaction under. The caller's own metadata is kept alongside it.
+pub const MTN_REFERENCE_ID_KEY: &str = "mtn.referenceId";
+
+/// Key in `TransactionStatus::metadata` and `Payment::metadata` holding MTN's
+/// `financialTransactionId`, once MTN has completed the transaction.
+pub const MTN_FINANCIAL_TRANSACTION_ID_KEY: &str = "mtn.financialTransactionId";
+
+/// Key in `TransactionStatus::metadata` and `Payment::metadata` holding the code of
+/// MTN's failure reason, such as `PAYER_NOT_FOUND`, which `TransactionStatus::reason`
+/// replaces with its message.
+pub const MTN_REASON_CODE_KEY: &str = "mtn.reasonCode";
+
+/// Key in `Balance::metadata` holding the available balance exactly as MTN reported it.
+pub const MTN_AVAILABLE_BALANCE_KEY: &str = "mtn.availableBalance";
+
+/// Key in `Balance::metadata` holding the currency MTN reported the balance in. It is
+/// absent when MTN omitted the currency and the configured default was assumed.
+pub const MTN_CURRENCY_KEY: &str = "mtn.currency";
+
+/// MTN MoMo environment the adapter talks to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MtnEnvironment {
+    #[default]
+    Sandbox,
+    Production,
+}
+
+impl MtnEnvironment {
+    const SANDBOX_BASE_URL: &'static str = "https://sandbox.momodeveloper.mtn.com";
+    const PRODUCTION_BASE_URL: &'static str = "https://proxy.momoapi.mtn.com";
+
+    /// Base URL of the MTN MoMo API in this environment.
+    pub fn default_base_url(self) -> &'static str {
+        match self {
+            MtnEnvironment::Sandbox => Self::SANDBOX_BASE_URL,
+            MtnEnvironment::Production => Self::PRODUCTION_BASE_URL,
+        }
+    }
+
+    /// `X-Target-Environment` header value for this environment. Production targets
+    /// MTN Cameroon unless overridden.
+    pub fn default_target_environment(self) -> &'static str {
+        match self {
+            MtnEnvironment::Sandbox => "sandbox",
+            MtnEnvironment::Production => "mtncameroon",
+        }
+    }
+}
 
 /// Configuration for the MTN Sandbox Provider.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MtnSandboxConfig {
-    pub base_url: String,
+    #[serde(default)]
+    pub environment: MtnEnvironment, // Selects the default base URL and X-Target-Environment
+    #[serde(default)]
+    pub base_url: Option<String>, // Overrides the environment's base URL
     pub api_key: String, // X-Reference-Id for MTN
-    pub target_environment: String, // X-Target-Environment
+    #[serde(default)]
+    pub target_environment: Option<String>, // Overrides the environment's X-Target-Environment
     pub webhook_secret: String, // Secret for verifying webhooks
     pub redis_url: String, // Redis URL for idempotency and caching
     pub nats_url: String, // NATS URL for event bus
-    pub cache_ttl_seconds: u64, // TTL for cached items
+    #[serde(default = "default_nats_enabled")]
+    pub nats_enabled: bool, // Publish events to NATS; disable where no event bus runs
+    #[serde(default)]
+    pub nats_subject_prefix: Option<String>, // Prepended, followed by '.', to every event subject
+    #[serde(default)]
+    pub nats_jetstream: bool, // Publish events through JetStream and wait for the stream's ack
+    pub cache_ttl_seconds: u64, // TTL for cached items (0 disables caching)
+    #[serde(default)]
+    pub webhook_verifier: WebhookVerifier, // How webhook signatures are computed and encoded
+    #[serde(default = "default_currency")]
+    pub default_currency: String, // Currency assumed when MTN or a request omits one
+    #[serde(default = "default_country_code")]
+    pub default_country_code: String, // Calling code prepended to MSISDNs given without one
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String, // User-Agent sent on every MTN API call
+}
+
+impl MtnSandboxConfig {
+    /// Base URL for MTN API calls: the override if set, else the environment's.
+    pub fn base_url(&self) -> &str {
+        self.base_url
+            .as_deref()
+            .unwrap_or(self.environment.default_base_url())
+    }
+
+    /// `X-Target-Environment` header value: the override if set, else the environment's.
+    pub fn target_environment(&self) -> &str {
+        self.target_environment
+            .as_deref()
+            .unwrap_or(self.environment.default_target_environment())
+    }
+
+    /// Subject an event named `subject` (e.g. `payments.status.update`) is published
+    /// on: `{nats_subject_prefix}.{subject}`, or `subject` alone without a prefix.
+    pub fn event_subject(&self, subject: &str) -> String {
+        match self.nats_subject_prefix.as_deref() {
+            Some(prefix) if !prefix.is_empty() => format!("{}.{}", prefix, subject),
+            _ => subject.to_string(),
+        }
+    }
+
+    /// Check that the base URL and target environment agree with `environment`.
+    ///
+    /// Returns `Error::InvalidArgument` if the base URL is not HTTP(S), or if a
+    /// sandbox config targets a production environment or the other way around.
+    pub fn validate(&self) -> Result<()> {
+        let base_url = self.base_url();
+        if !base_url.starts_with("https://") && !base_url.starts_with("http://") {
+            return Err(Error::InvalidArgument(format!(
+                "MTN base URL {} is not an HTTP(S) URL",
+                base_url
+            )));
+        }
+
+        let targets_sandbox = self.target_environment() == "sandbox";
+        match self.environment {
+            MtnEnvironment::Sandbox if !targets_sandbox => Err(Error::InvalidArgument(format!(
+                "MTN sandbox only accepts the sandbox target environment, not {}",
+                self.target_environment()
+            ))),
+            MtnEnvironment::Production if targets_sandbox => Err(Error::InvalidArgument(
+                "MTN production requires a production target environment, not sandbox".to_string(),
+            )),
+            MtnEnvironment::Production
+                if base_url.starts_with(MtnEnvironment::SANDBOX_BASE_URL) =>
+            {
+                Err(Error::InvalidArgument(format!(
+                    "MTN production cannot use the sandbox base URL {}",
+                    base_url
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn default_currency() -> String {
+    "XAF".to_string()
+}
+
+fn default_country_code() -> String {
+    "237".to_string()
+}
+
+fn default_nats_enabled() -> bool {
+    true
+}
+
+fn default_user_agent() -> String {
+    format!("psc-provider-gateway/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Longest MSISDN E.164 allows, in digits.
+const MSISDN_MAX_DIGITS: usize = 15;
+
+/// Shortest MSISDN accepted, in digits, country code included.
+const MSISDN_MIN_DIGITS: usize = 8;
+
+/// National numbers are at most this many digits, so a longer number given without
+/// a `+` or `00` prefix is taken to already include its country code.
+const MSISDN_MAX_NATIONAL_DIGITS: usize = 10;
+
+/// Normalize a phone number to the digits-only international form MTN expects,
+/// e.g. `+237 670-00-00-00` becomes `237670000000`.
+///
+/// Spaces, dashes, dots and parentheses are dropped. A number given without a `+` or
+/// `00` prefix and short enough to be national has its trunk `0` removed and
+/// `default_country_code` prepended.
+///
+/// Returns `Error::InvalidArgument` if the number has other characters or the wrong
+/// number of digits.
+pub fn normalize_msisdn(msisdn: &str, default_country_code: &str) -> Result<String> {
+    let invalid = || Error::InvalidArgument(format!("Invalid MSISDN {:?}", msisdn));
+    let compact: String = msisdn
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    let (international, digits) = match compact.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => match compact.strip_prefix("00") {
+            Some(rest) => (true, rest),
+            None => (false, compact.as_str()),
+        },
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let normalized = if international || digits.len() > MSISDN_MAX_NATIONAL_DIGITS {
+        digits.to_string()
+    } else {
+        format!("{}{}", default_country_code, digits.trim_start_matches('0'))
+    };
+    if normalized.starts_with('0')
+        || !(MSISDN_MIN_DIGITS..=MSISDN_MAX_DIGITS).contains(&normalized.len())
+    {
+        return Err(invalid());
+    }
+    Ok(normalized)
+}
+
+/// Reply of a JetStream stream that stored a published message.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JetStreamPublishAck {
+    pub stream: String,
+    pub seq: u64,
+    #[serde(default)]
+    pub duplicate: bool,
+}
+
+#[derive(Deserialize)]
+struct JetStreamErrorReply {
+    error: JetStreamApiError,
+}
+
+#[derive(Deserialize)]
+struct JetStreamApiError {
+    code: u16,
+    #[serde(default)]
+    description: String,
+}
+
+/// Parse the reply to a JetStream publish, turning an API error reply into
+/// `Error::Internal`.
+fn parse_jetstream_ack(reply: &[u8]) -> Result<JetStreamPublishAck> {
+    if let Ok(JetStreamErrorReply { error }) = serde_json::from_slice(reply) {
+        return Err(Error::Internal(format!(
+            "JetStream rejected event ({}): {}",
+            error.code, error.description
+        )));
+    }
+    serde_json::from_slice(reply)
+        .map_err(|e| Error::Internal(format!("Invalid JetStream publish ack: {}", e)))
+}
+
+/// Destination for the events the adapter emits, such as an event bus.
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    /// Publish `payload` on `subject`, which already carries any configured prefix.
+    async fn publish(&self, subject: &str, payload: Vec<u8>) -> Result<()>;
+}
+
+/// Publishes events to NATS, optionally through JetStream.
+pub struct NatsEventPublisher {
+    client: NatsClient,
+    jetstream: bool,
+}
+
+impl NatsEventPublisher {
+    /// Publish with `client`. With `jetstream` the event is sent as a request and
+    /// only counts as published once a stream acknowledges storing it.
+    pub fn new(client: NatsClient, jetstream: bool) -> Self {
+        Self { client, jetstream }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for NatsEventPublisher {
+    /// On core NATS the event is lost if nobody is subscribed. With JetStream the
+    /// event counts as published only once a stream acknowledges storing it,
+    /// giving at-least-once delivery.
+    async fn publish(&self, subject: &str, payload: Vec<u8>) -> Result<()> {
+        if !self.jetstream {
+            return self
+                .client
+                .publish(subject, payload)
+                .await
+                .map_err(|e| Error::Internal(format!("Failed to publish NATS event: {}", e)));
+        }
+
+        let reply =
+            tokio::time::timeout(JETSTREAM_ACK_TIMEOUT, self.client.request(subject, payload))
+                .await
+                .map_err(|_| Error::Timeout(format!("JetStream ack for {}", subject)))?
+                .map_err(|e| {
+                    Error::Internal(format!("Failed to publish JetStream event: {}", e))
+                })?;
+        let ack = parse_jetstream_ack(&reply.data)?;
+        tracing::debug!(
+            subject,
+            stream = %ack.stream,
+            seq = ack.seq,
+            "Event stored by JetStream"
+        );
+        Ok(())
+    }
+}
+
+/// Records published events in memory, for tests.
+#[derive(Debug, Default)]
+pub struct InMemoryEventPublisher {
+    events: std::sync::Mutex<Vec<(String, Vec<u8>)>>,
+}
+
+impl InMemoryEventPublisher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `(subject, payload)` pairs published so far, oldest first.
+    pub fn events(&self) -> Vec<(String, Vec<u8>)> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl EventPublisher for InMemoryEventPublisher {
+    async fn publish(&self, subject: &str, payload: Vec<u8>) -> Result<()> {
+        self.events
+            .lock()
+            .unwrap()
+            .push((subject.to_string(), payload));
+        Ok(())
+    }
+}
+
+/// HMAC hash function used to sign webhook payloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+/// Encoding of the signature sent in the webhook header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+/// Default for [`WebhookTimestampPolicy::tolerance_seconds`].
+pub const DEFAULT_WEBHOOK_TIMESTAMP_TOLERANCE_SECONDS: u64 = 5 * 60;
+
+/// Replay protection for providers that send a timestamp header with their webhooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookTimestampPolicy {
+    /// How far the timestamp may be from now, in either direction, in seconds.
+    pub tolerance_seconds: u64,
+    /// Whether the provider signs `{timestamp}.{payload}` instead of the payload alone.
+    pub signed: bool,
+}
+
+impl Default for WebhookTimestampPolicy {
+    fn default() -> Self {
+        Self {
+            tolerance_seconds: DEFAULT_WEBHOOK_TIMESTAMP_TOLERANCE_SECONDS,
+            signed: true,
+        }
+    }
+}
+
+/// Verifies webhook signatures. Defaults to HMAC-SHA256 with a hex-encoded signature
+/// and no timestamp check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookVerifier {
+    pub algorithm: WebhookAlgorithm,
+    pub encoding: SignatureEncoding,
+    pub timestamp: Option<WebhookTimestampPolicy>,
+}
+
+impl WebhookVerifier {
+    pub fn new(algorithm: WebhookAlgorithm, encoding: SignatureEncoding) -> Self {
+        Self {
+            algorithm,
+            encoding,
+            timestamp: None,
+        }
+    }
+
+    /// Also require a fresh timestamp header, see [`Self::verify_with_timestamp`].
+    pub fn with_timestamp_policy(mut self, policy: WebhookTimestampPolicy) -> Self {
+        self.timestamp = Some(policy);
+        self
+    }
+
+    /// Returns whether `signature` is the HMAC of `payload` under `secret`.
+    ///
+    /// A signature that doesn't decode with the configured encoding is reported as
+    /// a mismatch rather than an error. The comparison is constant-time.
+    pub fn verify(&self, secret: &[u8], payload: &[u8], signature: &str) -> Result<bool> {
+        use base64::Engine as _;
+        use base64::engine::general_purpose::STANDARD as BASE64;
+        use hmac::Hmac;
+        use sha2::{Sha256, Sha512};
+
+        let signature = match self.encoding {
+            SignatureEncoding::Hex => hex::decode(signature).ok(),
+            SignatureEncoding::Base64 => BASE64.decode(signature).ok(),
+        };
+        let Some(signature) = signature else {
+            return Ok(false);
+        };
+
+        match self.algorithm {
+            WebhookAlgorithm::Sha256 => verify_hmac::<Hmac<Sha256>>(secret, payload, &signature),
+            WebhookAlgorithm::Sha512 => verify_hmac::<Hmac<Sha512>>(secret, payload, &signature),
+        }
+    }
+
+    /// Like [`Self::verify`], but with a timestamp policy also rejects webhooks whose
+    /// `timestamp` (Unix seconds) is missing or further from now than the tolerance,
+    /// so a captured webhook cannot be replayed later. If the policy says the
+    /// timestamp is signed, the signature must cover `{timestamp}.{payload}`.
+    ///
+    /// Without a timestamp policy `timestamp` is ignored.
+    pub fn verify_with_timestamp(
+        &self,
+        secret: &[u8],
+        payload: &[u8],
+        signature: &str,
+        timestamp: Option<&str>,
+    ) -> Result<bool> {
+        let Some(policy) = self.timestamp else {
+            return self.verify(secret, payload, signature);
+        };
+        let Some(timestamp) = timestamp.map(str::trim) else {
+            return Ok(false);
+        };
+        let Ok(seconds) = timestamp.parse::<i64>() else {
+            return Ok(false);
+        };
+
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        if now.abs_diff(seconds) > policy.tolerance_seconds {
+            return Ok(false);
+        }
+
+        if policy.signed {
+            self.verify(secret, &timestamped_payload(timestamp, payload), signature)
+        } else {
+            self.verify(secret, payload, signature)
+        }
+    }
+
+    /// Sign `payload` sent at `timestamp` (Unix seconds) the way the provider would,
+    /// so that `verify_with_timestamp` accepts it. Intended for tests.
+    pub fn sign_with_timestamp(
+        &self,
+        secret: &[u8],
+        payload: &[u8],
+        timestamp: i64,
+    ) -> Result<String> {
+        match self.timestamp {
+            Some(policy) if policy.signed => self.sign(
+                secret,
+                &timestamped_payload(&timestamp.to_string(), payload),
+            ),
+            _ => self.sign(secret, payload),
+        }
+    }
+
+    /// Sign `payload` under `secret` the way the provider would, so that `verify`
+    /// accepts it. Intended for tests that need to simulate provider webhooks.
+    pub fn sign(&self, secret: &[u8], payload: &[u8]) -> Result<String> {
+        use base64::Engine as _;
+        use base64::engine::general_purpose::STANDARD as BASE64;
+        use hmac::Hmac;
+        use sha2::{Sha256, Sha512};
+
+        let signature = match self.algorithm {
+            WebhookAlgorithm::Sha256 => sign_hmac::<Hmac<Sha256>>(secret, payload)?,
+            WebhookAlgorithm::Sha512 => sign_hmac::<Hmac<Sha512>>(secret, payload)?,
+        };
+
+        Ok(match self.encoding {
+            SignatureEncoding::Hex => hex::encode(signature),
+            SignatureEncoding::Base64 => BASE64.encode(signature),
+        })
+    }
+}
+
+/// The string providers sign when they sign the timestamp along with the payload.
+fn timestamped_payload(timestamp: &str, payload: &[u8]) -> Vec<u8> {
+    let mut signed = format!("{}.", timestamp).into_bytes();
+    signed.extend_from_slice(payload);
+    signed
+}
+
+fn verify_hmac<M>(secret: &[u8], payload: &[u8], signature: &[u8]) -> Result<bool>
+where
+    M: hmac::Mac + hmac::digest::KeyInit,
+{
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(secret)
+        .map_err(|_| Error::Internal("Failed to create HMAC key".to_string()))?;
+    mac.update(payload);
+
+    Ok(mac.verify_slice(signature).is_ok())
+}
+
+fn sign_hmac<M>(secret: &[u8], payload: &[u8]) -> Result<Vec<u8>>
+where
+    M: hmac::Mac + hmac::digest::KeyInit,
+{
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(secret)
+        .map_err(|_| Error::Internal("Failed to create HMAC key".to_string()))?;
+    mac.update(payload);
+
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// Build the JSON body MTN posts to the callback URL when a request-to-pay settles.
+///
+/// Together with [`WebhookVerifier::sign`] this lets tests simulate provider webhooks.
+pub fn mtn_callback_body(
+    reference_id: &str,
+    amount: &Money,
+    payer_msisdn: &str,
+    state: TransactionState,
+) -> Vec<u8> {
+    let status = match state {
+        TransactionState::Pending => "PENDING",
+        TransactionState::Successful => "SUCCESSFUL",
+        TransactionState::Failed => "FAILED",
+    };
+    let (amount, currency) = to_provider_amount(amount);
+    let mut body = serde_json::json!({
+        "externalId": reference_id,
+        "amount": amount,
+        "currency": currency,
+        "payer": { "partyIdType": "MSISDN", "partyId": payer_msisdn },
+        "payeeNote": "Payment collection",
+        "status": status,
+    });
+    if state == TransactionState::Successful {
+        body["financialTransactionId"] = serde_json::Value::String(cuid2());
+    }
+
+    body.to_string().into_bytes()
+}
+
+/// Return the message cached under `key`, or fetch it and cache it for `ttl`.
+///
+/// Messages are stored protobuf-encoded. Cache failures are not fatal: a failed read
+/// falls through to `fetch` and a failed write is ignored.
+async fn cached_message<M, F, Fut>(
+    cache: Option<&dyn Cache>,
+    key: &str,
+    ttl: Duration,
+    fetch: F,
+) -> Result<M>
+where
+    M: Message + Default,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<M>>,
+{
+    let Some(cache) = cache else {
+        return fetch().await;
+    };
+
+    if let Ok(Some(bytes)) = cache.get(key).await
+        && let Ok(message) = M::decode(bytes.as_slice())
+    {
+        return Ok(message);
+    }
+
+    let message = fetch().await?;
+    let _ = cache.set(key, message.encode_to_vec(), ttl).await;
+    Ok(message)
+}
+
+/// Serialize a protobuf message as the base64 of its wire encoding.
+fn serialize_message<M, S>(message: &M, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    M: Message,
+    S: serde::Serializer,
+{
+    use base64::Engine as _;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+
+    serializer.serialize_str(&BASE64.encode(message.encode_to_vec()))
+}
+
+/// Deserialize a protobuf message written by [`serialize_message`].
+fn deserialize_message<'de, M, D>(deserializer: D) -> std::result::Result<M, D::Error>
+where
+    M: Message + Default,
+    D: serde::Deserializer<'de>,
+{
+    use base64::Engine as _;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use serde::de::Error as _;
+
+    let encoded = String::deserialize(deserializer)?;
+    let bytes = BASE64.decode(encoded).map_err(D::Error::custom)?;
+    M::decode(bytes.as_slice()).map_err(D::Error::custom)
+}
+
+/// A [`Payment`] that can be stored through `psc-idempotency`.
+///
+/// Serializes as the base64 of the payment's protobuf encoding, so every field
+/// round-trips, including fields added to the proto later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedPayment(pub Payment);
+
+impl Serialize for CachedPayment {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serialize_message(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CachedPayment {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        deserialize_message(deserializer).map(CachedPayment)
+    }
+}
+
+impl From<Payment> for CachedPayment {
+    fn from(payment: Payment) -> Self {
+        CachedPayment(payment)
+    }
+}
+
+impl From<CachedPayment> for Payment {
+    fn from(cached: CachedPayment) -> Self {
+        cached.0
+    }
+}
+
+/// A [`Payout`] that can be stored through `psc-idempotency`, serialized like
+/// [`CachedPayment`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedPayout(pub Payout);
+
+impl Serialize for CachedPayout {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serialize_message(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CachedPayout {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        deserialize_message(deserializer).map(CachedPayout)
+    }
+}
+
+impl From<Payout> for CachedPayout {
+    fn from(payout: Payout) -> Self {
+        CachedPayout(payout)
+    }
+}
+
+impl From<CachedPayout> for Payout {
+    fn from(cached: CachedPayout) -> Self {
+        cached.0
+    }
+}
+
+/// Await an MTN API call inside `span`, mapping its error with `map_err`.
+///
+/// The call's duration is recorded in the span's `latency_ms` field, and a warning is
+/// emitted within the span if the call fails.
+async fn traced_call<T, E, Fut>(span: Span, call: Fut, map_err: fn(E) -> Error) -> Result<T>
+where
+    Fut: Future<Output = std::result::Result<T, E>>,
+{
+    let started = Instant::now();
+    let result = call.instrument(span.clone()).await;
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+
+    result.map_err(|e| {
+        let error = map_err(e);
+        span.in_scope(|| tracing::warn!(error = %error, "MTN API call failed"));
+        error
+    })
+}
+
+/// Await `call` until `ctx`'s deadline, dropping it and returning `Error::Timeout` once
+/// the deadline passes. Without a deadline `call` is awaited to completion.
+async fn until_deadline<Fut: Future>(ctx: &Ctx, operation: &str, call: Fut) -> Result<Fut::Output> {
+    match ctx.deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, call).await.map_err(|_| {
+            Error::Timeout(format!(
+                "{operation} did not complete before the request deadline"
+            ))
+        }),
+        None => Ok(call.await),
+    }
 }
 
 /// Adapter for the MTN Sandbox environment implementing the Provider trait.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct MtnSandboxAdapter {
     config: MtnSandboxConfig,
     client: Client,
@@ -49,450 +763,2733 @@ pub struct MtnSandboxAdapter {
     disbursement_cfg: psc_mtn_disbursement::apis::configuration::Configuration,
     remittance_cfg: psc_mtn_remittance::apis::configuration::Configuration,
     sandbox_provisioning_cfg: psc_mtn_sandbox_provisioning::apis::configuration::Configuration,
-    nats_client: NatsClient,
+    events: Option<Arc<dyn EventPublisher>>, // None when `nats_enabled` is off
+    cache: Option<Arc<dyn Cache>>, // Caches query/balance responses for cache_ttl_seconds
+    published_events: Arc<RedisIdempotencyStore>, // Remembers which status events were published
+    refunds: Arc<dyn Reservations>, // Amounts refunded per original payment
+}
+
+impl fmt::Debug for MtnSandboxAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MtnSandboxAdapter")
+            .field("config", &self.config)
+            .field("client", &self.client)
+            .field("cache_enabled", &self.cache.is_some())
+            .field("events_enabled", &self.events.is_some())
+            .finish_non_exhaustive()
+    }
 }
 
 impl MtnSandboxAdapter {
-    pub async fn new(config: MtnSandboxConfig) -> Self {
-        let reqwest_client = Client::new();
+    /// Create an adapter, connecting to NATS and Redis.
+    ///
+    /// With `nats_enabled` off no NATS connection is made and events are skipped.
+    ///
+    /// Returns `Error::InvalidArgument` if `config` fails [`MtnSandboxConfig::validate`],
+    /// and `Error::Internal` if the HTTP client cannot be built, NATS is enabled but
+    /// cannot be reached or `redis_url` is invalid.
+    pub async fn new(config: MtnSandboxConfig) -> Result<Self> {
+        config.validate()?;
+
+        let reqwest_client = Client::builder()
+            .user_agent(&config.user_agent)
+            .build()
+            .map_err(|e| Error::Internal(format!("Failed to build MTN HTTP client: {}", e)))?;
         let collection_config = psc_mtn_collection::apis::configuration::Configuration {
-            base_path: config.base_url.clone(),
-            user_agent: Some("psc-provider-gateway".to_string()),
+            base_path: config.base_url().to_string(),
+            user_agent: Some(config.user_agent.clone()),
+            client: reqwest_client.clone(),
+            // No API key directly here, it's passed as header
+            ..Default::default()
+        };
+        let disbursement_config = psc_mtn_disbursement::apis::configuration::Configuration {
+            base_path: config.base_url().to_string(),
+            user_agent: Some(config.user_agent.clone()),
             client: reqwest_client.clone(),
             // No API key directly here, it's passed as header
             ..Default::default()
         };
-        let disbursement_config = psc_mtn_disbursement::apis::configuration::Configuration {
-            base_path: config.base_url.clone(),
-            user_agent: Some("psc-provider-gateway".to_string()),
-            client: reqwest_client.clone(),
-            // No API key directly here, it's passed as header
+        let remittance_config = psc_mtn_remittance::apis::configuration::Configuration {
+            base_path: config.base_url().to_string(),
+            user_agent: Some(config.user_agent.clone()),
+            client: reqwest_client.clone(),
+            // No API key directly here, it's passed as header
+            ..Default::default()
+        };
+        let sandbox_provisioning_config = psc_mtn_sandbox_provisioning::apis::configuration::Configuration {
+            base_path: config.base_url().to_string(),
+            user_agent: Some(config.user_agent.clone()),
+            client: reqwest_client.clone(),
+            ..Default::default()
+        };
+
+        let events: Option<Arc<dyn EventPublisher>> = if config.nats_enabled {
+            let nats_client = nats::asynk::connect(&config.nats_url)
+                .await
+                .map_err(|e| Error::Internal(format!("Failed to connect to NATS server: {}", e)))?;
+            Some(Arc::new(NatsEventPublisher::new(
+                nats_client,
+                config.nats_jetstream,
+            )))
+        } else {
+            tracing::info!("NATS disabled, events will not be published");
+            None
+        };
+
+        let cache: Option<Arc<dyn Cache>> = if config.cache_ttl_seconds > 0 {
+            let redis_cache = RedisCache::new(&config.redis_url)
+                .map_err(|e| Error::Internal(format!("Invalid Redis URL: {e}")))?;
+            Some(Arc::new(redis_cache))
+        } else {
+            None
+        };
+
+        // Fail open: publishing a duplicate event beats losing one while Redis is down
+        let published_events = RedisIdempotencyStore::new(&config.redis_url)
+            .map_err(|e| Error::Internal(format!("Invalid Redis URL: {e}")))?
+            .with_failure_policy(FailurePolicy::FailOpen);
+        let refunds = RedisReservations::new(&config.redis_url)
+            .map_err(|e| Error::Internal(format!("Invalid Redis URL: {e}")))?;
+
+        Ok(MtnSandboxAdapter {
+            config,
+            client: reqwest_client,
+            collection_cfg: collection_config,
+            disbursement_cfg: disbursement_config,
+            remittance_cfg: remittance_config,
+            sandbox_provisioning_cfg: sandbox_provisioning_config,
+            events,
+            cache,
+            published_events: Arc::new(published_events),
+            refunds: Arc::new(refunds),
+        })
+    }
+
+    /// Replace the cache used for query/balance responses (e.g. with an in-memory cache).
+    pub fn with_cache(mut self, cache: Arc<dyn Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Replace where refunded amounts are tracked (e.g. with [`InMemoryReservations`]).
+    ///
+    /// [`InMemoryReservations`]: psc_idempotency::InMemoryReservations
+    pub fn with_refund_reservations(mut self, refunds: Arc<dyn Reservations>) -> Self {
+        self.refunds = refunds;
+        self
+    }
+
+    /// Replace where events are published (e.g. with an [`InMemoryEventPublisher`]).
+    ///
+    /// Events are published here even when `nats_enabled` is off.
+    pub fn with_event_publisher(mut self, events: Arc<dyn EventPublisher>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Publish a status event for `reference_id` on `subject`, unless one was already
+    /// published for it.
+    ///
+    /// A request replayed with the same idempotency key after the provider call
+    /// succeeded therefore does not emit a second event. The event only counts as
+    /// published once publishing succeeds, so if it fails a replay publishes it.
+    async fn publish_once(
+        &self,
+        subject: &str,
+        reference_id: &str,
+        event: serde_json::Value,
+    ) -> Result<()> {
+        if self.events.is_none() {
+            tracing::debug!(
+                subject,
+                reference_id,
+                "Event publishing disabled, skipping status event"
+            );
+            return Ok(());
+        }
+        let key = format!(
+            "mtn:{}:event:{}:{}",
+            self.config.target_environment(),
+            self.config.event_subject(subject),
+            reference_id
+        );
+        if !self
+            .published_events
+            .begin(&key, EVENT_PUBLISH_LOCK_TTL_SECONDS)
+            .await?
+        {
+            tracing::debug!(subject, reference_id, "Status event already published");
+            return Ok(());
+        }
+        if let Err(e) = self.publish_event(subject, event).await {
+            if let Err(release_error) = self.published_events.release(&key).await {
+                tracing::warn!(
+                    error = %release_error,
+                    subject,
+                    reference_id,
+                    "Failed to release status event claim"
+                );
+            }
+            return Err(e);
+        }
+        self.published_events
+            .complete(&key, &true, EVENT_DEDUP_TTL_SECONDS)
+            .await?;
+        Ok(())
+    }
+
+    /// The correlation id for the events and spans of `reference_id`: the one taken
+    /// from `ctx`, or a freshly minted one.
+    ///
+    /// The first id used for a reference is remembered alongside the published
+    /// events, so a replayed request and any later status event for the same
+    /// reference carry it too, whatever id the later caller passes.
+    async fn correlation_id(&self, ctx: &Ctx, reference_id: &str) -> String {
+        let correlation_id = ctx.correlation_id.clone().unwrap_or_else(cuid2);
+        if self.events.is_none() {
+            return correlation_id;
+        }
+        let key = format!(
+            "mtn:{}:correlation:{}",
+            self.config.target_environment(),
+            reference_id
+        );
+        let remembered = match self
+            .published_events
+            .check_and_set(&key, &correlation_id, EVENT_DEDUP_TTL_SECONDS)
+            .await
+        {
+            Ok(true) => return correlation_id,
+            Ok(false) => self.published_events.get_result::<String>(&key).await,
+            Err(e) => Err(e),
+        };
+        match remembered {
+            Ok(Some(Outcome::Found(remembered))) => remembered,
+            Ok(_) => correlation_id,
+            Err(e) => {
+                tracing::warn!(error = %e, reference_id, "Failed to look up correlation id");
+                correlation_id
+            }
+        }
+    }
+
+    /// Publish a failure event so downstream alerting sees failed provider calls.
+    ///
+    /// Publishing is best effort: the caller returns the provider error either way.
+    async fn publish_failure(&self, subject: &str, event: serde_json::Value) {
+        if let Err(e) = self.publish_event(subject, event).await {
+            tracing::warn!(error = %e, subject, "Failed to publish failure event");
+        }
+    }
+
+    /// Publish `event` on the configured subject for `subject`.
+    async fn publish_event(&self, subject: &str, event: serde_json::Value) -> Result<()> {
+        let Some(events) = &self.events else {
+            tracing::debug!(subject, "Event publishing disabled, skipping event");
+            return Ok(());
+        };
+        events
+            .publish(
+                &self.config.event_subject(subject),
+                event.to_string().into_bytes(),
+            )
+            .await
+    }
+
+    /// Fetch the account balance from MTN, bypassing the cache.
+    async fn fetch_balance(&self, ctx: &Ctx, account_id: String) -> Result<Balance> {
+        let x_target_environment = Some(self.config.target_environment().to_string());
+        let authorization = Some(format!("Bearer {}", self.config.api_key));
+
+        let span = tracing::info_span!(
+            "mtn.get_account_balance",
+            account_id = %account_id,
+            latency_ms = tracing::field::Empty,
+        );
+        let mtn_balance = until_deadline(
+            ctx,
+            "MTN get_account_balance",
+            traced_call(
+                span,
+                psc_mtn_collection::apis::default_api::get_account_balance(
+                    &self.collection_cfg,
+                    authorization.as_deref().unwrap_or(""),
+                    x_target_environment.as_deref().unwrap_or("sandbox"),
+                ),
+                Self::map_mtn_collection_error,
+            ),
+        )
+        .await??;
+
+        mtn_balance_to_balance(
+            account_id,
+            mtn_balance.available_balance.as_deref(),
+            mtn_balance.currency.as_deref(),
+            &self.config.default_currency,
+        )
+    }
+
+    /// MTN's details of the payment `reference_id`, such as its financial transaction
+    /// id once the payer has approved it, for the payment's metadata.
+    ///
+    /// The details are only informative, so a failed lookup is logged and yields none.
+    async fn payment_details(&self, ctx: &Ctx, reference_id: &str) -> HashMap<String, String> {
+        match self.get_transaction(ctx, reference_id).await {
+            Ok(status) => status.metadata,
+            Err(error) => {
+                tracing::warn!(
+                    reference_id = %reference_id,
+                    error = %error,
+                    "Failed to look up MTN details of payment"
+                );
+                HashMap::new()
+            }
+        }
+    }
+
+    /// Fetch the amount of a successful collection, for validating refunds against it.
+    async fn fetch_original_payment(&self, ctx: &Ctx, reference: &str) -> Result<Money> {
+        let x_target_environment = Some(self.config.target_environment().to_string());
+        let authorization = Some(format!("Bearer {}", self.config.api_key));
+
+        let mtn_result = until_deadline(
+            ctx,
+            "MTN requestto_pay_transaction_status",
+            psc_mtn_collection::apis::default_api::requestto_pay_transaction_status(
+                &self.collection_cfg,
+                reference,
+                authorization.as_deref().unwrap_or(""),
+                x_target_environment.as_deref().unwrap_or("sandbox"),
+            ),
+        )
+        .await?
+        .map_err(Self::map_mtn_collection_error)?;
+
+        if map_mtn_transaction_state(mtn_result.status) != TransactionState::Successful {
+            return Err(Error::BadRequest(format!(
+                "Original payment {} has not succeeded and cannot be refunded",
+                reference
+            )));
+        }
+
+        let currency = mtn_result
+            .currency
+            .as_deref()
+            .unwrap_or(&self.config.default_currency);
+        mtn_result
+            .amount
+            .as_deref()
+            .and_then(|amount| from_provider_amount(amount, currency))
+            .ok_or_else(|| Error::Internal(format!("MTN returned no valid amount for payment {}", reference)))
+    }
+
+    /// Reserve `amount` of the `original` payment for the refund `reference_id`, so
+    /// the refunds issued against a payment never add up to more than it. A replayed
+    /// refund keeps its reservation rather than making a second one.
+    ///
+    /// Returns `Error::BadRequest` if earlier refunds leave less than `amount`.
+    async fn reserve_refund(
+        &self,
+        original_reference: &str,
+        reference_id: &str,
+        amount: &Money,
+        original: &Money,
+    ) -> Result<()> {
+        let reserved = self
+            .refunds
+            .reserve(
+                &self.refunds_key(original_reference),
+                reference_id,
+                amount.amount_minor_units,
+                original.amount_minor_units,
+                REFUND_RESERVATION_TTL,
+            )
+            .await?;
+        if !reserved {
+            return Err(Error::BadRequest(format!(
+                "Refund amount {} together with earlier refunds exceeds original payment amount {}",
+                amount.amount_minor_units, original.amount_minor_units
+            )));
+        }
+        Ok(())
+    }
+
+    /// Give back the amount reserved for the refund `reference_id`, after MTN
+    /// rejected it.
+    async fn release_refund(&self, original_reference: &str, reference_id: &str) {
+        if let Err(e) = self
+            .refunds
+            .release(&self.refunds_key(original_reference), reference_id)
+            .await
+        {
+            tracing::warn!(
+                error = %e,
+                reference_id,
+                original_reference,
+                "Failed to release refund reservation"
+            );
+        }
+    }
+
+    /// The key the refunds issued against `original_reference` are reserved under.
+    fn refunds_key(&self, original_reference: &str) -> String {
+        format!(
+            "mtn:{}:refunds:{}",
+            self.config.target_environment(),
+            original_reference
+        )
+    }
+
+    /// Helper to map MTN Collection API errors to our unified Error type.
+    fn map_mtn_collection_error<T>(e: psc_mtn_collection::apis::Error<T>) -> Error {
+        match e {
+            psc_mtn_collection::apis::Error::ResponseError(response_error) => {
+                let status_code = response_error.status.as_u16();
+                let content = response_error.content;
+
+                if status_code == 429 {
+                    return Error::RateLimited { retry_after: None };
+                }
+
+                // Try to parse MTN's ErrorReason structure
+                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
+                    let code = error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_COLLECTION_ERROR_CODE".to_string());
+                    Error::Provider {
+                        kind: classify_mtn_error(&code, status_code),
+                        code,
+                        message: error_reason.message.unwrap_or_else(|| format!("MTN Collection API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
+                    }
+                } else {
+                    // Fallback if ErrorReason cannot be parsed
+                    Error::Provider {
+                        code: format!("HTTP_{}", status_code),
+                        message: format!("MTN Collection API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
+                        kind: ProviderErrorKind::from_http_status(status_code),
+                    }
+                }
+            }
+            psc_mtn_collection::apis::Error::Reqwest(e) if e.is_timeout() => Error::Timeout(format!("MTN Collection API request timed out: {}", e)),
+            psc_mtn_collection::apis::Error::Reqwest(e) => Error::Internal(format!("MTN Collection API Reqwest error: {}", e)),
+            psc_mtn_collection::apis::Error::Serde(e) => Error::Internal(format!("MTN Collection API Serde error: {}", e)),
+            psc_mtn_collection::apis::Error::Io(e) => Error::Internal(format!("MTN Collection API IO error: {}", e)),
+        }
+    }
+
+    /// Helper to map MTN Disbursement API errors to our unified Error type.
+    fn map_mtn_disbursement_error<T>(e: psc_mtn_disbursement::apis::Error<T>) -> Error {
+        match e {
+            psc_mtn_disbursement::apis::Error::ResponseError(response_error) => {
+                let status_code = response_error.status.as_u16();
+                let content = response_error.content;
+
+                if status_code == 429 {
+                    return Error::RateLimited { retry_after: None };
+                }
+
+                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
+                    let code = error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_DISBURSEMENT_ERROR_CODE".to_string());
+                    Error::Provider {
+                        kind: classify_mtn_error(&code, status_code),
+                        code,
+                        message: error_reason.message.unwrap_or_else(|| format!("MTN Disbursement API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
+                    }
+                } else {
+                    Error::Provider {
+                        code: format!("HTTP_{}", status_code),
+                        message: format!("MTN Disbursement API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
+                        kind: ProviderErrorKind::from_http_status(status_code),
+                    }
+                }
+            }
+            psc_mtn_disbursement::apis::Error::Reqwest(e) if e.is_timeout() => Error::Timeout(format!("MTN Disbursement API request timed out: {}", e)),
+            _ => Error::Internal(format!("MTN Disbursement API error: {}", e)),
+        }
+    }
+
+    /// Helper to map MTN Remittance API errors to our unified Error type.
+    fn map_mtn_remittance_error<T>(e: psc_mtn_remittance::apis::Error<T>) -> Error {
+        match e {
+            psc_mtn_remittance::apis::Error::ResponseError(response_error) => {
+                let status_code = response_error.status.as_u16();
+                let content = response_error.content;
+
+                if status_code == 429 {
+                    return Error::RateLimited { retry_after: None };
+                }
+
+                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
+                    let code = error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_REMITTANCE_ERROR_CODE".to_string());
+                    Error::Provider {
+                        kind: classify_mtn_error(&code, status_code),
+                        code,
+                        message: error_reason.message.unwrap_or_else(|| format!("MTN Remittance API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
+                    }
+                } else {
+                    Error::Provider {
+                        code: format!("HTTP_{}", status_code),
+                        message: format!("MTN Remittance API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
+                        kind: ProviderErrorKind::from_http_status(status_code),
+                    }
+                }
+            }
+            psc_mtn_remittance::apis::Error::Reqwest(e) if e.is_timeout() => Error::Timeout(format!("MTN Remittance API request timed out: {}", e)),
+            _ => Error::Internal(format!("MTN Remittance API error: {}", e)),
+        }
+    }
+
+    /// Helper to map MTN Sandbox Provisioning API errors to our unified Error type.
+    fn map_mtn_sandbox_provisioning_error<T>(e: psc_mtn_sandbox_provisioning::apis::Error<T>) -> Error {
+        match e {
+            psc_mtn_sandbox_provisioning::apis::Error::ResponseError(response_error) => {
+                let status_code = response_error.status.as_u16();
+                let content = response_error.content;
+
+                if status_code == 429 {
+                    return Error::RateLimited { retry_after: None };
+                }
+
+                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
+                    let code = error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_SANDBOX_PROVISIONING_ERROR_CODE".to_string());
+                    Error::Provider {
+                        kind: classify_mtn_error(&code, status_code),
+                        code,
+                        message: error_reason.message.unwrap_or_else(|| format!("MTN Sandbox Provisioning API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
+                    }
+                } else {
+                    Error::Provider {
+                        code: format!("HTTP_{}", status_code),
+                        message: format!("MTN Sandbox Provisioning API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
+                        kind: ProviderErrorKind::from_http_status(status_code),
+                    }
+                }
+            }
+            _ => Error::Internal(format!("MTN Sandbox Provisioning API error: {}", e)),
+        }
+    }
+}
+
+// Struct to parse MTN's error response body
+#[derive(Debug, Deserialize)]
+struct MtnErrorReason {
+    code: Option<String>,
+    message: Option<String>,
+}
+
+/// Classify an MTN error code, falling back to the HTTP status for codes we don't know.
+///
+/// Codes describing the payer, payee or request itself are terminal; retrying them
+/// cannot succeed.
+fn classify_mtn_error(code: &str, status_code: u16) -> ProviderErrorKind {
+    match code {
+        "PAYER_NOT_FOUND"
+        | "PAYEE_NOT_FOUND"
+        | "NOT_ENOUGH_FUNDS"
+        | "PAYER_LIMIT_REACHED"
+        | "PAYEE_NOT_ALLOWED_TO_RECEIVE"
+        | "NOT_ALLOWED"
+        | "NOT_ALLOWED_TARGET_ENVIRONMENT"
+        | "INVALID_CALLBACK_URL_HOST"
+        | "INVALID_CURRENCY"
+        | "APPROVAL_REJECTED"
+        | "EXPIRED"
+        | "TRANSACTION_CANCELED"
+        | "RESOURCE_ALREADY_EXIST"
+        | "RESOURCE_NOT_FOUND" => ProviderErrorKind::Terminal,
+        "INTERNAL_PROCESSING_ERROR" | "SERVICE_UNAVAILABLE" | "COULD_NOT_PERFORM_TRANSACTION" => {
+            ProviderErrorKind::Transient
+        }
+        _ => ProviderErrorKind::from_http_status(status_code),
+    }
+}
+
+/// Whether `error` is MTN refusing a request because one with the same
+/// `X-Reference-Id` already exists.
+///
+/// The reference is the caller's idempotency key, so this is a replay of a request
+/// MTN already accepted rather than a failure.
+fn is_mtn_replay(idempotency_key: &str, error: &Error) -> bool {
+    !idempotency_key.is_empty() && error.provider_code() == Some("RESOURCE_ALREADY_EXIST")
+}
+
+/// Map an MTN transaction status (`PENDING`, `SUCCESSFUL`, `FAILED`, ...) to our state.
+///
+/// The generated clients model the status as per-API enums, so it is read back
+/// through its serialized form.
+fn map_mtn_transaction_state<S: Serialize>(status: Option<S>) -> TransactionState {
+    let status = status.and_then(|s| serde_json::to_value(s).ok());
+    match status.as_ref().and_then(|s| s.as_str()) {
+        Some("SUCCESSFUL") => TransactionState::Successful,
+        Some("FAILED") | Some("REJECTED") | Some("TIMEOUT") => TransactionState::Failed,
+        _ => TransactionState::Pending,
+    }
+}
+
+/// Convert our amount to the decimal string and currency code providers expect.
+///
+/// The string has as many fractional digits as the currency's ISO 4217 exponent, so
+/// 10050 minor units are `"100.50"` USD but `"10050"` XAF.
+fn to_provider_amount(money: &Money) -> (String, String) {
+    let amount = rust_decimal::Decimal::new(
+        money.amount_minor_units,
+        currency_exponent(&money.currency_code),
+    );
+    (amount.to_string(), money.currency_code.clone())
+}
+
+/// Parse a provider's decimal amount string into our amount in minor units, using the
+/// currency's ISO 4217 exponent. Sub-minor-unit digits are rounded half to even.
+fn from_provider_amount(amount: &str, currency: &str) -> Option<Money> {
+    let amount = rust_decimal::Decimal::from_str(amount).ok()?;
+    let scale = rust_decimal::Decimal::from(10i64.pow(currency_exponent(currency)));
+    let amount_minor_units = (amount * scale).round().to_i64()?;
+    Some(Money {
+        amount_minor_units,
+        currency_code: currency.to_string(),
+    })
+}
+
+/// Build our balance from an MTN account balance, using `default_currency` when MTN
+/// omits the currency.
+///
+/// MTN does not report reservations, so `reserved` is zero. A missing or unparseable
+/// available balance is an error rather than a zero balance.
+fn mtn_balance_to_balance(
+    account_id: String,
+    available_balance: Option<&str>,
+    currency: Option<&str>,
+    default_currency: &str,
+) -> Result<Balance> {
+    let mut metadata = HashMap::new();
+    if let Some(currency) = currency {
+        metadata.insert(MTN_CURRENCY_KEY.to_string(), currency.to_string());
+    }
+    let currency = currency.unwrap_or(default_currency).to_string();
+    let available_balance = available_balance.ok_or_else(|| Error::Provider {
+        code: "MISSING_AVAILABLE_BALANCE".to_string(),
+        message: format!(
+            "MTN returned no available balance for account {}",
+            account_id
+        ),
+        kind: ProviderErrorKind::Transient,
+    })?;
+    let money_available = from_provider_amount(available_balance, &currency).ok_or_else(|| {
+        Error::Internal(format!(
+            "MTN returned an invalid available balance {:?} for account {}",
+            available_balance, account_id
+        ))
+    })?;
+    metadata.insert(
+        MTN_AVAILABLE_BALANCE_KEY.to_string(),
+        available_balance.to_string(),
+    );
+    Ok(Balance {
+        account_id: Some(Id { value: account_id }),
+        available: Some(money_available.clone()),
+        reserved: Some(Money {
+            amount_minor_units: 0,
+            currency_code: currency,
+        }),
+        ledger: Some(money_available),
+        as_of: Some(Timestamp {
+            value: Some(prost_types::Timestamp {
+                seconds: time::OffsetDateTime::now_utc().unix_timestamp(),
+                nanos: 0,
+            }),
+        }),
+        metadata,
+    })
+}
+
+/// Check that `refund` can be issued against the `original` payment.
+///
+/// Returns `Error::BadRequest` if the currencies differ or the refund is not positive
+/// or exceeds the original amount.
+fn validate_refund(refund: &Money, original: &Money) -> Result<()> {
+    if refund.currency_code != original.currency_code {
+        return Err(Error::BadRequest(format!(
+            "Refund currency {} does not match original payment currency {}",
+            refund.currency_code, original.currency_code
+        )));
+    }
+    if refund.amount_minor_units <= 0 {
+        return Err(Error::BadRequest(
+            "Refund amount must be positive".to_string(),
+        ));
+    }
+    if refund.amount_minor_units > original.amount_minor_units {
+        return Err(Error::BadRequest(format!(
+            "Refund amount {} exceeds original payment amount {}",
+            refund.amount_minor_units, original.amount_minor_units
+        )));
+    }
+    Ok(())
+}
+
+/// Build the result of a refund MTN has accepted for processing.
+///
+/// MTN tracks the refund by `reference_id`, its `X-Reference-Id`, so that is the
+/// provider reference used to poll for its final status.
+fn pending_refund(
+    reference_id: String,
+    amount: Money,
+    original_reference: Option<String>,
+    metadata: HashMap<String, String>,
+) -> Refund {
+    Refund {
+        id: Some(Id { value: cuid2() }),
+        amount: Some(amount),
+        status: RefundStatus::Pending as i32,
+        provider_reference: reference_id,
+        original_reference: original_reference.unwrap_or_default(),
+        created_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
+        metadata,
+    }
+}
+
+/// Build the event published on `payments.status.failed` / `payouts.status.failed`
+/// when MTN rejects a request.
+fn failure_event(
+    transaction_type: &str,
+    reference_id: &str,
+    correlation_id: &str,
+    amount: &str,
+    currency: &str,
+    error: &Error,
+) -> serde_json::Value {
+    serde_json::json!({
+        "transaction_type": transaction_type,
+        "reference_id": reference_id,
+        "correlation_id": correlation_id,
+        "status": "failed",
+        "provider": "MTN_SANDBOX",
+        "amount": amount,
+        "currency": currency,
+        "error": ErrorBody::from(error),
+    })
+}
+
+/// The caller's metadata for a payment or payout, plus the MTN reference it was sent
+/// under.
+fn mtn_metadata(metadata: &HashMap<String, String>, reference_id: &str) -> HashMap<String, String> {
+    let mut metadata = metadata.clone();
+    metadata.insert(MTN_REFERENCE_ID_KEY.to_string(), reference_id.to_string());
+    metadata
+}
+
+/// Metadata for the status of an MTN transaction: its financial transaction id and
+/// the code of its failure reason, when MTN reports them.
+fn mtn_transaction_metadata<R: Serialize>(
+    financial_transaction_id: Option<&str>,
+    reason: Option<R>,
+) -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+    if let Some(financial_transaction_id) = financial_transaction_id {
+        metadata.insert(
+            MTN_FINANCIAL_TRANSACTION_ID_KEY.to_string(),
+            financial_transaction_id.to_string(),
+        );
+    }
+    let reason_code = reason
+        .and_then(|reason| serde_json::to_value(reason).ok())
+        .and_then(|reason| serde_json::from_value::<MtnErrorReason>(reason).ok())
+        .and_then(|reason| reason.code);
+    if let Some(reason_code) = reason_code {
+        metadata.insert(MTN_REASON_CODE_KEY.to_string(), reason_code);
+    }
+    metadata
+}
+
+/// Extract a human-readable failure reason from an MTN `ErrorReason`.
+fn map_mtn_transaction_reason<R: Serialize>(reason: Option<R>) -> Option<String> {
+    let reason = serde_json::to_value(reason?).ok()?;
+    let reason = serde_json::from_value::<MtnErrorReason>(reason).ok()?;
+    reason.message.or(reason.code)
+}
+
+#[async_trait]
+impl Provider for MtnSandboxAdapter {
+    async fn deposit(&self, ctx: &Ctx, req: CreatePaymentRequest) -> Result<Payment> {
+        // Map unified request to MTN RequestToPay
+        let reference_id = if req.idempotency_key.is_empty() {
+            cuid2()
+        } else {
+            req.idempotency_key.clone()
+        };
+        let amount = req.amount.clone().unwrap_or_else(|| Money {
+            amount_minor_units: 0,
+            currency_code: self.config.default_currency.clone(),
+        });
+        let payer_msisdn = match req.payer_id.as_ref() {
+            Some(payer) => normalize_msisdn(&payer.value, &self.config.default_country_code)?,
+            None => return Err(Error::InvalidArgument("payer_id is required".to_string())),
+        };
+
+        // Convert minor units to the decimal string MTN expects
+        let (amount_str, currency_code) = to_provider_amount(&amount);
+
+        // Map to MTN model
+        let mtn_request_to_pay = psc_mtn_collection::models::RequestToPay {
+            amount: Some(amount_str.clone()),
+            currency: Some(currency_code.clone()),
+            external_id: Some(reference_id.clone()),
+            payer: Some(Box::new(psc_mtn_collection::models::Party { party_id_type: Some(psc_mtn_collection::models::party::PartyIdType::Msisdn), party_id: Some(payer_msisdn.clone()) })),
+            payer_message: None,
+            payee_note: Some("Payment collection".to_string()),
+        };
+
+        let x_target_environment = Some(self.config.target_environment().to_string());
+        let authorization = Some(format!("Bearer {}", self.config.api_key)); // Assuming API key is directly the bearer token
+        let x_callback_url: Option<&str> = None;
+
+        let correlation_id = self.correlation_id(ctx, &reference_id).await;
+        let span = tracing::info_span!(
+            "mtn.requestto_pay",
+            reference_id = %reference_id,
+            correlation_id = %correlation_id,
+            amount = %amount_str,
+            currency = %currency_code,
+            latency_ms = tracing::field::Empty,
+        );
+        // Past the deadline the outcome is unknown, so no failure event is published
+        let result = until_deadline(
+            ctx,
+            "MTN requestto_pay",
+            traced_call(
+                span,
+                psc_mtn_collection::apis::default_api::requestto_pay(
+                    &self.collection_cfg,
+                    authorization.as_deref().unwrap_or(""),
+                    &reference_id,
+                    x_target_environment.as_deref().unwrap_or("sandbox"),
+                    x_callback_url.as_deref(),
+                    Some(mtn_request_to_pay),
+                ),
+                Self::map_mtn_collection_error,
+            ),
+        )
+        .await?;
+        let result = match result {
+            Err(error) if is_mtn_replay(&req.idempotency_key, &error) => {
+                tracing::info!(
+                    reference_id = %reference_id,
+                    "MTN already has this payment, treating the request as a replay"
+                );
+                Ok(())
+            }
+            result => result,
+        };
+        if let Err(error) = &result {
+            let event = failure_event(
+                "deposit",
+                &reference_id,
+                &correlation_id,
+                &amount_str,
+                &currency_code,
+                error,
+            );
+            self.publish_failure("payments.status.failed", event).await;
+        }
+        result?;
+
+        let mut metadata = mtn_metadata(&req.metadata, &reference_id);
+        metadata.extend(self.payment_details(ctx, &reference_id).await);
+
+        // Return PENDING; webhook updates later
+        let payment = Payment {
+            id: Some(Id { value: cuid2() }),
+            amount: Some(amount),
+            status: PaymentStatus::Pending as i32,
+            created_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
+            updated_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
+            metadata,
+            reference: reference_id.clone(),
+        };
+
+        // Publish event to NATS
+        let event_payload = serde_json::json!({
+            "transaction_type": "deposit",
+            "reference_id": reference_id,
+            "correlation_id": correlation_id,
+            "status": "pending",
+            "provider": "MTN_SANDBOX",
+            "payer": payer_msisdn,
+            "amount": amount_str,
+            "currency": currency_code,
+        });
+        self.publish_once("payments.status.update", &reference_id, event_payload).await?;
+
+        Ok(payment)
+    }
+
+    async fn withdraw(&self, ctx: &Ctx, req: CreatePayoutRequest) -> Result<Payout> {
+        let reference_id = if req.idempotency_key.is_empty() {
+            cuid2()
+        } else {
+            req.idempotency_key.clone()
+        };
+        let amount = req.amount.clone().unwrap_or_else(|| Money {
+            amount_minor_units: 0,
+            currency_code: self.config.default_currency.clone(),
+        });
+        let recipient_msisdn = match req.recipient_id.as_ref() {
+            Some(recipient) => {
+                normalize_msisdn(&recipient.value, &self.config.default_country_code)?
+            }
+            None => return Err(Error::InvalidArgument("recipient_id is required".to_string())),
+        };
+
+        let (amount_str, currency_code) = to_provider_amount(&amount);
+
+        let mtn_disbursement_request = psc_mtn_disbursement::models::Transfer {
+            amount: Some(amount_str.clone()),
+            currency: Some(currency_code.clone()),
+            external_id: Some(reference_id.clone()),
+            payee: Some(Box::new(psc_mtn_disbursement::models::Party { party_id_type: Some(psc_mtn_disbursement::models::party::PartyIdType::Msisdn), party_id: Some(recipient_msisdn.clone()) })),
+            payer_message: None,
+            payee_note: Some("Payment disbursement".to_string()),
+        };
+
+        let x_target_environment = Some(self.config.target_environment().to_string());
+        let authorization = Some(format!("Bearer {}", self.config.api_key));
+        let x_callback_url: Option<&str> = None;
+
+        let correlation_id = self.correlation_id(ctx, &reference_id).await;
+        let span = tracing::info_span!(
+            "mtn.disbursement.transfer",
+            reference_id = %reference_id,
+            correlation_id = %correlation_id,
+            amount = %amount_str,
+            currency = %currency_code,
+            latency_ms = tracing::field::Empty,
+        );
+        // Past the deadline the outcome is unknown, so no failure event is published
+        let result = until_deadline(
+            ctx,
+            "MTN disbursement transfer",
+            traced_call(
+                span,
+                psc_mtn_disbursement::apis::default_api::transfer(
+                    &self.disbursement_cfg,
+                    authorization.as_deref().unwrap_or(""),
+                    &reference_id,
+                    x_target_environment.as_deref().unwrap_or("sandbox"),
+                    x_callback_url.as_deref(),
+                    Some(mtn_disbursement_request),
+                ),
+                Self::map_mtn_disbursement_error,
+            ),
+        )
+        .await?;
+        let result = match result {
+            Err(error) if is_mtn_replay(&req.idempotency_key, &error) => {
+                tracing::info!(
+                    reference_id = %reference_id,
+                    "MTN already has this payout, treating the request as a replay"
+                );
+                Ok(())
+            }
+            result => result,
+        };
+        if let Err(error) = &result {
+            let event = failure_event(
+                "withdraw",
+                &reference_id,
+                &correlation_id,
+                &amount_str,
+                &currency_code,
+                error,
+            );
+            self.publish_failure("payouts.status.failed", event).await;
+        }
+        result?;
+
+        let payout = Payout {
+            id: Some(Id { value: cuid2() }),
+            amount: Some(amount),
+            status: PayoutStatus::Pending as i32,
+            created_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
+            updated_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
+            external_reference: reference_id.clone(),
+            metadata: mtn_metadata(&req.metadata, &reference_id),
+        };
+
+        // Publish event to NATS
+        let event_payload = serde_json::json!({
+            "transaction_type": "withdraw",
+            "reference_id": reference_id,
+            "correlation_id": correlation_id,
+            "status": "pending",
+            "provider": "MTN_SANDBOX",
+            "recipient": recipient_msisdn,
+            "amount": amount_str,
+            "currency": currency_code,
+        });
+        self.publish_once("payouts.status.update", &reference_id, event_payload).await?;
+
+        Ok(payout)
+    }
+
+    async fn refund(&self, ctx: &Ctx, req: PostJournalRequest) -> Result<Refund> {
+        let reference_id = if req.idempotency_key.is_empty() {
+            cuid2()
+        } else {
+            req.idempotency_key.clone()
+        };
+
+        let first = req.entries.get(0);
+        let (amount, account) = match first.and_then(|e| e.amount.as_ref()) {
+            Some(m) => (m.clone(), first.unwrap().account.clone()),
+            None => (
+                Money {
+                    amount_minor_units: 0,
+                    currency_code: self.config.default_currency.clone(),
+                },
+                first.map(|e| e.account.clone()).unwrap_or_default(),
+            ),
+        };
+        let account = normalize_msisdn(&account, &self.config.default_country_code)?;
+
+        // Partial refunds, together, may not exceed the payment they are issued against
+        let original_reference = req
+            .metadata
+            .get(ORIGINAL_REFERENCE_KEY)
+            .filter(|r| !r.is_empty())
+            .cloned();
+        if let Some(original_reference) = &original_reference {
+            let original = self.fetch_original_payment(ctx, original_reference).await?;
+            validate_refund(&amount, &original)?;
+            self.reserve_refund(original_reference, &reference_id, &amount, &original)
+                .await?;
+        }
+
+        let (amount_str, currency_code) = to_provider_amount(&amount);
+
+        let mtn_remittance_request = psc_mtn_remittance::models::Transfer {
+            amount: Some(amount_str.clone()),
+            currency: Some(currency_code.clone()),
+            external_id: Some(reference_id.clone()),
+            payee: Some(Box::new(psc_mtn_remittance::models::Party { party_id_type: Some(psc_mtn_remittance::models::party::PartyIdType::Msisdn), party_id: Some(account.clone()) })),
+            payer_message: None,
+            payee_note: Some("Payment refund/remittance".to_string()),
+        };
+
+        let x_target_environment = Some(self.config.target_environment().to_string());
+        let authorization = Some(format!("Bearer {}", self.config.api_key));
+        let x_callback_url: Option<&str> = None;
+
+        let correlation_id = self.correlation_id(ctx, &reference_id).await;
+        let span = tracing::info_span!(
+            "mtn.remittance.transfer",
+            reference_id = %reference_id,
+            correlation_id = %correlation_id,
+            amount = %amount_str,
+            currency = %currency_code,
+            latency_ms = tracing::field::Empty,
+        );
+        let result = until_deadline(
+            ctx,
+            "MTN remittance transfer",
+            traced_call(
+                span,
+                psc_mtn_remittance::apis::default_api::transfer(
+                    &self.remittance_cfg,
+                    authorization.as_deref().unwrap_or(""),
+                    &reference_id,
+                    x_target_environment.as_deref().unwrap_or("sandbox"),
+                    x_callback_url.as_deref(),
+                    Some(mtn_remittance_request),
+                ),
+                Self::map_mtn_remittance_error,
+            ),
+        )
+        .await?;
+        match result {
+            Err(error) if is_mtn_replay(&req.idempotency_key, &error) => {
+                tracing::info!(
+                    reference_id = %reference_id,
+                    "MTN already has this refund, treating the request as a replay"
+                );
+            }
+            Err(error) => {
+                // Only an answer from MTN shows the refund was not made; after a
+                // timeout it may still go through, so its amount stays reserved.
+                if let Some(original_reference) = &original_reference {
+                    if matches!(error, Error::Provider { .. } | Error::RateLimited { .. }) {
+                        self.release_refund(original_reference, &reference_id).await;
+                    }
+                }
+                return Err(error);
+            }
+            Ok(_) => {}
+        }
+
+        let mut metadata = first.map(|e| e.metadata.clone()).unwrap_or_default();
+        if let Some(original_reference) = &original_reference {
+            metadata.insert(ORIGINAL_REFERENCE_KEY.to_string(), original_reference.clone());
+        }
+
+        // Publish event to NATS
+        let event_payload = serde_json::json!({
+            "transaction_type": "refund",
+            "reference_id": reference_id,
+            "correlation_id": correlation_id,
+            "original_reference": original_reference,
+            "status": "pending",
+            "provider": "MTN_SANDBOX",
+            "recipient": account,
+            "amount": amount_str,
+            "currency": currency_code,
+        });
+        self.publish_once("refunds.status.update", &reference_id, event_payload).await?;
+
+        Ok(pending_refund(
+            reference_id,
+            amount,
+            original_reference,
+            metadata,
+        ))
+    }
+
+    async fn query(&self, ctx: &Ctx, req: GetBalanceRequest) -> Result<Balance> {
+        let account_id = req
+            .account_id
+            .as_ref()
+            .map(|i| i.value.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let cache_key = format!("mtn:{}:balance:{}", self.config.target_environment(), account_id);
+        cached_message(
+            self.cache.as_deref(),
+            &cache_key,
+            Duration::from_secs(self.config.cache_ttl_seconds),
+            || self.fetch_balance(ctx, account_id),
+        )
+        .await
+    }
+
+    async fn get_transaction(&self, ctx: &Ctx, reference: &str) -> Result<TransactionStatus> {
+        let x_target_environment = Some(self.config.target_environment().to_string());
+        let authorization = Some(format!("Bearer {}", self.config.api_key));
+
+        let result = until_deadline(
+            ctx,
+            "MTN requestto_pay_transaction_status",
+            psc_mtn_collection::apis::default_api::requestto_pay_transaction_status(
+                &self.collection_cfg,
+                reference,
+                authorization.as_deref().unwrap_or(""),
+                x_target_environment.as_deref().unwrap_or("sandbox"),
+            ),
+        )
+        .await?;
+
+        match result {
+            Ok(mtn_result) => Ok(TransactionStatus {
+                reference: reference.to_string(),
+                state: map_mtn_transaction_state(mtn_result.status),
+                metadata: mtn_transaction_metadata(
+                    mtn_result.financial_transaction_id.as_deref(),
+                    mtn_result.reason.as_ref(),
+                ),
+                provider_transaction_id: mtn_result.financial_transaction_id,
+                reason: map_mtn_transaction_reason(mtn_result.reason),
+            }),
+            // Unknown to Collection: the reference may belong to a payout
+            Err(psc_mtn_collection::apis::Error::ResponseError(ref response_error))
+                if response_error.status.as_u16() == 404 =>
+            {
+                let mtn_result = until_deadline(
+                    ctx,
+                    "MTN get_transfer_status",
+                    psc_mtn_disbursement::apis::default_api::get_transfer_status(
+                        &self.disbursement_cfg,
+                        reference,
+                        authorization.as_deref().unwrap_or(""),
+                        x_target_environment.as_deref().unwrap_or("sandbox"),
+                    ),
+                )
+                .await?
+                .map_err(Self::map_mtn_disbursement_error)?;
+
+                Ok(TransactionStatus {
+                    reference: reference.to_string(),
+                    state: map_mtn_transaction_state(mtn_result.status),
+                    metadata: mtn_transaction_metadata(
+                        mtn_result.financial_transaction_id.as_deref(),
+                        mtn_result.reason.as_ref(),
+                    ),
+                    provider_transaction_id: mtn_result.financial_transaction_id,
+                    reason: map_mtn_transaction_reason(mtn_result.reason),
+                })
+            }
+            Err(e) => Err(Self::map_mtn_collection_error(e)),
+        }
+    }
+
+    async fn verify_webhook(
+        &self,
+        _ctx: &Ctx,
+        payload: &[u8],
+        signature_header: Option<&str>,
+        timestamp_header: Option<&str>,
+    ) -> Result<bool> {
+        let expected_signature = match signature_header {
+            Some(s) => s,
+            None => return Ok(false), // No signature header, cannot verify
+        };
+
+        self.config.webhook_verifier.verify_with_timestamp(
+            self.config.webhook_secret.as_bytes(),
+            payload,
+            expected_signature,
+            timestamp_header,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use sha2::{Sha256, Sha512};
+
+    const SECRET: &[u8] = b"webhook-secret";
+    const PAYLOAD: &[u8] = br#"{"externalId":"123","status":"SUCCESSFUL"}"#;
+
+    fn sign<M: Mac + hmac::digest::KeyInit>() -> Vec<u8> {
+        let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(SECRET).unwrap();
+        mac.update(PAYLOAD);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn balance(amount_minor_units: i64) -> Balance {
+        Balance {
+            available: Some(Money {
+                amount_minor_units,
+                currency_code: "XAF".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_second_query_within_ttl_is_served_from_cache() {
+        use psc_idempotency::InMemoryCache;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = InMemoryCache::new();
+        let provider_calls = AtomicUsize::new(0);
+        let fetch = || async {
+            provider_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(balance(5_000))
+        };
+
+        let first = cached_message(Some(&cache), "balance:acc", Duration::from_secs(60), fetch)
+            .await
+            .unwrap();
+        let second = cached_message(Some(&cache), "balance:acc", Duration::from_secs(60), fetch)
+            .await
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(provider_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_without_cache_always_hits_provider() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let provider_calls = AtomicUsize::new(0);
+        let fetch = || async {
+            provider_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(balance(5_000))
+        };
+
+        cached_message(None, "balance:acc", Duration::from_secs(60), fetch)
+            .await
+            .unwrap();
+        cached_message(None, "balance:acc", Duration::from_secs(60), fetch)
+            .await
+            .unwrap();
+
+        assert_eq!(provider_calls.load(Ordering::SeqCst), 2);
+    }
+
+    /// Records the fields of every span and event, as `name: field=value ...`.
+    #[derive(Clone, Default)]
+    struct RecordingLayer(Arc<std::sync::Mutex<Vec<String>>>);
+
+    struct FieldRecorder<'a>(&'a mut String);
+
+    impl tracing::field::Visit for FieldRecorder<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for RecordingLayer {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut line = format!("{}:", attrs.metadata().name());
+            attrs.record(&mut FieldRecorder(&mut line));
+            self.0.lock().unwrap().push(line);
+        }
+
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut line = format!("{}:", event.metadata().level());
+            event.record(&mut FieldRecorder(&mut line));
+            self.0.lock().unwrap().push(line);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_traced_call_records_span_and_warns_on_error() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let layer = RecordingLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+
+        let span = tracing::info_span!(
+            "mtn.requestto_pay",
+            reference_id = %"ref-123",
+            amount = %"10.00",
+            currency = %"XAF",
+            latency_ms = tracing::field::Empty,
+        );
+        let result: Result<()> = traced_call(span, async { Err("boom") }, |e: &str| {
+            Error::Timeout(e.to_string())
+        })
+        .await;
+        assert!(matches!(result, Err(Error::Timeout(_))));
+
+        let lines = layer.0.lock().unwrap();
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("mtn.requestto_pay:") && l.contains("reference_id=ref-123"))
+        );
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("WARN:") && l.contains("MTN API call failed"))
+        );
+    }
+
+    #[test]
+    fn test_map_mtn_transaction_state() {
+        assert_eq!(
+            map_mtn_transaction_state(Some("SUCCESSFUL")),
+            TransactionState::Successful
+        );
+        assert_eq!(
+            map_mtn_transaction_state(Some("FAILED")),
+            TransactionState::Failed
+        );
+        assert_eq!(
+            map_mtn_transaction_state(Some("PENDING")),
+            TransactionState::Pending
+        );
+        assert_eq!(
+            map_mtn_transaction_state(None::<&str>),
+            TransactionState::Pending
+        );
+    }
+
+    #[test]
+    fn test_map_mtn_transaction_reason() {
+        let reason = serde_json::json!({ "code": "PAYER_NOT_FOUND", "message": "Payer not found" });
+        assert_eq!(
+            map_mtn_transaction_reason(Some(reason)),
+            Some("Payer not found".to_string())
+        );
+
+        let reason = serde_json::json!({ "code": "PAYER_NOT_FOUND" });
+        assert_eq!(
+            map_mtn_transaction_reason(Some(reason)),
+            Some("PAYER_NOT_FOUND".to_string())
+        );
+        assert_eq!(map_mtn_transaction_reason(None::<serde_json::Value>), None);
+    }
+
+    fn xaf(amount_minor_units: i64) -> Money {
+        Money {
+            amount_minor_units,
+            currency_code: "XAF".to_string(),
+        }
+    }
+
+    fn msisdn() -> Option<Id> {
+        Some(Id {
+            value: "237670000000".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_normalize_msisdn_accepts_international_number() {
+        assert_eq!(
+            normalize_msisdn("+237 670-00-00-00", "237").unwrap(),
+            "237670000000"
+        );
+        assert_eq!(
+            normalize_msisdn("00237670000000", "237").unwrap(),
+            "237670000000"
+        );
+        assert_eq!(
+            normalize_msisdn("46733123450", "237").unwrap(),
+            "46733123450"
+        );
+    }
+
+    #[test]
+    fn test_normalize_msisdn_adds_missing_country_code() {
+        assert_eq!(
+            normalize_msisdn("670000000", "237").unwrap(),
+            "237670000000"
+        );
+        assert_eq!(
+            normalize_msisdn("0772 123456", "256").unwrap(),
+            "256772123456"
+        );
+    }
+
+    #[test]
+    fn test_normalize_msisdn_rejects_malformed_numbers() {
+        for msisdn in [
+            "",
+            "unknown",
+            "+237 67O000000",
+            "+1234",
+            "+1234567890123456",
+            "+0670000000",
+        ] {
+            assert!(
+                matches!(
+                    normalize_msisdn(msisdn, "237"),
+                    Err(Error::InvalidArgument(_))
+                ),
+                "{msisdn:?} was accepted"
+            );
+        }
+    }
+
+    fn money(amount_minor_units: i64, currency_code: &str) -> Money {
+        Money {
+            amount_minor_units,
+            currency_code: currency_code.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_to_provider_amount_uses_currency_exponent() {
+        assert_eq!(
+            to_provider_amount(&money(10050, "USD")),
+            ("100.50".to_string(), "USD".to_string())
+        );
+        assert_eq!(
+            to_provider_amount(&money(10050, "XAF")),
+            ("10050".to_string(), "XAF".to_string())
+        );
+        assert_eq!(to_provider_amount(&money(1050, "BHD")).0, "1.050");
+        assert_eq!(to_provider_amount(&money(-5, "USD")).0, "-0.05");
+    }
+
+    #[test]
+    fn test_from_provider_amount_uses_currency_exponent() {
+        assert_eq!(
+            from_provider_amount("100", "USD"),
+            Some(money(10000, "USD"))
+        );
+        assert_eq!(
+            from_provider_amount("12.34", "USD"),
+            Some(money(1234, "USD"))
+        );
+        assert_eq!(
+            from_provider_amount("10050", "XAF"),
+            Some(money(10050, "XAF"))
+        );
+        assert_eq!(
+            from_provider_amount("1.05", "BHD"),
+            Some(money(1050, "BHD"))
+        );
+        assert_eq!(from_provider_amount("not-a-number", "USD"), None);
+    }
+
+    #[test]
+    fn test_provider_amount_round_trips() {
+        for amount in [
+            money(0, "XAF"),
+            money(123456, "XAF"),
+            money(99, "USD"),
+            money(1, "BHD"),
+        ] {
+            let (value, currency) = to_provider_amount(&amount);
+            assert_eq!(from_provider_amount(&value, &currency), Some(amount));
+        }
+    }
+
+    #[test]
+    fn test_validate_partial_refund() {
+        assert!(validate_refund(&xaf(2500), &xaf(10000)).is_ok());
+        assert!(validate_refund(&xaf(10000), &xaf(10000)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_refund_rejects_over_refund() {
+        assert!(matches!(
+            validate_refund(&xaf(10001), &xaf(10000)),
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_refund_rejects_currency_mismatch_and_non_positive() {
+        let usd = Money {
+            amount_minor_units: 100,
+            currency_code: "USD".to_string(),
+        };
+        assert!(matches!(
+            validate_refund(&usd, &xaf(10000)),
+            Err(Error::BadRequest(_))
+        ));
+        assert!(matches!(
+            validate_refund(&xaf(0), &xaf(10000)),
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_balance_uses_currency_reported_by_mtn() {
+        let balance =
+            mtn_balance_to_balance("acc-usd".to_string(), Some("12.34"), Some("USD"), "XAF")
+                .unwrap();
+
+        let available = balance.available.unwrap();
+        assert_eq!(available.amount_minor_units, 1234);
+        assert_eq!(available.currency_code, "USD");
+        assert_eq!(balance.reserved.unwrap().currency_code, "USD");
+        assert_eq!(balance.metadata[MTN_AVAILABLE_BALANCE_KEY], "12.34");
+        assert_eq!(balance.metadata[MTN_CURRENCY_KEY], "USD");
+    }
+
+    #[test]
+    fn test_balance_falls_back_to_configured_currency() {
+        let balance = mtn_balance_to_balance("acc".to_string(), Some("5.00"), None, "USD").unwrap();
+
+        assert_eq!(balance.available.unwrap().currency_code, "USD");
+        assert!(!balance.metadata.contains_key(MTN_CURRENCY_KEY));
+    }
+
+    #[test]
+    fn test_missing_available_balance_is_an_error() {
+        let result = mtn_balance_to_balance("acc".to_string(), None, Some("USD"), "XAF");
+
+        assert!(matches!(
+            result,
+            Err(Error::Provider { ref code, .. }) if code == "MISSING_AVAILABLE_BALANCE"
+        ));
+    }
+
+    #[test]
+    fn test_failure_event_carries_error_and_reference() {
+        let error = Error::Provider {
+            code: "NOT_ENOUGH_FUNDS".to_string(),
+            message: "The payer does not have enough funds".to_string(),
+            kind: ProviderErrorKind::Terminal,
+        };
+
+        let event = failure_event("deposit", "ref-123", "corr-123", "100.00", "XAF", &error);
+
+        assert_eq!(event["reference_id"], "ref-123");
+        assert_eq!(event["correlation_id"], "corr-123");
+        assert_eq!(event["status"], "failed");
+        assert_eq!(event["error"]["code"], "PROVIDER_ERROR");
+        assert_eq!(event["error"]["provider_code"], "NOT_ENOUGH_FUNDS");
+    }
+
+    fn timestamp(seconds: i64) -> Timestamp {
+        Timestamp {
+            value: Some(prost_types::Timestamp { seconds, nanos: 0 }),
+        }
+    }
+
+    #[test]
+    fn test_cached_payment_round_trips_through_json() {
+        let payment = Payment {
+            id: Some(Id {
+                value: "pay-123".to_string(),
+            }),
+            amount: Some(xaf(10000)),
+            status: PaymentStatus::Pending as i32,
+            created_at: Some(timestamp(1_700_000_000)),
+            updated_at: Some(timestamp(1_700_000_060)),
+            metadata: [("order".to_string(), "42".to_string())].into(),
+            reference: "ref-123".to_string(),
+        };
+
+        let json = serde_json::to_string(&CachedPayment::from(payment.clone())).unwrap();
+        let cached: CachedPayment = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(Payment::from(cached), payment);
+    }
+
+    #[test]
+    fn test_cached_payout_round_trips_through_json() {
+        let payout = Payout {
+            id: Some(Id {
+                value: "payout-123".to_string(),
+            }),
+            amount: Some(xaf(2500)),
+            status: PayoutStatus::Pending as i32,
+            created_at: Some(timestamp(1_700_000_000)),
+            updated_at: Some(timestamp(1_700_000_060)),
+            external_reference: "ref-456".to_string(),
+            metadata: [("batch".to_string(), "7".to_string())].into(),
+        };
+
+        let json = serde_json::to_string(&CachedPayout::from(payout.clone())).unwrap();
+        let cached: CachedPayout = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(Payout::from(cached), payout);
+    }
+
+    #[test]
+    fn test_cached_payment_rejects_corrupt_value() {
+        assert!(serde_json::from_str::<CachedPayment>(r#""not base64!""#).is_err());
+    }
+
+    #[test]
+    fn test_pending_refund_carries_mtn_reference() {
+        let refund = pending_refund(
+            "ref-refund".to_string(),
+            xaf(2500),
+            Some("ref-payment".to_string()),
+            Default::default(),
+        );
+
+        assert_eq!(refund.provider_reference, "ref-refund");
+        assert_eq!(refund.original_reference, "ref-payment");
+        assert_eq!(refund.status(), RefundStatus::Pending);
+        assert_eq!(refund.amount, Some(xaf(2500)));
+    }
+
+    /// Answer every request on a local port with the same MTN-style response,
+    /// returning its base URL.
+    async fn mtn_responding(status: u16, body: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 8192];
+                let _ = socket.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        base_url
+    }
+
+    /// Adapter configuration for tests needing NATS and Redis on localhost.
+    fn local_config(base_url: String) -> MtnSandboxConfig {
+        MtnSandboxConfig {
+            environment: MtnEnvironment::Sandbox,
+            base_url: Some(base_url),
+            api_key: "api-key".to_string(),
+            target_environment: None,
+            webhook_secret: "secret".to_string(),
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            nats_url: "nats://127.0.0.1:4222".to_string(),
+            nats_enabled: true,
+            nats_subject_prefix: None,
+            nats_jetstream: false,
+            cache_ttl_seconds: 0,
+            webhook_verifier: WebhookVerifier::default(),
+            default_currency: "XAF".to_string(),
+            default_country_code: default_country_code(),
+            user_agent: default_user_agent(),
+        }
+    }
+
+    #[test]
+    fn test_environment_selects_base_url_and_target_environment() {
+        let mut config = local_config(String::new());
+        config.base_url = None;
+        assert_eq!(config.base_url(), "https://sandbox.momodeveloper.mtn.com");
+        assert_eq!(config.target_environment(), "sandbox");
+        assert!(config.validate().is_ok());
+
+        config.environment = MtnEnvironment::Production;
+        assert_eq!(config.base_url(), "https://proxy.momoapi.mtn.com");
+        assert_eq!(config.target_environment(), "mtncameroon");
+        assert!(config.validate().is_ok());
+
+        config.target_environment = Some("mtnuganda".to_string());
+        assert_eq!(config.target_environment(), "mtnuganda");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_environment_mismatches_are_rejected() {
+        let mut config = local_config(String::new());
+        config.base_url = None;
+        config.target_environment = Some("mtncameroon".to_string());
+        assert!(matches!(config.validate(), Err(Error::InvalidArgument(_))));
+
+        config.environment = MtnEnvironment::Production;
+        config.target_environment = Some("sandbox".to_string());
+        assert!(matches!(config.validate(), Err(Error::InvalidArgument(_))));
+
+        config.target_environment = None;
+        config.base_url = Some("https://sandbox.momodeveloper.mtn.com".to_string());
+        assert!(matches!(config.validate(), Err(Error::InvalidArgument(_))));
+
+        config.base_url = Some("proxy.momoapi.mtn.com".to_string());
+        assert!(matches!(config.validate(), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_environment_defaults_to_sandbox_when_omitted() {
+        let config: MtnSandboxConfig = serde_json::from_value(serde_json::json!({
+            "api_key": "api-key",
+            "webhook_secret": "secret",
+            "redis_url": "redis://127.0.0.1:6379",
+            "nats_url": "nats://127.0.0.1:4222",
+            "cache_ttl_seconds": 0,
+        }))
+        .unwrap();
+
+        assert_eq!(config.environment, MtnEnvironment::Sandbox);
+        assert_eq!(config.base_url(), "https://sandbox.momodeveloper.mtn.com");
+        assert_eq!(config.target_environment(), "sandbox");
+        assert!(config.nats_enabled);
+    }
+
+    #[test]
+    fn test_event_subject_prepends_prefix() {
+        let mut config = local_config(String::new());
+        assert_eq!(
+            config.event_subject("payments.status.update"),
+            "payments.status.update"
+        );
+
+        config.nats_subject_prefix = Some("tenant-a".to_string());
+        assert_eq!(
+            config.event_subject("payments.status.update"),
+            "tenant-a.payments.status.update"
+        );
+        assert_eq!(
+            config.event_subject("payouts.status.update"),
+            "tenant-a.payouts.status.update"
+        );
+    }
+
+    #[test]
+    fn test_parse_jetstream_ack() {
+        let ack = parse_jetstream_ack(br#"{"stream":"EVENTS","seq":42}"#).unwrap();
+        assert_eq!(
+            ack,
+            JetStreamPublishAck {
+                stream: "EVENTS".to_string(),
+                seq: 42,
+                duplicate: false,
+            }
+        );
+
+        let rejected = parse_jetstream_ack(
+            br#"{"error":{"code":503,"err_code":10077,"description":"maximum messages exceeded"}}"#,
+        );
+        assert!(matches!(rejected, Err(Error::Internal(ref m)) if m.contains("maximum messages")));
+        assert!(matches!(parse_jetstream_ack(b""), Err(Error::Internal(_))));
+    }
+
+    #[tokio::test]
+    #[ignore] // This test requires running NATS (with JetStream) and Redis servers on localhost
+    async fn test_jetstream_publish_waits_for_ack_on_prefixed_subject() {
+        let prefix = format!("test-{}", cuid2());
+        let mut config = local_config(mtn_responding(202, "").await);
+        config.nats_subject_prefix = Some(prefix.clone());
+        config.nats_jetstream = true;
+        let subscriber = nats::asynk::connect(&config.nats_url).await.unwrap();
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+        let refund_req = |reference: &str| PostJournalRequest {
+            idempotency_key: reference.to_string(),
+            entries: vec![psc_provider::pb::journal::v1::JournalEntry {
+                amount: Some(xaf(2500)),
+                account: "237670000000".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Without a stream on the subject nothing acknowledges the event
+        let unacked = adapter
+            .refund(
+                &Ctx::default(),
+                refund_req(&format!("ref-unacked-{}", cuid2())),
+            )
+            .await;
+        assert!(
+            unacked.is_err(),
+            "JetStream publish succeeded without a stream"
+        );
+
+        let stream = prefix.replace('-', "_");
+        let created = subscriber
+            .request(
+                &format!("$JS.API.STREAM.CREATE.{}", stream),
+                serde_json::json!({
+                    "name": stream,
+                    "subjects": [format!("{}.>", prefix)],
+                    "storage": "memory",
+                })
+                .to_string(),
+            )
+            .await
+            .unwrap();
+        let created: serde_json::Value = serde_json::from_slice(&created.data).unwrap();
+        assert!(created.get("error").is_none(), "{}", created);
+        let updates = subscriber
+            .subscribe(&format!("{}.refunds.status.update", prefix))
+            .await
+            .unwrap();
+        subscriber.flush().await.unwrap();
+
+        let reference = format!("ref-acked-{}", cuid2());
+        adapter
+            .refund(&Ctx::default(), refund_req(&reference))
+            .await
+            .unwrap();
+
+        let message = updates.next().await.unwrap();
+        let event: serde_json::Value = serde_json::from_slice(&message.data).unwrap();
+        assert_eq!(event["reference_id"], reference.as_str());
+    }
+
+    #[tokio::test]
+    #[ignore] // This test requires a running NATS server on localhost:4222
+    async fn test_deposit_failure_publishes_failed_event() {
+        let base_url = mtn_responding(
+            500,
+            r#"{"code":"NOT_ENOUGH_FUNDS","message":"The payer does not have enough funds"}"#,
+        )
+        .await;
+        let config = local_config(base_url);
+        let subscriber = nats::asynk::connect(&config.nats_url).await.unwrap();
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+        let failures = subscriber
+            .subscribe("payments.status.failed")
+            .await
+            .unwrap();
+        subscriber.flush().await.unwrap();
+
+        let result = adapter
+            .deposit(
+                &Ctx::default(),
+                CreatePaymentRequest {
+                    payer_id: msisdn(),
+                    idempotency_key: "ref-failed-deposit".to_string(),
+                    amount: Some(xaf(10000)),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        assert!(
+            matches!(result, Err(Error::Provider { ref code, .. }) if code == "NOT_ENOUGH_FUNDS")
+        );
+        let message = failures.next().await.unwrap();
+        let event: serde_json::Value = serde_json::from_slice(&message.data).unwrap();
+        assert_eq!(event["reference_id"], "ref-failed-deposit");
+        assert_eq!(event["error"]["provider_code"], "NOT_ENOUGH_FUNDS");
+    }
+
+    #[tokio::test]
+    async fn test_deposit_succeeds_with_nats_disabled() {
+        let mut config = local_config(mtn_responding(202, "").await);
+        config.nats_enabled = false;
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+
+        let payment = adapter
+            .deposit(
+                &Ctx::default(),
+                CreatePaymentRequest {
+                    payer_id: msisdn(),
+                    idempotency_key: "ref-no-nats".to_string(),
+                    amount: Some(xaf(10000)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(payment.reference, "ref-no-nats");
+        assert_eq!(payment.status(), PaymentStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_deposit_metadata_carries_mtn_reference_and_transaction_id() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "financialTransactionId": "363440463",
+                "externalId": "ref-metadata",
+                "status": "SUCCESSFUL",
+            })))
+            .mount(&server)
+            .await;
+        let mut config = local_config(server.uri());
+        config.nats_enabled = false;
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+
+        let payment = adapter
+            .deposit(
+                &Ctx::default(),
+                CreatePaymentRequest {
+                    payer_id: msisdn(),
+                    idempotency_key: "ref-metadata".to_string(),
+                    amount: Some(xaf(10000)),
+                    metadata: [("order".to_string(), "42".to_string())].into(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(payment.metadata[MTN_REFERENCE_ID_KEY], "ref-metadata");
+        assert_eq!(
+            payment.metadata[MTN_FINANCIAL_TRANSACTION_ID_KEY],
+            "363440463"
+        );
+        assert_eq!(payment.metadata["order"], "42");
+    }
+
+    #[tokio::test]
+    async fn test_transaction_status_metadata_carries_mtn_transaction_id() {
+        let body = r#"{"financialTransactionId":"363440463","externalId":"ref-123","status":"SUCCESSFUL"}"#;
+        let mut config = local_config(mtn_responding(200, body).await);
+        config.nats_enabled = false;
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+
+        let status = adapter
+            .get_transaction(&Ctx::default(), "ref-123")
+            .await
+            .unwrap();
+
+        assert_eq!(status.state, TransactionState::Successful);
+        assert_eq!(
+            status.metadata[MTN_FINANCIAL_TRANSACTION_ID_KEY],
+            "363440463"
+        );
+        assert!(!status.metadata.contains_key(MTN_REASON_CODE_KEY));
+    }
+
+    #[test]
+    fn test_mtn_transaction_metadata_keeps_reason_code() {
+        let reason = serde_json::json!({ "code": "PAYER_NOT_FOUND", "message": "Payer not found" });
+
+        let metadata = mtn_transaction_metadata(None, Some(reason));
+
+        assert_eq!(metadata[MTN_REASON_CODE_KEY], "PAYER_NOT_FOUND");
+        assert!(!metadata.contains_key(MTN_FINANCIAL_TRANSACTION_ID_KEY));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_is_unsupported() {
+        let mut config = local_config(String::new());
+        config.base_url = None;
+        config.nats_enabled = false;
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+
+        let result = adapter.cancel(&Ctx::default(), "ref-pending").await;
+
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deposit_records_event_with_in_memory_publisher() {
+        let mut config = local_config(mtn_responding(202, "").await);
+        config.nats_enabled = false;
+        config.nats_subject_prefix = Some("tenant-a".to_string());
+        let events = Arc::new(InMemoryEventPublisher::new());
+        let adapter = MtnSandboxAdapter::new(config)
+            .await
+            .unwrap()
+            .with_event_publisher(events.clone());
+
+        let reference = format!("ref-recorded-{}", cuid2());
+        adapter
+            .deposit(
+                &Ctx::default(),
+                CreatePaymentRequest {
+                    payer_id: msisdn(),
+                    idempotency_key: reference.clone(),
+                    amount: Some(xaf(10000)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let recorded = events.events();
+        assert_eq!(recorded.len(), 1);
+        let (subject, payload) = &recorded[0];
+        assert_eq!(subject, "tenant-a.payments.status.update");
+        let event: serde_json::Value = serde_json::from_slice(payload).unwrap();
+        assert_eq!(event["transaction_type"], "deposit");
+        assert_eq!(event["reference_id"], reference.as_str());
+        assert_eq!(event["status"], "pending");
+    }
+
+    /// An adapter answering with `status` and `body`, recording events in `events`.
+    async fn recording_adapter(
+        status: u16,
+        body: &'static str,
+        events: &Arc<InMemoryEventPublisher>,
+    ) -> MtnSandboxAdapter {
+        let mut config = local_config(mtn_responding(status, body).await);
+        config.nats_enabled = false;
+        MtnSandboxAdapter::new(config)
+            .await
+            .unwrap()
+            .with_event_publisher(events.clone())
+    }
+
+    fn recorded_event(events: &InMemoryEventPublisher, index: usize) -> serde_json::Value {
+        serde_json::from_slice(&events.events()[index].1).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_deposit_event_carries_correlation_id() {
+        let events = Arc::new(InMemoryEventPublisher::new());
+        let adapter = recording_adapter(202, "", &events).await;
+        let request = |reference: &str| CreatePaymentRequest {
+            payer_id: msisdn(),
+            idempotency_key: reference.to_string(),
+            amount: Some(xaf(10000)),
+            ..Default::default()
+        };
+
+        let ctx = Ctx::default().with_correlation_id("corr-123");
+        let reference = format!("ref-correlated-{}", cuid2());
+        adapter.deposit(&ctx, request(&reference)).await.unwrap();
+        assert_eq!(recorded_event(&events, 0)["correlation_id"], "corr-123");
+
+        // Without one from the caller, a correlation id is minted
+        let reference = format!("ref-uncorrelated-{}", cuid2());
+        adapter
+            .deposit(&Ctx::default(), request(&reference))
+            .await
+            .unwrap();
+        let minted = recorded_event(&events, 1)["correlation_id"].clone();
+        assert!(minted.as_str().is_some_and(|id| !id.is_empty()));
+    }
+
+    #[tokio::test]
+    #[ignore] // This test requires a running Redis server on localhost
+    async fn test_correlation_id_is_stable_across_deposit_events() {
+        let events = Arc::new(InMemoryEventPublisher::new());
+        let failing = recording_adapter(
+            500,
+            r#"{"code":"NOT_ENOUGH_FUNDS","message":"The payer does not have enough funds"}"#,
+            &events,
+        )
+        .await;
+        let succeeding = recording_adapter(202, "", &events).await;
+        let reference = format!("ref-correlation-{}", cuid2());
+        let request = CreatePaymentRequest {
+            payer_id: msisdn(),
+            idempotency_key: reference.clone(),
+            amount: Some(xaf(10000)),
+            ..Default::default()
+        };
+
+        let ctx = Ctx::default().with_correlation_id(format!("corr-{}", cuid2()));
+        assert!(failing.deposit(&ctx, request.clone()).await.is_err());
+        // The retry comes with its own correlation id, but the reference keeps the first
+        let retry_ctx = Ctx::default().with_correlation_id("corr-retry");
+        succeeding.deposit(&retry_ctx, request).await.unwrap();
+
+        let failed = recorded_event(&events, 0);
+        let updated = recorded_event(&events, 1);
+        assert_eq!(failed["status"], "failed");
+        assert_eq!(updated["status"], "pending");
+        assert_eq!(
+            failed["correlation_id"].as_str(),
+            ctx.correlation_id.as_deref()
+        );
+        assert_eq!(updated["correlation_id"], failed["correlation_id"]);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_deposit_rejected_by_mtn_as_duplicate_is_pending() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+                "code": "RESOURCE_ALREADY_EXIST",
+                "message": "Duplicated reference id. Creation of resource failed.",
+            })))
+            .mount(&server)
+            .await;
+        let mut config = local_config(server.uri());
+        config.nats_enabled = false;
+        let events = Arc::new(InMemoryEventPublisher::new());
+        let adapter = MtnSandboxAdapter::new(config)
+            .await
+            .unwrap()
+            .with_event_publisher(events.clone());
+
+        let req = CreatePaymentRequest {
+            payer_id: msisdn(),
+            idempotency_key: format!("ref-duplicate-{}", cuid2()),
+            amount: Some(xaf(10000)),
             ..Default::default()
         };
-        let remittance_config = psc_mtn_remittance::apis::configuration::Configuration {
-            base_path: config.base_url.clone(),
-            user_agent: Some("psc-provider-gateway".to_string()),
-            client: reqwest_client.clone(),
-            // No API key directly here, it's passed as header
+        adapter.deposit(&Ctx::default(), req.clone()).await.unwrap();
+        let replayed = adapter.deposit(&Ctx::default(), req.clone()).await.unwrap();
+
+        assert_eq!(replayed.status(), PaymentStatus::Pending);
+        assert_eq!(replayed.reference, req.idempotency_key);
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(
+            requests
+                .iter()
+                .filter(|request| request.method == wiremock::http::Method::POST)
+                .count(),
+            2
+        );
+        assert!(
+            events
+                .events()
+                .iter()
+                .all(|(subject, _)| subject == "payments.status.update")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replayed_refund_rejected_by_mtn_as_duplicate_is_pending() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+                "code": "RESOURCE_ALREADY_EXIST",
+                "message": "Duplicated reference id. Creation of resource failed.",
+            })))
+            .mount(&server)
+            .await;
+        let mut config = local_config(server.uri());
+        config.nats_enabled = false;
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+
+        let req = PostJournalRequest {
+            idempotency_key: format!("ref-refund-duplicate-{}", cuid2()),
+            entries: vec![psc_provider::pb::journal::v1::JournalEntry {
+                amount: Some(xaf(2500)),
+                account: "237670000000".to_string(),
+                ..Default::default()
+            }],
             ..Default::default()
         };
-        let sandbox_provisioning_config = psc_mtn_sandbox_provisioning::apis::configuration::Configuration {
-            base_path: config.base_url.clone(),
-            user_agent: Some("psc-provider-gateway".to_string()),
-            client: reqwest_client.clone(),
+        adapter.refund(&Ctx::default(), req.clone()).await.unwrap();
+        let replayed = adapter.refund(&Ctx::default(), req.clone()).await.unwrap();
+
+        assert_eq!(replayed.status(), RefundStatus::Pending);
+        assert_eq!(replayed.provider_reference, req.idempotency_key);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_refunds_together_may_not_exceed_original_payment() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "amount": "10000",
+                "currency": "XAF",
+                "externalId": "ref-original",
+                "status": "SUCCESSFUL",
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&server)
+            .await;
+        let mut config = local_config(server.uri());
+        config.nats_enabled = false;
+        let adapter = MtnSandboxAdapter::new(config)
+            .await
+            .unwrap()
+            .with_refund_reservations(Arc::new(psc_idempotency::InMemoryReservations::new()));
+        let refund = |idempotency_key: &str, amount: i64| PostJournalRequest {
+            idempotency_key: idempotency_key.to_string(),
+            entries: vec![psc_provider::pb::journal::v1::JournalEntry {
+                amount: Some(xaf(amount)),
+                account: "237670000000".to_string(),
+                ..Default::default()
+            }],
+            metadata: [(
+                ORIGINAL_REFERENCE_KEY.to_string(),
+                "ref-original".to_string(),
+            )]
+            .into(),
             ..Default::default()
         };
 
-        let nats_client = nats::asynk::connect(&config.nats_url)
+        adapter
+            .refund(&Ctx::default(), refund("ref-refund-1", 6000))
+            .await
+            .unwrap();
+        // A replay of the first refund is not counted twice...
+        adapter
+            .refund(&Ctx::default(), refund("ref-refund-1", 6000))
             .await
-            .expect("Failed to connect to NATS server"); // TODO: Handle error properly
+            .unwrap();
+        // ...but a second 60% refund would exceed the payment
+        let result = adapter
+            .refund(&Ctx::default(), refund("ref-refund-2", 6000))
+            .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+        adapter
+            .refund(&Ctx::default(), refund("ref-refund-3", 4000))
+            .await
+            .unwrap();
 
-        MtnSandboxAdapter {
-            config,
-            client: reqwest_client,
-            collection_cfg: collection_config,
-            disbursement_cfg: disbursement_config,
-            remittance_cfg: remittance_config,
-            sandbox_provisioning_cfg: sandbox_provisioning_config,
-            nats_client,
-        }
+        let transfers = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|request| request.method == wiremock::http::Method::POST)
+            .count();
+        assert_eq!(transfers, 3);
     }
 
-    /// Helper to map MTN Collection API errors to our unified Error type.
-    fn map_mtn_collection_error<T>(e: psc_mtn_collection::apis::Error<T>) -> Error {
-        match e {
-            psc_mtn_collection::apis::Error::ResponseError(response_error) => {
-                let status_code = response_error.status.as_u16();
-                let content = response_error.content;
+    #[tokio::test]
+    async fn test_refund_assumes_default_currency_when_mtn_omits_it() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-                // Try to parse MTN's ErrorReason structure
-                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
-                    Error::Provider {
-                        code: error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_COLLECTION_ERROR_CODE".to_string()),
-                        message: error_reason.message.unwrap_or_else(|| format!("MTN Collection API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
-                    }
-                } else {
-                    // Fallback if ErrorReason cannot be parsed
-                    Error::Provider {
-                        code: format!("HTTP_{}", status_code),
-                        message: format!("MTN Collection API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
-                    }
-                }
-            }
-            psc_mtn_collection::apis::Error::Reqwest(e) => Error::Internal(format!("MTN Collection API Reqwest error: {}", e)),
-            psc_mtn_collection::apis::Error::Serde(e) => Error::Internal(format!("MTN Collection API Serde error: {}", e)),
-            psc_mtn_collection::apis::Error::Io(e) => Error::Internal(format!("MTN Collection API IO error: {}", e)),
-        }
-    }
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "amount": "100.00",
+                "externalId": "ref-original",
+                "status": "SUCCESSFUL",
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&server)
+            .await;
+        let mut config = local_config(server.uri());
+        config.nats_enabled = false;
+        config.default_currency = "USD".to_string();
+        let adapter = MtnSandboxAdapter::new(config)
+            .await
+            .unwrap()
+            .with_refund_reservations(Arc::new(psc_idempotency::InMemoryReservations::new()));
 
-    /// Helper to map MTN Disbursement API errors to our unified Error type.
-    fn map_mtn_disbursement_error<T>(e: psc_mtn_disbursement::apis::Error<T>) -> Error {
-        match e {
-            psc_mtn_disbursement::apis::Error::ResponseError(response_error) => {
-                let status_code = response_error.status.as_u16();
-                let content = response_error.content;
+        adapter
+            .refund(
+                &Ctx::default(),
+                PostJournalRequest {
+                    idempotency_key: format!("ref-refund-usd-{}", cuid2()),
+                    entries: vec![psc_provider::pb::journal::v1::JournalEntry {
+                        amount: Some(Money {
+                            amount_minor_units: 2500,
+                            currency_code: "USD".to_string(),
+                        }),
+                        account: "237670000000".to_string(),
+                        ..Default::default()
+                    }],
+                    metadata: [(
+                        ORIGINAL_REFERENCE_KEY.to_string(),
+                        "ref-original".to_string(),
+                    )]
+                    .into(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
 
-                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
-                    Error::Provider {
-                        code: error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_DISBURSEMENT_ERROR_CODE".to_string()),
-                        message: error_reason.message.unwrap_or_else(|| format!("MTN Disbursement API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
-                    }
-                } else {
-                    Error::Provider {
-                        code: format!("HTTP_{}", status_code),
-                        message: format!("MTN Disbursement API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
-                    }
-                }
-            }
-            _ => Error::Internal(format!("MTN Disbursement API error: {}", e)),
-        }
+        let requests = server.received_requests().await.unwrap();
+        let transfer = requests
+            .iter()
+            .find(|request| request.method == wiremock::http::Method::POST)
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&transfer.body).unwrap();
+        assert_eq!(body["currency"], "USD");
+        assert_eq!(body["amount"], "25.00");
     }
 
-    /// Helper to map MTN Remittance API errors to our unified Error type.
-    fn map_mtn_remittance_error<T>(e: psc_mtn_remittance::apis::Error<T>) -> Error {
-        match e {
-            psc_mtn_remittance::apis::Error::ResponseError(response_error) => {
-                let status_code = response_error.status.as_u16();
-                let content = response_error.content;
+    /// Fails the first publish, then records events like [`InMemoryEventPublisher`].
+    #[derive(Default)]
+    struct FlakyEventPublisher {
+        failed: std::sync::atomic::AtomicBool,
+        events: InMemoryEventPublisher,
+    }
 
-                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
-                    Error::Provider {
-                        code: error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_REMITTANCE_ERROR_CODE".to_string()),
-                        message: error_reason.message.unwrap_or_else(|| format!("MTN Remittance API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
-                    }
-                } else {
-                    Error::Provider {
-                        code: format!("HTTP_{}", status_code),
-                        message: format!("MTN Remittance API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
-                    }
-                }
+    #[async_trait]
+    impl EventPublisher for FlakyEventPublisher {
+        async fn publish(&self, subject: &str, payload: Vec<u8>) -> Result<()> {
+            if !self.failed.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                return Err(Error::Internal("NATS unavailable".to_string()));
             }
-            _ => Error::Internal(format!("MTN Remittance API error: {}", e)),
+            self.events.publish(subject, payload).await
         }
     }
 
-    /// Helper to map MTN Sandbox Provisioning API errors to our unified Error type.
-    fn map_mtn_sandbox_provisioning_error<T>(e: psc_mtn_sandbox_provisioning::apis::Error<T>) -> Error {
-        match e {
-            psc_mtn_sandbox_provisioning::apis::Error::ResponseError(response_error) => {
-                let status_code = response_error.status.as_u16();
-                let content = response_error.content;
+    #[tokio::test]
+    #[ignore] // This test requires a running Redis server on localhost
+    async fn test_replay_publishes_event_after_failed_publish() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-                if let Ok(error_reason) = serde_json::from_slice::<MtnErrorReason>(content.as_bytes()) {
-                    Error::Provider {
-                        code: error_reason.code.unwrap_or_else(|| "UNKNOWN_MTN_SANDBOX_PROVISIONING_ERROR_CODE".to_string()),
-                        message: error_reason.message.unwrap_or_else(|| format!("MTN Sandbox Provisioning API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes()))),
-                    }
-                } else {
-                    Error::Provider {
-                        code: format!("HTTP_{}", status_code),
-                        message: format!("MTN Sandbox Provisioning API error (HTTP {}): {}", status_code, String::from_utf8_lossy(content.as_bytes())),
-                    }
-                }
-            }
-            _ => Error::Internal(format!("MTN Sandbox Provisioning API error: {}", e)),
-        }
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(202))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+                "code": "RESOURCE_ALREADY_EXIST",
+                "message": "Duplicated reference id. Creation of resource failed.",
+            })))
+            .mount(&server)
+            .await;
+        let mut config = local_config(server.uri());
+        config.nats_enabled = false;
+        let events = Arc::new(FlakyEventPublisher::default());
+        let adapter = MtnSandboxAdapter::new(config)
+            .await
+            .unwrap()
+            .with_event_publisher(events.clone());
+
+        let req = CreatePaymentRequest {
+            payer_id: msisdn(),
+            idempotency_key: format!("ref-flaky-{}", cuid2()),
+            amount: Some(xaf(10000)),
+            ..Default::default()
+        };
+        assert!(adapter.deposit(&Ctx::default(), req.clone()).await.is_err());
+        assert!(events.events.events().is_empty());
+
+        adapter.deposit(&Ctx::default(), req.clone()).await.unwrap();
+        adapter.deposit(&Ctx::default(), req.clone()).await.unwrap();
+
+        let recorded = events.events.events();
+        assert_eq!(recorded.len(), 1, "event was not published exactly once");
+        let event: serde_json::Value = serde_json::from_slice(&recorded[0].1).unwrap();
+        assert_eq!(event["reference_id"], req.idempotency_key.as_str());
+        assert_eq!(event["status"], "pending");
     }
-}
 
-// Struct to parse MTN's error response body
-#[derive(Debug, Deserialize)]
-struct MtnErrorReason {
-    code: Option<String>,
-    message: Option<String>,
-}
+    #[tokio::test]
+    #[ignore] // This test requires running NATS and Redis servers on localhost
+    async fn test_replayed_deposit_publishes_event_once() {
+        let config = local_config(mtn_responding(202, "").await);
+        let subscriber = nats::asynk::connect(&config.nats_url).await.unwrap();
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+        let updates = subscriber
+            .subscribe("payments.status.update")
+            .await
+            .unwrap();
+        subscriber.flush().await.unwrap();
 
-#[async_trait]
-impl Provider for MtnSandboxAdapter {
-    async fn deposit(&self, _ctx: &Ctx, req: CreatePaymentRequest) -> Result<Payment> {
-        // Map unified request to MTN RequestToPay
-        let reference_id = if req.idempotency_key.is_empty() {
-            cuid2()
-        } else {
-            req.idempotency_key.clone()
-        };
-        let (amount_minor, currency_code) = match &req.amount {
-            Some(m) => (m.amount_minor_units, m.currency_code.clone()),
-            None => (0, "XAF".to_string()),
+        let req = CreatePaymentRequest {
+            payer_id: msisdn(),
+            idempotency_key: format!("ref-replayed-{}", cuid2()),
+            amount: Some(xaf(10000)),
+            ..Default::default()
         };
-        let payer_msisdn = req
-            .payer_id
-            .as_ref()
-            .map(|i| i.value.clone())
-            .unwrap_or_else(|| "unknown".to_string());
+        adapter.deposit(&Ctx::default(), req.clone()).await.unwrap();
+        adapter.deposit(&Ctx::default(), req.clone()).await.unwrap();
 
-        // Convert minor units to decimal string for MTN API (assume 2 dp)
-        let amount_str = format!("{:.2}", (amount_minor as f64) / 100.0);
+        let message = updates.next().await.unwrap();
+        let event: serde_json::Value = serde_json::from_slice(&message.data).unwrap();
+        assert_eq!(event["reference_id"], req.idempotency_key.as_str());
+        let second = tokio::time::timeout(Duration::from_millis(500), updates.next()).await;
+        assert!(second.is_err(), "event was published twice");
+    }
 
-        // Map to MTN model
-        let mtn_request_to_pay = psc_mtn_collection::models::RequestToPay {
-            amount: Some(amount_str.clone()),
-            currency: Some(currency_code.clone()),
-            external_id: Some(reference_id.clone()),
-            payer: Some(Box::new(psc_mtn_collection::models::Party { party_id_type: Some(psc_mtn_collection::models::party::PartyIdType::Msisdn), party_id: Some(payer_msisdn.clone()) })),
-            payer_message: None,
-            payee_note: Some("Payment collection".to_string()),
-        };
+    #[tokio::test]
+    #[ignore] // This test requires running NATS and Redis servers on localhost
+    async fn test_refund_returns_pending_result_and_publishes_event() {
+        let config = local_config(mtn_responding(202, "").await);
+        let subscriber = nats::asynk::connect(&config.nats_url).await.unwrap();
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+        let updates = subscriber.subscribe("refunds.status.update").await.unwrap();
+        subscriber.flush().await.unwrap();
 
-        let x_target_environment = Some(self.config.target_environment.clone());
-        let authorization = Some(format!("Bearer {}", self.config.api_key)); // Assuming API key is directly the bearer token
-        let x_callback_url: Option<&str> = None;
+        let reference = format!("ref-refund-{}", cuid2());
+        let refund = adapter
+            .refund(
+                &Ctx::default(),
+                PostJournalRequest {
+                    idempotency_key: reference.clone(),
+                    entries: vec![psc_provider::pb::journal::v1::JournalEntry {
+                        amount: Some(xaf(2500)),
+                        account: "237670000000".to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
 
-        let result = psc_mtn_collection::apis::default_api::requestto_pay(
-            &self.collection_cfg,
-            authorization.as_deref().unwrap_or(""),
-            &reference_id,
-            x_target_environment.as_deref().unwrap_or("sandbox"),
-            x_callback_url.as_deref(),
-            Some(mtn_request_to_pay),
-        )
-        .await;
+        assert_eq!(refund.provider_reference, reference);
+        assert_eq!(refund.status(), RefundStatus::Pending);
+        let message = updates.next().await.unwrap();
+        let event: serde_json::Value = serde_json::from_slice(&message.data).unwrap();
+        assert_eq!(event["reference_id"], reference.as_str());
+        assert_eq!(event["status"], "pending");
+    }
 
-        match result {
-            Ok(_) => {
-                // Return PENDING; webhook updates later
-                let payment = Payment {
-                    id: Some(Id { value: cuid2() }),
-                    amount: Some(Money { amount_minor_units: amount_minor, currency_code: currency_code.clone() }),
-                    status: PaymentStatus::Pending as i32,
-                    created_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
-                    updated_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
-                    metadata: Default::default(),
-                    reference: reference_id.clone(),
-                };
-
-                // Publish event to NATS
-                let event_payload = serde_json::json!({
-                    "transaction_type": "deposit",
-                    "reference_id": reference_id,
-                    "status": "pending",
-                    "provider": "MTN_SANDBOX",
-                    "payer": payer_msisdn,
-                    "amount": amount_str,
-                    "currency": currency_code,
-                });
-                self.nats_client.publish("payments.status.update", event_payload.to_string().into_bytes()).await
-                    .map_err(|e| Error::Internal(format!("Failed to publish NATS event: {}", e)))?;
-
-                Ok(payment)
-            }
-            Err(e) => {
-                Err(Self::map_mtn_collection_error(e))
-            }
+    #[tokio::test]
+    async fn test_mtn_calls_send_user_agent() {
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("user-agent", default_user_agent().as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "availableBalance": "100.00",
+                "currency": "XAF",
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let mut config = local_config(server.uri());
+        config.nats_enabled = false;
+        let adapter = MtnSandboxAdapter::new(config).await.unwrap();
+
+        for _ in 0..2 {
+            adapter
+                .query(
+                    &Ctx::default(),
+                    GetBalanceRequest {
+                        account_id: Some(Id {
+                            value: "acc-1".to_string(),
+                        }),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
         }
+
+        assert!(default_user_agent().ends_with(env!("CARGO_PKG_VERSION")));
     }
 
-    async fn withdraw(&self, _ctx: &Ctx, req: CreatePayoutRequest) -> Result<Payout> {
-        let reference_id = if req.idempotency_key.is_empty() {
-            cuid2()
-        } else {
-            req.idempotency_key.clone()
-        };
-        let (amount_minor, currency_code) = match &req.amount {
-            Some(m) => (m.amount_minor_units, m.currency_code.clone()),
-            None => (0, "XAF".to_string()),
-        };
-        let recipient_msisdn = req
-            .recipient_id
-            .as_ref()
-            .map(|i| i.value.clone())
-            .unwrap_or_else(|| "unknown".to_string());
+    #[tokio::test]
+    async fn test_mtn_call_past_deadline_times_out() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-        let amount_str = format!("{:.2}", (amount_minor as f64) / 100.0);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
 
-        let mtn_disbursement_request = psc_mtn_disbursement::models::Transfer {
-            amount: Some(amount_str.clone()),
-            currency: Some(currency_code.clone()),
-            external_id: Some(reference_id.clone()),
-            payee: Some(Box::new(psc_mtn_disbursement::models::Party { party_id_type: Some(psc_mtn_disbursement::models::party::PartyIdType::Msisdn), party_id: Some(recipient_msisdn.clone()) })),
-            payer_message: None,
-            payee_note: Some("Payment disbursement".to_string()),
+        let cfg = psc_mtn_collection::apis::configuration::Configuration {
+            base_path: server.uri(),
+            ..Default::default()
         };
-
-        let x_target_environment = Some(self.config.target_environment.clone());
-        let authorization = Some(format!("Bearer {}", self.config.api_key));
-        let x_callback_url: Option<&str> = None;
-
-        let result = psc_mtn_disbursement::apis::default_api::transfer(
-            &self.disbursement_cfg,
-            authorization.as_deref().unwrap_or(""),
-            &reference_id,
-            x_target_environment.as_deref().unwrap_or("sandbox"),
-            x_callback_url.as_deref(),
-            Some(mtn_disbursement_request),
+        let ctx = Ctx::default().with_timeout(Duration::from_millis(50));
+        let started = Instant::now();
+        let result = until_deadline(
+            &ctx,
+            "MTN get_account_balance",
+            psc_mtn_collection::apis::default_api::get_account_balance(
+                &cfg,
+                "Bearer token",
+                "sandbox",
+            ),
         )
         .await;
 
-        match result {
-            Ok(_) => {
-                let payout = Payout {
-                    id: Some(Id { value: cuid2() }),
-                    amount: Some(Money { amount_minor_units: amount_minor, currency_code: currency_code.clone() }),
-                    status: PayoutStatus::Pending as i32,
-                    created_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
-                    updated_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
-                    external_reference: reference_id.clone(),
-                    metadata: Default::default(),
-                };
-
-                // Publish event to NATS
-                let event_payload = serde_json::json!({
-                    "transaction_type": "withdraw",
-                    "reference_id": reference_id,
-                    "status": "pending",
-                    "provider": "MTN_SANDBOX",
-                    "recipient": recipient_msisdn,
-                    "amount": amount_str,
-                    "currency": currency_code,
-                });
-                self.nats_client
-                    .publish("payouts.status.update", event_payload.to_string().into_bytes())
-                    .await
-                    .map_err(|e| Error::Internal(format!("Failed to publish NATS event: {}", e)))?;
-
-                Ok(payout)
-            }
-            Err(e) => Err(Self::map_mtn_disbursement_error(e)),
-        }
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(1));
     }
 
-    async fn refund(&self, _ctx: &Ctx, req: PostJournalRequest) -> Result<JournalEntry> {
-        let reference_id = if req.idempotency_key.is_empty() {
-            cuid2()
-        } else {
-            req.idempotency_key.clone()
+    #[tokio::test]
+    async fn test_timed_out_transfer_status_maps_to_timeout() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+        let client = Client::builder()
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        let cfg = psc_mtn_disbursement::apis::configuration::Configuration {
+            base_path: server.uri(),
+            client: client.clone(),
+            ..Default::default()
         };
+        let err = psc_mtn_disbursement::apis::default_api::get_transfer_status(
+            &cfg,
+            "ref-timeout",
+            "Bearer token",
+            "sandbox",
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            MtnSandboxAdapter::map_mtn_disbursement_error(err),
+            Error::Timeout(_)
+        ));
 
-        let first = req.entries.get(0);
-        let (amount_minor, currency_code, account) = match first.and_then(|e| e.amount.as_ref()) {
-            Some(m) => (
-                m.amount_minor_units,
-                m.currency_code.clone(),
-                first.unwrap().account.clone(),
-            ),
-            None => (0, "XAF".to_string(), first.map(|e| e.account.clone()).unwrap_or_default()),
+        let cfg = psc_mtn_remittance::apis::configuration::Configuration {
+            base_path: server.uri(),
+            client,
+            ..Default::default()
         };
+        let err = psc_mtn_remittance::apis::default_api::get_transfer_status(
+            &cfg,
+            "ref-timeout",
+            "Bearer token",
+            "sandbox",
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            MtnSandboxAdapter::map_mtn_remittance_error(err),
+            Error::Timeout(_)
+        ));
+    }
 
-        let amount_str = format!("{:.2}", (amount_minor as f64) / 100.0);
+    #[test]
+    fn test_classify_mtn_error_codes() {
+        assert_eq!(
+            classify_mtn_error("NOT_ENOUGH_FUNDS", 500),
+            ProviderErrorKind::Terminal
+        );
+        assert_eq!(
+            classify_mtn_error("PAYER_NOT_FOUND", 404),
+            ProviderErrorKind::Terminal
+        );
+        assert_eq!(
+            classify_mtn_error("SERVICE_UNAVAILABLE", 503),
+            ProviderErrorKind::Transient
+        );
+        assert_eq!(
+            classify_mtn_error("INTERNAL_PROCESSING_ERROR", 500),
+            ProviderErrorKind::Transient
+        );
+        assert_eq!(
+            classify_mtn_error("SOMETHING_NEW", 502),
+            ProviderErrorKind::Transient
+        );
+        assert_eq!(
+            classify_mtn_error("SOMETHING_NEW", 429),
+            ProviderErrorKind::RateLimited
+        );
+        assert_eq!(
+            classify_mtn_error("SOMETHING_NEW", 400),
+            ProviderErrorKind::Terminal
+        );
+    }
 
-        let mtn_remittance_request = psc_mtn_remittance::models::Transfer {
-            amount: Some(amount_str.clone()),
-            currency: Some(currency_code.clone()),
-            external_id: Some(reference_id.clone()),
-            payee: Some(Box::new(psc_mtn_remittance::models::Party { party_id_type: Some(psc_mtn_remittance::models::party::PartyIdType::Msisdn), party_id: Some(account.clone()) })),
-            payer_message: None,
-            payee_note: Some("Payment refund/remittance".to_string()),
-        };
+    #[test]
+    fn test_signed_mtn_callback_verifies_and_tampering_fails() {
+        let body = mtn_callback_body(
+            "ref-123",
+            &xaf(10000),
+            "237670000000",
+            TransactionState::Successful,
+        );
+        let callback: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(callback["externalId"], "ref-123");
+        assert_eq!(callback["amount"], "10000");
+        assert_eq!(callback["status"], "SUCCESSFUL");
 
-        let x_target_environment = Some(self.config.target_environment.clone());
-        let authorization = Some(format!("Bearer {}", self.config.api_key));
-        let x_callback_url: Option<&str> = None;
+        let verifier = WebhookVerifier::default();
+        let signature = verifier.sign(SECRET, &body).unwrap();
+        assert!(verifier.verify(SECRET, &body, &signature).unwrap());
 
-        let result = psc_mtn_remittance::apis::default_api::transfer(
-            &self.remittance_cfg,
-            authorization.as_deref().unwrap_or(""),
-            &reference_id,
-            x_target_environment.as_deref().unwrap_or("sandbox"),
-            x_callback_url.as_deref(),
-            Some(mtn_remittance_request),
-        )
-        .await;
+        let tampered = String::from_utf8(body).unwrap().replace("\"10000\"", "\"99900\"");
+        assert!(
+            !verifier
+                .verify(SECRET, tampered.as_bytes(), &signature)
+                .unwrap()
+        );
+    }
 
-        match result {
-            Ok(_) => Ok(JournalEntry {
-                id: Some(Id { value: cuid2() }),
-                amount: Some(Money { amount_minor_units: amount_minor, currency_code }),
-                r#type: first.map(|e| e.r#type).unwrap_or_default(),
-                account,
-                posted_at: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
-                reference: reference_id,
-                metadata: first.map(|e| e.metadata.clone()).unwrap_or_default(),
-            }),
-            Err(e) => Err(Self::map_mtn_remittance_error(e)),
+    #[test]
+    fn test_sign_round_trips_for_each_verifier() {
+        for verifier in [
+            WebhookVerifier::new(WebhookAlgorithm::Sha256, SignatureEncoding::Hex),
+            WebhookVerifier::new(WebhookAlgorithm::Sha512, SignatureEncoding::Base64),
+        ] {
+            let signature = verifier.sign(SECRET, PAYLOAD).unwrap();
+            assert!(verifier.verify(SECRET, PAYLOAD, &signature).unwrap());
         }
     }
 
-    async fn query(&self, _ctx: &Ctx, req: GetBalanceRequest) -> Result<Balance> {
-        let account_id = req
-            .account_id
-            .as_ref()
-            .map(|i| i.value.clone())
-            .unwrap_or_else(|| "unknown".to_string());
+    #[test]
+    fn test_default_verifier_accepts_sha256_hex() {
+        let signature = hex::encode(sign::<Hmac<Sha256>>());
+        let verifier = WebhookVerifier::default();
 
-        let x_target_environment = Some(self.config.target_environment.clone());
-        let authorization = Some(format!("Bearer {}", self.config.api_key));
+        assert!(verifier.verify(SECRET, PAYLOAD, &signature).unwrap());
+        assert!(
+            !verifier
+                .verify(b"wrong-secret", PAYLOAD, &signature)
+                .unwrap()
+        );
+    }
 
-        let result = psc_mtn_collection::apis::default_api::get_account_balance(
-            &self.collection_cfg,
-            authorization.as_deref().unwrap_or(""),
-            x_target_environment.as_deref().unwrap_or("sandbox"),
-        )
-        .await;
+    #[test]
+    fn test_sha512_verifier() {
+        let signature = hex::encode(sign::<Hmac<Sha512>>());
+        let verifier = WebhookVerifier::new(WebhookAlgorithm::Sha512, SignatureEncoding::Hex);
 
-        match result {
-            Ok(mtn_balance) => {
-                let currency = mtn_balance
-                    .currency
-                    .clone()
-                    .unwrap_or_else(|| "XAF".to_string());
-                let available_minor = mtn_balance
-                    .available_balance
-                    .as_deref()
-                    .map(|s| {
-                        // parse decimal string assuming 2 fractional digits
-                        let d = rust_decimal::Decimal::from_str(s)
-                            .unwrap_or(rust_decimal::Decimal::ZERO);
-                        (d * rust_decimal::Decimal::from(100u64))
-                            .round()
-                            .to_i64()
-                            .unwrap_or(0)
-                    })
-                    .unwrap_or(0);
-
-                let money_available = Money { amount_minor_units: available_minor, currency_code: currency.clone() };
-                let balance = Balance {
-                    account_id: Some(Id { value: account_id }),
-                    available: Some(money_available.clone()),
-                    reserved: Some(Money { amount_minor_units: 0, currency_code: currency.clone() }),
-                    ledger: Some(money_available),
-                    as_of: Some(Timestamp { value: Some(prost_types::Timestamp { seconds: time::OffsetDateTime::now_utc().unix_timestamp(), nanos: 0 }) }),
-                    metadata: Default::default(),
-                };
-
-                Ok(balance)
-            }
-            Err(e) => Err(Self::map_mtn_collection_error(e)),
-        }
+        assert!(verifier.verify(SECRET, PAYLOAD, &signature).unwrap());
+        assert!(
+            !WebhookVerifier::default()
+                .verify(SECRET, PAYLOAD, &signature)
+                .unwrap()
+        );
     }
 
-    async fn verify_webhook(
-        &self,
-        _ctx: &Ctx,
-        payload: &[u8],
-        signature_header: Option<&str>,
-    ) -> Result<bool> {
-        use hmac::{Hmac, Mac};
-        use sha2::Sha256;
+    #[test]
+    fn test_base64_encoded_signature() {
+        use base64::Engine as _;
 
-        type HmacSha256 = Hmac<Sha256>;
+        let signature = base64::engine::general_purpose::STANDARD.encode(sign::<Hmac<Sha256>>());
+        let verifier = WebhookVerifier::new(WebhookAlgorithm::Sha256, SignatureEncoding::Base64);
 
-        let expected_signature = match signature_header {
-            Some(s) => s.to_string(),
-            None => return Ok(false), // No signature header, cannot verify
-        };
+        assert!(verifier.verify(SECRET, PAYLOAD, &signature).unwrap());
+        assert!(
+            !WebhookVerifier::default()
+                .verify(SECRET, PAYLOAD, &signature)
+                .unwrap()
+        );
+    }
+
+    fn timestamped_verifier() -> WebhookVerifier {
+        WebhookVerifier::default().with_timestamp_policy(WebhookTimestampPolicy::default())
+    }
+
+    #[test]
+    fn test_fresh_timestamped_webhook_verifies() {
+        let verifier = timestamped_verifier();
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let signature = verifier.sign_with_timestamp(SECRET, PAYLOAD, now).unwrap();
+
+        assert!(
+            verifier
+                .verify_with_timestamp(SECRET, PAYLOAD, &signature, Some(&now.to_string()))
+                .unwrap()
+        );
+        assert!(
+            !verifier
+                .verify_with_timestamp(SECRET, PAYLOAD, &signature, None)
+                .unwrap()
+        );
+    }
 
-        let key = self.config.webhook_secret.as_bytes();
-        let mut mac = HmacSha256::new_from_slice(key)
-            .map_err(|_| Error::Internal("Failed to create HMAC key".to_string()))?;
+    #[test]
+    fn test_stale_timestamped_webhook_is_rejected() {
+        let verifier = timestamped_verifier();
+        let stale = time::OffsetDateTime::now_utc().unix_timestamp()
+            - DEFAULT_WEBHOOK_TIMESTAMP_TOLERANCE_SECONDS as i64
+            - 60;
+        let signature = verifier
+            .sign_with_timestamp(SECRET, PAYLOAD, stale)
+            .unwrap();
 
-        mac.update(payload);
-        let result = mac.finalize();
-        let signature_bytes = result.into_bytes();
+        assert!(
+            !verifier
+                .verify_with_timestamp(SECRET, PAYLOAD, &signature, Some(&stale.to_string()))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_tampered_webhook_timestamp_is_rejected() {
+        let verifier = timestamped_verifier();
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let signature = verifier.sign_with_timestamp(SECRET, PAYLOAD, now).unwrap();
+
+        // Still within the tolerance, but not the timestamp that was signed
+        let tampered = (now - 1).to_string();
+        assert!(
+            !verifier
+                .verify_with_timestamp(SECRET, PAYLOAD, &signature, Some(&tampered))
+                .unwrap()
+        );
+        assert!(
+            !verifier
+                .verify_with_timestamp(SECRET, PAYLOAD, &signature, Some("not-a-timestamp"))
+                .unwrap()
+        );
+    }
 
-        let actual_signature = hex::encode(signature_bytes);
+    #[test]
+    fn test_verifier_without_timestamp_policy_ignores_timestamp() {
+        let verifier = WebhookVerifier::default();
+        let signature = verifier.sign(SECRET, PAYLOAD).unwrap();
 
-        // Simple comparison for now. In a real scenario, you might need to parse the header
-        // (e.g., "sha256=<signature>") and handle timing attacks.
-        Ok(actual_signature == expected_signature)
+        assert!(
+            verifier
+                .verify_with_timestamp(SECRET, PAYLOAD, &signature, None)
+                .unwrap()
+        );
+        assert!(
+            verifier
+                .verify_with_timestamp(SECRET, PAYLOAD, &signature, Some("0"))
+                .unwrap()
+        );
     }
 }